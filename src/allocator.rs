@@ -4,7 +4,7 @@ use alloc::heap::{Alloc, AllocErr, Heap, Layout};
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::cmp::min;
+use std::cmp::{max, min};
 
 use super::error::DBError;
 
@@ -195,23 +195,38 @@ impl<'a> ChainedArena<'a> {
 
         let new_size = if let Some(ref mut arena) = self.chunks.last_mut() {
             if arena.len() - self.pos >= size {
-                let ptr = arena.as_mut_ptr().offset(size as isize);
+                let ptr = arena.as_mut_ptr().offset(self.pos as isize);
                 self.pos += size;
                 return Ok(ptr);
             }
 
-            min(arena.len() * 2, self.max_size)
+            max(min(arena.len() * 2, self.max_size), size)
         } else {
-            self.min_size
+            max(self.min_size, size)
         };
 
         let new_arena = make_arena(self.parent, new_size)?;
         let ptr = new_arena.as_mut_ptr();
 
         self.chunks.push(new_arena);
+        self.pos = size;
         Ok(ptr)
     }
 
+    /// Total size of the chunks allocated by the arena
+    pub fn allocated(&self) -> usize {
+        self.chunks.iter()
+            .map(|chunk| chunk.len())
+            .sum()
+    }
+
+    /// Memory chunks of the arena, in allocation order
+    pub fn chunks(&self) -> Vec<&[u8]> {
+        self.chunks.iter()
+            .map(|chunk| &chunk[..])
+            .collect()
+    }
+
     pub fn append(&mut self, data: &[u8]) -> Result<ArenaAppend, DBError> {
         unsafe {
             let ptr = self.allocate(data.len())?;
@@ -231,3 +246,38 @@ impl<'a> Drop for ChainedArena<'a> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_spans_chunks() {
+        let mut arena = ChainedArena::new(&GLOBAL, 16, 64);
+
+        let first = arena.append(b"0123456789").unwrap();
+        let second = arena.append(b"abcdefghij").unwrap();
+        let third = arena.append(b"xyz").unwrap();
+        assert_eq!((first.0, second.0, third.0), (1, 2, 2));
+
+        // Values are placed one after the other in a chunk, and don't overlap
+        assert_eq!(third.1 as usize, second.1 as usize + 10);
+
+        // Chunks grow 2X, or to fit the value
+        let large = arena.append(&[7; 40]).unwrap();
+        assert_eq!(large.0, 3);
+        assert_eq!(arena.allocated(), 16 + 32 + 64);
+
+        unsafe {
+            assert_eq!(slice::from_raw_parts(first.1, 10), b"0123456789");
+            assert_eq!(slice::from_raw_parts(second.1, 10), b"abcdefghij");
+            assert_eq!(slice::from_raw_parts(third.1, 3), b"xyz");
+            assert_eq!(slice::from_raw_parts(large.1, 40), &[7; 40][..]);
+        }
+
+        match arena.append(&[0; 65]) {
+            Err(DBError::MemoryLimit) => {},
+            _ => panic!("value larger than the maximum chunk size"),
+        }
+    }
+}
@@ -0,0 +1,978 @@
+// vim : set ts=4 sw=4 et :
+
+//! Arrow C Data Interface (`ArrowSchema` / `ArrowArray`) export and import of views, for handing
+//! data to and from other Arrow based libraries in the same process.
+//!
+//! A view is exported as a STRUCT array with a child array per column, the Arrow convention for
+//! record batches. Buffers with the same layout in both formats are shared instead of copied:
+//! fixed width values (integers, floats, DATE, DECIMAL, FIXED_BINARY, UUID) in both directions and
+//! TEXT / BLOB bytes on import. The rest is converted:
+//!
+//! - Null vectors (set bits are NULL) become validity bitmaps (set bits are valid) and back.
+//! - BOOLEAN rows are a byte each here, a bit each in Arrow.
+//! - TEXT / BLOB `RawData` rows become offset and data buffers, and back.
+//! - LIST `ListEntry` rows become an offset buffer, and back.
+//! - TIMESTAMP is exported as `tsu:UTC`, dropping the display offset. INTERVAL is exported as
+//!   `tin` (month, day, nanoseconds).
+//!
+//! UUID columns are exported as `w:16` with the `arrow.uuid` extension type. Only little endian
+//! platforms are supported.
+
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use ::allocator::Allocator;
+use ::bitmaps::OwnedBitmap;
+use ::block::{Block, Encoding, RefColumn, View, alias_column, column_nulls, column_row_data};
+use ::block::{list_row_data, varlen_at};
+use ::error::DBError;
+use ::kernel;
+use ::row::{RowOffset, RowRange};
+use ::schema::{Attribute, Schema, TypeModifiers};
+use ::selection;
+use ::types::{self, IntervalValue, ListEntry, RawData, TimestampValue, Type};
+
+/// `ArrowSchema::flags` bit of nullable fields
+pub const ARROW_FLAG_NULLABLE: i64 = 2;
+
+const EXTENSION_NAME: &'static str = "ARROW:extension:name";
+const UUID_EXTENSION: &'static str = "arrow.uuid";
+
+/// Type of an array, as defined by the Arrow C Data Interface
+#[repr(C)]
+pub struct ArrowSchema {
+    pub format: *const c_char,
+    pub name: *const c_char,
+    pub metadata: *const c_char,
+    pub flags: i64,
+    pub n_children: i64,
+    pub children: *mut *mut ArrowSchema,
+    pub dictionary: *mut ArrowSchema,
+    pub release: Option<unsafe extern "C" fn(*mut ArrowSchema)>,
+    pub private_data: *mut c_void,
+}
+
+/// Data of an array, as defined by the Arrow C Data Interface
+#[repr(C)]
+pub struct ArrowArray {
+    pub length: i64,
+    pub null_count: i64,
+    pub offset: i64,
+    pub n_buffers: i64,
+    pub n_children: i64,
+    pub buffers: *mut *const c_void,
+    pub children: *mut *mut ArrowArray,
+    pub dictionary: *mut ArrowArray,
+    pub release: Option<unsafe extern "C" fn(*mut ArrowArray)>,
+    pub private_data: *mut c_void,
+}
+
+/// Arrow `tin` (month_day_nano) interval value
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MonthDayNano {
+    months: i32,
+    days: i32,
+    nanos: i64,
+}
+
+/// Structs owning a `release` callback
+trait Release {
+    /// Call the release callback, unless already released (or moved)
+    unsafe fn release_data(&mut self);
+}
+
+impl Release for ArrowSchema {
+    unsafe fn release_data(&mut self) {
+        if let Some(release) = self.release {
+            release(self);
+        }
+    }
+}
+
+impl Release for ArrowArray {
+    unsafe fn release_data(&mut self) {
+        if let Some(release) = self.release {
+            release(self);
+        }
+    }
+}
+
+/// Build `count` children, releasing the ones already built on error
+fn build_children<T, F>(count: usize, mut build: F) -> Result<Vec<T>, DBError>
+    where T: Release, F: FnMut(usize) -> Result<T, DBError>
+{
+    let mut out = Vec::with_capacity(count);
+
+    for pos in 0 .. count {
+        match build(pos) {
+            Ok(child) => out.push(child),
+            Err(err) => {
+                for mut child in out {
+                    unsafe { child.release_data() };
+                }
+                return Err(err)
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Arrow format string of an attribute type
+fn arrow_format(attr: &Attribute) -> String {
+    let format = match attr.dtype {
+        Type::UINT8 => "C",
+        Type::UINT16 => "S",
+        Type::UINT32 => "I",
+        Type::UINT64 => "L",
+        Type::INT8 => "c",
+        Type::INT16 => "s",
+        Type::INT32 => "i",
+        Type::INT64 => "l",
+        Type::FLOAT32 => "f",
+        Type::FLOAT64 => "g",
+        Type::BOOLEAN => "b",
+        Type::DATE => "tdD",
+        Type::TIMESTAMP => "tsu:UTC",
+        Type::INTERVAL => "tin",
+        Type::DECIMAL =>
+            return format!("d:{},{}", attr.modifiers.precision, attr.modifiers.scale),
+        Type::FIXED_BINARY => return format!("w:{}", attr.modifiers.width),
+        Type::UUID => "w:16",
+        Type::TEXT => "u",
+        Type::BLOB => "z",
+        Type::LIST => "+l",
+        Type::STRUCT => "+s",
+    };
+
+    String::from(format)
+}
+
+/// Type (and modifiers) of an Arrow format string. UUID is `w:16` with the `arrow.uuid`
+/// extension type.
+fn parse_format(format: &str, extension: Option<&str>) -> Result<(Type, TypeModifiers), DBError> {
+    let unknown = || DBError::UnknownType(format!("(arrow format: {})", format));
+
+    let dtype = match format {
+        "C" => Type::UINT8,
+        "S" => Type::UINT16,
+        "I" => Type::UINT32,
+        "L" => Type::UINT64,
+        "c" => Type::INT8,
+        "s" => Type::INT16,
+        "i" => Type::INT32,
+        "l" => Type::INT64,
+        "f" => Type::FLOAT32,
+        "g" => Type::FLOAT64,
+        "b" => Type::BOOLEAN,
+        "tdD" => Type::DATE,
+        "tin" => Type::INTERVAL,
+        "u" => Type::TEXT,
+        "z" => Type::BLOB,
+        "+l" => Type::LIST,
+        "+s" => Type::STRUCT,
+        "w:16" if extension == Some(UUID_EXTENSION) => Type::UUID,
+        _ if format.starts_with("tsu:") => Type::TIMESTAMP,
+        _ if format.starts_with("w:") => {
+            let width = format[2 ..].parse::<u32>().map_err(|_| unknown())?;
+            let modifiers = TypeModifiers::checked_fixed(width).map_err(|_| unknown())?;
+            return Ok((Type::FIXED_BINARY, modifiers))
+        }
+        _ if format.starts_with("d:") => {
+            // Only 128 bit decimals: "d:precision,scale" or "d:precision,scale,128"
+            let parts: Vec<&str> = format[2 ..].split(',').collect();
+            if parts.len() < 2 || parts.len() > 3 || parts.get(2).map_or(false, |w| *w != "128") {
+                return Err(unknown())
+            }
+
+            let precision = parts[0].parse::<u32>().map_err(|_| unknown())?;
+            let scale = parts[1].parse::<u32>().map_err(|_| unknown())?;
+            let modifiers = TypeModifiers::checked_decimal(precision, scale)
+                .map_err(|_| unknown())?;
+            return Ok((Type::DECIMAL, modifiers))
+        }
+        _ => return Err(unknown()),
+    };
+
+    Ok((dtype, Default::default()))
+}
+
+/// Arrow metadata encoding: native endian `i32` pair count, then length prefixed keys and values
+fn encode_metadata(pairs: &[(&str, &str)]) -> Vec<u8> {
+    let push_i32 = |out: &mut Vec<u8>, value: usize| {
+        let bytes: [u8; 4] = unsafe { mem::transmute(value as i32) };
+        out.extend_from_slice(&bytes);
+    };
+
+    let mut out = Vec::new();
+    push_i32(&mut out, pairs.len());
+
+    for &(key, value) in pairs {
+        push_i32(&mut out, key.len());
+        out.extend_from_slice(key.as_bytes());
+        push_i32(&mut out, value.len());
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    out
+}
+
+unsafe fn read_i32(pos: &mut *const u8) -> i32 {
+    let value = ptr::read_unaligned(*pos as *const i32);
+    *pos = pos.offset(4);
+    value
+}
+
+unsafe fn read_bytes<'a>(pos: &mut *const u8) -> &'a [u8] {
+    let len = read_i32(pos) as usize;
+    let out = slice::from_raw_parts(*pos, len);
+    *pos = pos.offset(len as isize);
+    out
+}
+
+/// Value of a key in encoded Arrow metadata
+unsafe fn metadata_value(metadata: *const c_char, key: &str) -> Option<String> {
+    if metadata.is_null() {
+        return None
+    }
+
+    let mut pos = metadata as *const u8;
+    let count = read_i32(&mut pos);
+
+    for _ in 0 .. count {
+        let k = read_bytes(&mut pos);
+        let v = read_bytes(&mut pos);
+
+        if k == key.as_bytes() {
+            return Some(String::from_utf8_lossy(v).into_owned())
+        }
+    }
+
+    None
+}
+
+/// Strings, metadata and children of an exported `ArrowSchema`
+struct SchemaData {
+    format: CString,
+    name: CString,
+    metadata: Option<Vec<u8>>,
+    children: Vec<*mut ArrowSchema>,
+}
+
+unsafe extern "C" fn release_schema(schema: *mut ArrowSchema) {
+    if schema.is_null() || (*schema).release.is_none() {
+        return
+    }
+
+    let data = Box::from_raw((*schema).private_data as *mut SchemaData);
+    for child in &data.children {
+        Box::from_raw(*child).release_data();
+    }
+
+    (*schema).release = None;
+}
+
+fn make_schema(format: &str, name: &str, metadata: Option<Vec<u8>>, flags: i64,
+               children: Vec<ArrowSchema>) -> Result<ArrowSchema, DBError>
+{
+    let c_string = |text: &str| {
+        CString::new(text).map_err(|_| DBError::ValueParse(format!("{:?} (contains NUL)", text)))
+    };
+
+    let (format, name) = match (c_string(format), c_string(name)) {
+        (Ok(format), Ok(name)) => (format, name),
+        (Err(err), _) | (_, Err(err)) => {
+            for mut child in children {
+                unsafe { child.release_data() };
+            }
+            return Err(err)
+        }
+    };
+
+    let mut data = Box::new(SchemaData {
+        format: format,
+        name: name,
+        metadata: metadata,
+        children: children.into_iter().map(|c| Box::into_raw(Box::new(c))).collect(),
+    });
+
+    Ok(ArrowSchema {
+        format: data.format.as_ptr(),
+        name: data.name.as_ptr(),
+        metadata: data.metadata.as_ref().map_or(ptr::null(), |m| m.as_ptr() as *const c_char),
+        flags: flags,
+        n_children: data.children.len() as i64,
+        children: data.children.as_mut_ptr(),
+        dictionary: ptr::null_mut(),
+        release: Some(release_schema),
+        private_data: Box::into_raw(data) as *mut c_void,
+    })
+}
+
+fn export_attribute(attr: &Attribute) -> Result<ArrowSchema, DBError> {
+    let children = build_children(attr.children.len(), |pos| {
+        export_attribute(&attr.children[pos])
+    })?;
+
+    let metadata = if attr.dtype == Type::UUID {
+        Some(encode_metadata(&[(EXTENSION_NAME, UUID_EXTENSION)]))
+    } else {
+        None
+    };
+
+    let flags = if attr.nullable { ARROW_FLAG_NULLABLE } else { 0 };
+    make_schema(&arrow_format(attr), &attr.name, metadata, flags, children)
+}
+
+/// Export a schema as an `ArrowSchema` of a STRUCT with a field per attribute. The result owns
+/// all of its data, the consumer calls `release` when done with it.
+pub fn export_schema(schema: &Schema) -> Result<ArrowSchema, DBError> {
+    let attrs: Vec<&Attribute> = schema.iter().collect();
+    let children = build_children(attrs.len(), |pos| export_attribute(attrs[pos]))?;
+    make_schema("+s", "", None, 0, children)
+}
+
+/// Buffers and children of an exported `ArrowArray`
+struct ArrayData {
+    buffers: Vec<*const c_void>,
+    children: Vec<*mut ArrowArray>,
+    /// Buffers converted on export
+    #[allow(dead_code)]
+    owned: Vec<Box<Any>>,
+    /// Materialized data the buffers point into, shared by all the arrays exported from it
+    #[allow(dead_code)]
+    block: Option<Arc<Block<'static>>>,
+}
+
+unsafe extern "C" fn release_array(array: *mut ArrowArray) {
+    if array.is_null() || (*array).release.is_none() {
+        return
+    }
+
+    let data = Box::from_raw((*array).private_data as *mut ArrayData);
+    for child in &data.children {
+        Box::from_raw(*child).release_data();
+    }
+
+    (*array).release = None;
+}
+
+impl ArrayData {
+    fn new(block: Option<Arc<Block<'static>>>) -> ArrayData {
+        ArrayData { buffers: Vec::new(), children: Vec::new(), owned: Vec::new(), block: block }
+    }
+
+    fn push_owned<T: Any>(&mut self, buffer: Vec<T>) {
+        self.buffers.push(buffer.as_ptr() as *const c_void);
+        self.owned.push(Box::new(buffer));
+    }
+
+    fn into_array(self, length: RowOffset, null_count: usize, children: Vec<ArrowArray>)
+        -> ArrowArray
+    {
+        let mut data = Box::new(self);
+        data.children = children.into_iter().map(|c| Box::into_raw(Box::new(c))).collect();
+
+        ArrowArray {
+            length: length as i64,
+            null_count: null_count as i64,
+            offset: 0,
+            n_buffers: data.buffers.len() as i64,
+            n_children: data.children.len() as i64,
+            buffers: data.buffers.as_mut_ptr(),
+            children: data.children.as_mut_ptr(),
+            dictionary: ptr::null_mut(),
+            release: Some(release_array),
+            private_data: Box::into_raw(data) as *mut c_void,
+        }
+    }
+}
+
+/// Plain encoded column, including the nested columns
+fn is_plain<'c>(col: &'c RefColumn<'c>) -> bool {
+    col.encoding() == Encoding::Plain
+        && (0 .. col.attribute().children.len())
+            .all(|pos| col.child(pos).map_or(true, |child| is_plain(child)))
+}
+
+/// Export the (selected) rows of a view as an `ArrowArray` of a STRUCT with a child array per
+/// column, matching `export_schema`.
+///
+/// Fixed width column data is shared with the view, which makes this unsafe: the view has to
+/// outlive the array (until its `release` is called). Views with a selection or columns that
+/// aren't plain encoded are compacted into a `Block` first; the block is owned by the array.
+pub unsafe fn export_view<'v>(alloc: &'static Allocator, view: &'v View<'v>)
+    -> Result<ArrowArray, DBError>
+{
+    let mut plain = view.selection().is_none();
+
+    for pos in 0 .. view.schema().count() {
+        let col = view.column(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))?;
+        plain = plain && is_plain(col);
+    }
+
+    if plain {
+        return export_struct(alloc, view, None)
+    }
+
+    let rows: Vec<RowOffset> = selection::selected_rows(view.selection(), view.rows()).collect();
+    let block = Arc::new(kernel::take(alloc, view, &rows)?);
+    export_struct(alloc, &*block, Some(block.clone()))
+}
+
+unsafe fn export_struct<'v>(alloc: &'static Allocator, view: &'v View<'v>,
+                            block: Option<Arc<Block<'static>>>) -> Result<ArrowArray, DBError>
+{
+    let rows = view.rows();
+
+    let children = build_children(view.schema().count(), |pos| {
+        let col = view.column(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))?;
+        export_column(alloc, col, rows, block.clone())
+    })?;
+
+    // Rows of a record batch are never NULL
+    let mut data = ArrayData::new(block);
+    data.buffers.push(ptr::null());
+    Ok(data.into_array(rows, 0, children))
+}
+
+/// Validity buffer of the first `rows` rows, returns the null count
+fn export_validity<'c>(col: &'c RefColumn<'c>, rows: RowOffset, data: &mut ArrayData) -> usize {
+    let nulls = column_nulls(col);
+    let count = if nulls.is_empty() { 0 } else { nulls.slice(0, rows).count_ones() };
+
+    if count == 0 {
+        data.buffers.push(ptr::null());
+    } else {
+        data.push_owned(nulls.slice(0, rows).not().into_words());
+    }
+
+    count
+}
+
+unsafe fn export_column<'c>(alloc: &'static Allocator, col: &'c RefColumn<'c>, rows: RowOffset,
+                            block: Option<Arc<Block<'static>>>) -> Result<ArrowArray, DBError>
+{
+    let attr = col.attribute();
+
+    if col.encoding() != Encoding::Plain {
+        return Err(DBError::ColumnEncoding(attr.name.clone()))
+    }
+
+    let mut data = ArrayData::new(block.clone());
+    let null_count = export_validity(col, rows, &mut data);
+    let nulls = column_nulls(col);
+    let mut children = Vec::new();
+
+    match attr.dtype {
+        Type::BOOLEAN => {
+            let values = column_row_data::<types::Boolean>(col)?.values;
+            data.push_owned(OwnedBitmap::from_bools(&values[.. rows]).into_words());
+        }
+        Type::TIMESTAMP => {
+            let values = column_row_data::<types::Timestamp>(col)?.values;
+            data.push_owned(values[.. rows].iter().map(|ts| ts.micros).collect::<Vec<i64>>());
+        }
+        Type::INTERVAL => {
+            let values = column_row_data::<types::Interval>(col)?.values;
+            let mut out = Vec::with_capacity(rows);
+
+            for (row, iv) in values[.. rows].iter().enumerate() {
+                let nanos = if nulls.is_set(row) { Some(0) } else { iv.micros.checked_mul(1000) };
+                let nanos = nanos.ok_or(
+                    DBError::NumericOverflow(format!("{} (INTERVAL nanoseconds)", attr.name)))?;
+
+                out.push(MonthDayNano { months: iv.months, days: iv.days, nanos: nanos });
+            }
+
+            data.push_owned(out);
+        }
+        Type::TEXT | Type::BLOB => {
+            let mut offsets = Vec::with_capacity(rows + 1);
+            let mut bytes = Vec::new();
+            offsets.push(0 as i32);
+
+            for row in 0 .. rows {
+                if !nulls.is_set(row) {
+                    bytes.extend_from_slice(varlen_at(col, row));
+                }
+
+                offsets.push(arrow_offset(bytes.len(), attr)?);
+            }
+
+            data.push_owned(offsets);
+            data.push_owned(bytes);
+        }
+        Type::LIST => {
+            let lists = list_row_data(col)?;
+            let mut offsets = Vec::with_capacity(rows + 1);
+            let mut indices = Vec::new();
+            offsets.push(0 as i32);
+
+            for row in 0 .. rows {
+                if !lists.nulls.is_set(row) {
+                    indices.extend(lists.range(row));
+                }
+
+                offsets.push(arrow_offset(indices.len(), attr)?);
+            }
+
+            data.push_owned(offsets);
+            let elements = lists.elements;
+            children.push(export_elements(alloc, elements, col.element_rows(), &indices, block)?);
+        }
+        Type::STRUCT => {
+            children = build_children(attr.children.len(), |pos| {
+                let field = col.child(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+                export_column(alloc, field, rows, block.clone())
+            })?;
+        }
+        _ => data.buffers.push(col.rows_ptr() as *const c_void),
+    }
+
+    Ok(data.into_array(rows, null_count, children))
+}
+
+fn arrow_offset(offset: usize, attr: &Attribute) -> Result<i32, DBError> {
+    if offset > i32::max_value() as usize {
+        Err(DBError::NumericOverflow(format!("{} (arrow offset)", attr.name)))
+    } else {
+        Ok(offset as i32)
+    }
+}
+
+/// Elements of the exported LIST rows, in order. Shared when they're a contiguous range of the
+/// element column (of `rows` rows in use), otherwise gathered into a new block owned by the array.
+unsafe fn export_elements<'c>(alloc: &'static Allocator, elements: &'c RefColumn<'c>,
+                              rows: RowOffset, indices: &[RowOffset],
+                              block: Option<Arc<Block<'static>>>) -> Result<ArrowArray, DBError>
+{
+    if indices.windows(2).all(|pair| pair[1] == pair[0] + 1) {
+        let start = indices.first().cloned().unwrap_or(0);
+        let range = RowRange { offset: start, rows: indices.len() };
+        let alias = alias_column(elements, Some(range))?;
+        return export_column(alloc, &alias, indices.len(), block)
+    }
+
+    let taken = Arc::new(kernel::take_column(alloc, elements, rows, indices)?);
+    export_column(alloc, &taken[0], indices.len(), Some(taken.clone()))
+}
+
+/// Import the type of a field (and its children)
+unsafe fn import_attribute(schema: &ArrowSchema) -> Result<Attribute, DBError> {
+    let name = if schema.name.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(schema.name).to_string_lossy().into_owned()
+    };
+
+    if !schema.dictionary.is_null() {
+        return Err(DBError::ColumnEncoding(name))
+    }
+
+    let format = CStr::from_ptr(schema.format).to_string_lossy().into_owned();
+    let extension = metadata_value(schema.metadata, EXTENSION_NAME);
+    let (dtype, modifiers) = parse_format(&format, extension.as_ref().map(|e| e.as_str()))?;
+
+    let mut children = Vec::with_capacity(schema.n_children as usize);
+    for pos in 0 .. schema.n_children as isize {
+        children.push(import_attribute(&**schema.children.offset(pos))?);
+    }
+
+    if dtype == Type::LIST && children.len() != 1 {
+        return Err(DBError::AttributeMissing(format!("{}.element", name)))
+    }
+
+    Ok(Attribute {
+        name: name,
+        nullable: schema.flags & ARROW_FLAG_NULLABLE != 0,
+        dtype: dtype,
+        modifiers: modifiers,
+        children: children,
+    })
+}
+
+/// Import the schema of a STRUCT `ArrowSchema` (eg. from `export_schema`), with an attribute per
+/// field. The `ArrowSchema` isn't released.
+pub unsafe fn import_schema(schema: &ArrowSchema) -> Result<Schema, DBError> {
+    let top = import_attribute(schema)?;
+
+    if top.dtype != Type::STRUCT {
+        return Err(DBError::AttributeType(top.name))
+    }
+
+    Schema::from_vec(top.children)
+}
+
+/// Read-only view of an imported `ArrowArray`. Owns (and releases when dropped) the array.
+pub struct ArrowView {
+    schema: Schema,
+    columns: Vec<ImportedColumn>,
+    rows: RowOffset,
+    array: ArrowArray,
+}
+
+/// Column of an `ArrowView`. Row data either points into the Arrow buffers or into a converted
+/// copy.
+struct ImportedColumn {
+    attr: Attribute,
+    rows: RowOffset,
+    raw: *const u8,
+    raw_len: usize,
+    #[allow(dead_code)]
+    converted: Option<Box<Any>>,
+    nulls: OwnedBitmap,
+    children: Vec<ImportedColumn>,
+}
+
+impl ImportedColumn {
+    fn set_converted<T: Any>(&mut self, values: Vec<T>) {
+        self.raw = values.as_ptr() as *const u8;
+        self.raw_len = values.len() * mem::size_of::<T>();
+        self.converted = Some(Box::new(values));
+    }
+}
+
+/// Import a STRUCT `ArrowArray` of type `schema` (eg. from `export_view`) as a view with a column
+/// per field. The view takes ownership of the array and releases it when dropped.
+///
+/// Unsafe because the array has to follow the C Data Interface (valid pointers, buffers as large as
+/// the type and length require). The validity of the top level array is ignored.
+pub unsafe fn import_view(schema: &ArrowSchema, array: ArrowArray) -> Result<ArrowView, DBError> {
+    // The view owns the array from here on, so it's released on error as well
+    let mut view = ArrowView {
+        schema: Schema::default(),
+        columns: Vec::new(),
+        rows: array.length as RowOffset,
+        array: array,
+    };
+
+    view.schema = import_schema(schema)?;
+
+    let count = view.schema.count();
+    if view.array.n_children as usize != count {
+        return Err(DBError::ExpressionInputCount(
+            format!("{} != {}", view.array.n_children, count)))
+    }
+
+    let mut columns = Vec::with_capacity(count);
+    {
+        let top = &view.array;
+        for (pos, attr) in view.schema.iter().enumerate() {
+            let child = &**top.children.offset(pos as isize);
+            columns.push(import_column(attr, child, top.offset as usize, view.rows)?);
+        }
+    }
+
+    view.columns = columns;
+    Ok(view)
+}
+
+/// Typed view of an Arrow buffer starting at element `offset`
+unsafe fn buffer_slice<'a, T>(buffer: *const u8, offset: usize, len: usize, attr: &Attribute)
+    -> Result<&'a [T], DBError>
+{
+    if len == 0 {
+        Ok(&[])
+    } else if buffer.is_null() {
+        Err(DBError::ValueParse(format!("{} (missing arrow buffer)", attr.name)))
+    } else {
+        Ok(slice::from_raw_parts((buffer as *const T).offset(offset as isize), len))
+    }
+}
+
+/// Bits `offset .. offset + rows` of an Arrow bitmap buffer
+unsafe fn buffer_bits<'a>(buffer: *const u8, offset: usize, rows: RowOffset, attr: &Attribute)
+    -> Result<&'a [u8], DBError>
+{
+    let bytes = if rows == 0 { 0 } else { (offset + rows + 7) / 8 };
+    buffer_slice::<u8>(buffer, 0, bytes, attr)
+}
+
+fn arrow_bit(bits: &[u8], idx: usize) -> bool {
+    bits[idx / 8] >> (idx % 8) & 1 != 0
+}
+
+unsafe fn import_validity(attr: &Attribute, validity: *const u8, offset: usize, rows: RowOffset)
+    -> Result<OwnedBitmap, DBError>
+{
+    let mut nulls = OwnedBitmap::new(if attr.nullable { rows } else { 0 }, false);
+
+    if validity.is_null() {
+        return Ok(nulls)
+    }
+
+    let bits = buffer_bits(validity, offset, rows, attr)?;
+
+    for row in 0 .. rows {
+        if !arrow_bit(bits, offset + row) {
+            if !attr.nullable {
+                return Err(DBError::make_column_not_nullable(attr.name.clone()))
+            }
+            nulls.as_mut_bitmap().set(row, true);
+        }
+    }
+
+    Ok(nulls)
+}
+
+fn check_children(attr: &Attribute, array: &ArrowArray) -> Result<(), DBError> {
+    if array.n_children as usize != attr.children.len() {
+        return Err(DBError::ExpressionInputCount(
+            format!("{} ({} != {} children)", attr.name, array.n_children, attr.children.len())))
+    }
+
+    Ok(())
+}
+
+/// Import `rows` rows of an array starting at row `skip` (the offset of the parent STRUCT)
+unsafe fn import_column(attr: &Attribute, array: &ArrowArray, skip: usize, rows: RowOffset)
+    -> Result<ImportedColumn, DBError>
+{
+    if !array.dictionary.is_null() {
+        return Err(DBError::ColumnEncoding(attr.name.clone()))
+    }
+
+    if (array.length as usize) < skip + rows {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    let offset = array.offset as usize + skip;
+    let buffer = |idx: isize| -> *const u8 {
+        if idx < array.n_buffers as isize {
+            *array.buffers.offset(idx) as *const u8
+        } else {
+            ptr::null()
+        }
+    };
+
+    let mut col = ImportedColumn {
+        attr: attr.clone(),
+        rows: rows,
+        raw: ptr::null(),
+        raw_len: 0,
+        converted: None,
+        nulls: import_validity(attr, buffer(0), offset, rows)?,
+        children: Vec::new(),
+    };
+
+    match attr.dtype {
+        Type::BOOLEAN => {
+            let bits = buffer_bits(buffer(1), offset, rows, attr)?;
+            let values = (0 .. rows).map(|row| arrow_bit(bits, offset + row)).collect();
+            col.set_converted::<bool>(values);
+        }
+        Type::TIMESTAMP => {
+            let micros = buffer_slice::<i64>(buffer(1), offset, rows, attr)?;
+            let values = micros.iter().map(|us| TimestampValue::utc(*us)).collect();
+            col.set_converted::<TimestampValue>(values);
+        }
+        Type::INTERVAL => {
+            let values = buffer_slice::<MonthDayNano>(buffer(1), offset, rows, attr)?;
+            col.set_converted::<IntervalValue>(values.iter()
+                .map(|iv| IntervalValue::new(iv.months, iv.days, iv.nanos / 1000))
+                .collect());
+        }
+        Type::TEXT | Type::BLOB => {
+            let offsets = buffer_slice::<i32>(buffer(1), offset, rows + 1, attr)?;
+            let bytes = buffer(2);
+
+            col.set_converted::<RawData>(offsets.windows(2)
+                .map(|pair| RawData {
+                    data: bytes.offset(pair[0] as isize) as *mut u8,
+                    size: (pair[1] - pair[0]) as usize,
+                })
+                .collect());
+        }
+        Type::LIST => {
+            check_children(attr, array)?;
+            let offsets = buffer_slice::<i32>(buffer(1), offset, rows + 1, attr)?;
+            let elements = &**array.children;
+
+            col.set_converted::<ListEntry>(offsets.windows(2)
+                .map(|pair| ListEntry { offset: pair[0] as u32, len: (pair[1] - pair[0]) as u32 })
+                .collect());
+            col.children.push(
+                import_column(&attr.children[0], elements, 0, elements.length as usize)?);
+        }
+        Type::STRUCT => {
+            check_children(attr, array)?;
+            for (pos, field) in attr.children.iter().enumerate() {
+                let child = &**array.children.offset(pos as isize);
+                col.children.push(import_column(field, child, offset, rows)?);
+            }
+        }
+        _ => {
+            let size = attr.size_of();
+            col.raw = buffer_slice::<u8>(buffer(1), offset * size, rows * size, attr)?.as_ptr();
+            col.raw_len = rows * size;
+        }
+    }
+
+    Ok(col)
+}
+
+impl<'a> RefColumn<'a> for ImportedColumn {
+    fn attribute(&self) -> &Attribute {
+        &self.attr
+    }
+
+    /// Number of rows
+    fn capacity(&self) -> usize {
+        self.rows
+    }
+
+    fn rows_raw_slice(&'a self) -> &'a [u8] {
+        if self.raw.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.raw, self.raw_len) }
+        }
+    }
+
+    unsafe fn rows_ptr(&self) -> *const u8 {
+        self.raw
+    }
+
+    unsafe fn nulls_ptr(&self) -> *const u8 {
+        self.nulls.as_bitmap().words_ptr()
+    }
+
+    fn child(&'a self, pos: usize) -> Option<&'a RefColumn<'a>> {
+        self.children.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+}
+
+impl<'v> View<'v> for ArrowView {
+    fn schema(&'v self) -> &'v Schema {
+        &self.schema
+    }
+
+    fn column(&'v self, pos: usize) -> Option<&'v RefColumn<'v>> {
+        self.columns.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+
+    fn rows(&self) -> RowOffset {
+        self.rows
+    }
+}
+
+impl Drop for ArrowView {
+    fn drop(&mut self) {
+        // Columns point into the array buffers, drop them first
+        self.columns.clear();
+        unsafe { self.array.release_data() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use block::window_alias;
+    use selection::Selection;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn export_import_view() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("name", true, Type::TEXT),
+            Attribute::new("flag", false, Type::BOOLEAN),
+            Attribute::new("at", true, Type::TIMESTAMP),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::INT64)),
+            Attribute::new("uuid", false, Type::UUID),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i32).set("one").set(true).set(TimestampValue::utc(10))
+                    .set(vec![1 as i64, 2]).set(UuidValue([1; 16]))
+                .add_row().set(NULL_VALUE).set(NULL_VALUE).set(false).set(NULL_VALUE)
+                    .set(NULL_VALUE).set(UuidValue([2; 16]))
+                .add_row().set(3 as i32).set("three").set(true).set(TimestampValue::utc(-5))
+                    .set(vec![3 as i64]).set(UuidValue([3; 16]))
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let block = table.block_ref();
+
+        unsafe {
+            let mut schema = export_schema(block.schema()).unwrap();
+            let array = export_view(&allocator::GLOBAL, block).unwrap();
+            assert_eq!((array.length, array.n_children), (3, 6));
+
+            let imported = import_view(&schema, array).unwrap();
+            schema.release_data();
+
+            assert_views_eq!(block, &imported);
+        }
+    }
+
+    #[test]
+    fn export_selected_rows() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::TEXT)),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i32).set(vec!["a", "b"])
+                .add_row().set(NULL_VALUE).set(vec!["c"])
+                .add_row().set(3 as i32).set(NULL_VALUE)
+                .add_row().set(4 as i32).set(vec!["d", "e", "f"])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        // Views with a selection are compacted before they are exported
+        let view = window_alias(table.block_ref(), None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![0, 2, 3]).unwrap()))
+            .unwrap();
+
+        unsafe {
+            let mut schema = export_schema(view.schema()).unwrap();
+            let array = export_view(&allocator::GLOBAL, &view).unwrap();
+            assert_eq!(array.length, 3);
+
+            let imported = import_view(&schema, array).unwrap();
+            schema.release_data();
+
+            assert_views_eq!(&view, &imported);
+            assert!(schema.release.is_none());
+        }
+    }
+
+    #[test]
+    fn parse_formats() {
+        assert!(parse_format("d:10,2", None).unwrap().1 == TypeModifiers::decimal(10, 2));
+        assert!(parse_format("d:10,2,128", None).is_ok());
+        assert!(parse_format("d:39,0", None).is_err(), "Precision over 38");
+        assert!(parse_format("d:0,0", None).is_err(), "Zero precision");
+        assert!(parse_format("d:5,6", None).is_err(), "Scale larger than the precision");
+
+        assert!(parse_format("w:20", None).unwrap().1 == TypeModifiers::fixed(20));
+        assert!(parse_format("w:0", None).is_err(), "Zero width");
+        assert!(parse_format("w:16", Some(UUID_EXTENSION)).unwrap().0 == Type::UUID);
+    }
+}
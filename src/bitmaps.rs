@@ -1,6 +1,440 @@
 // vim : set ts=4 sw=4 et :
 
-type BoolBitmap = &[u8];
+//! Bit-packed bitmaps, used for column null vectors.
+//!
+//! Bits are stored LSB first in 64 bit words (bit `i` is bit `i % 64` of word `i / 64`), same as
+//! the Arrow validity bitmaps. Read-only bitmaps can start at a bit offset, so slicing a column
+//! doesn't require copying its null vector.
 
+use std::ptr;
+use std::slice;
 
+/// Storage unit of bitmaps
+pub type Word = u64;
 
+pub const WORD_BITS: usize = 64;
+
+/// Number of words needed to store `bits`
+#[inline]
+pub fn words_for(bits: usize) -> usize {
+    (bits + WORD_BITS - 1) / WORD_BITS
+}
+
+/// Number of bytes (whole words) needed to store `bits`
+#[inline]
+pub fn bytes_for(bits: usize) -> usize {
+    words_for(bits) * (WORD_BITS / 8)
+}
+
+/// Mask of the valid bits in the last word of a `len` bit bitmap
+#[inline]
+fn tail_mask(len: usize) -> Word {
+    match len % WORD_BITS {
+        0 => !0,
+        bits => (1 << bits) - 1,
+    }
+}
+
+/// Read-only bitmap, possibly starting at a bit offset into its words.
+#[derive(Clone, Copy)]
+pub struct Bitmap<'a> {
+    words: &'a [Word],
+    offset: usize,
+    len: usize,
+}
+
+/// Mutable bitmap over (column) owned words.
+pub struct MutBitmap<'a> {
+    words: &'a mut [Word],
+    len: usize,
+}
+
+/// Owned bitmap, the result of bitmap operations.
+#[derive(Clone, Default)]
+pub struct OwnedBitmap {
+    words: Vec<Word>,
+    len: usize,
+}
+
+/// Iterator over the positions of set bits
+pub struct SetBits<'a> {
+    bitmap: Bitmap<'a>,
+    /// Index of the next word to load
+    next: usize,
+    /// Remaining set bits of the current word
+    current: Word,
+    /// Bit position of the first bit of the current word
+    base: usize,
+}
+
+impl<'a> Bitmap<'a> {
+    /// Bitmap with no bits. Column null vectors of non-nullable columns are empty.
+    pub fn empty() -> Bitmap<'static> {
+        Bitmap { words: &[], offset: 0, len: 0 }
+    }
+
+    pub fn new(words: &'a [Word], len: usize) -> Bitmap<'a> {
+        assert!(len <= words.len() * WORD_BITS);
+        Bitmap { words: words, offset: 0, len: len }
+    }
+
+    /// Bitmap over raw (column) memory. A null pointer produces an empty bitmap.
+    ///
+    /// The pointer has to be aligned to `Word` and point to enough words for `offset + len` bits.
+    pub unsafe fn from_raw(ptr: *const u8, offset: usize, len: usize) -> Bitmap<'a> {
+        if ptr.is_null() {
+            return Bitmap::empty()
+        }
+
+        let words = slice::from_raw_parts(ptr as *const Word, words_for(offset + len));
+        Bitmap { words: words, offset: offset, len: len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Underlying words, the first bit is at `offset()`
+    pub fn words(&self) -> &'a [Word] {
+        self.words
+    }
+
+    /// Pointer to the underlying words, null if there are none (see `RefColumn::nulls_ptr`)
+    pub fn words_ptr(&self) -> *const u8 {
+        if self.words.is_empty() { ptr::null() } else { self.words.as_ptr() as *const u8 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Value of bit `idx`. Panics if out of bounds.
+    #[inline]
+    pub fn get(&self, idx: usize) -> bool {
+        assert!(idx < self.len, "bit {} out of bounds ({})", idx, self.len);
+        let pos = self.offset + idx;
+        self.words[pos / WORD_BITS] >> (pos % WORD_BITS) & 1 != 0
+    }
+
+    /// Value of bit `idx`, bits outside of the bitmap are not set.
+    ///
+    /// Useful for null vectors, where non-nullable columns have an empty bitmap.
+    #[inline]
+    pub fn is_set(&self, idx: usize) -> bool {
+        idx < self.len && self.get(idx)
+    }
+
+    /// `WORD_BITS` bits starting at bit `idx * WORD_BITS` (accounting for the offset). Bits past
+    /// the end of the bitmap are zero.
+    #[inline]
+    pub fn word(&self, idx: usize) -> Word {
+        let start = self.offset + idx * WORD_BITS;
+        let (widx, shift) = (start / WORD_BITS, start % WORD_BITS);
+
+        let lo = self.words.get(widx).cloned().unwrap_or(0);
+        let mut out = if shift == 0 {
+            lo
+        } else {
+            let hi = self.words.get(widx + 1).cloned().unwrap_or(0);
+            lo >> shift | hi << (WORD_BITS - shift)
+        };
+
+        if idx + 1 == words_for(self.len) {
+            out &= tail_mask(self.len);
+        }
+
+        out
+    }
+
+    /// Number of `word()`s covering the bitmap
+    pub fn word_count(&self) -> usize {
+        words_for(self.len)
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        (0 .. self.word_count())
+            .map(|idx| self.word(idx).count_ones() as usize)
+            .sum()
+    }
+
+    /// Any bit set. For null vectors: the column has NULLs.
+    pub fn any(&self) -> bool {
+        (0 .. self.word_count()).any(|idx| self.word(idx) != 0)
+    }
+
+    /// Sub-range of the bitmap, without copying
+    pub fn slice(&self, offset: usize, len: usize) -> Bitmap<'a> {
+        assert!(offset + len <= self.len);
+        Bitmap { words: self.words, offset: self.offset + offset, len: len }
+    }
+
+    /// Positions of set bits, in increasing order
+    pub fn iter_ones(&self) -> SetBits<'a> {
+        SetBits { bitmap: *self, next: 0, current: 0, base: 0 }
+    }
+
+    /// Copy into an owned bitmap starting at bit zero
+    pub fn to_owned_bitmap(&self) -> OwnedBitmap {
+        OwnedBitmap {
+            words: (0 .. self.word_count()).map(|idx| self.word(idx)).collect(),
+            len: self.len,
+        }
+    }
+
+    /// Bitwise AND. Both bitmaps must be the same length.
+    pub fn and(&self, rhs: &Bitmap) -> OwnedBitmap {
+        self.zip_words(rhs, |l, r| l & r)
+    }
+
+    /// Bitwise OR. Both bitmaps must be the same length.
+    pub fn or(&self, rhs: &Bitmap) -> OwnedBitmap {
+        self.zip_words(rhs, |l, r| l | r)
+    }
+
+    /// Bitwise NOT
+    pub fn not(&self) -> OwnedBitmap {
+        let mut out = self.to_owned_bitmap();
+        out.as_mut_bitmap().not_assign();
+        out
+    }
+
+    fn zip_words<F: Fn(Word, Word) -> Word>(&self, rhs: &Bitmap, f: F) -> OwnedBitmap {
+        assert_eq!(self.len, rhs.len, "Bitmap length mismatch");
+
+        OwnedBitmap {
+            words: (0 .. self.word_count()).map(|idx| f(self.word(idx), rhs.word(idx))).collect(),
+            len: self.len,
+        }
+    }
+}
+
+impl<'a> Iterator for SetBits<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            if self.next >= self.bitmap.word_count() {
+                return None
+            }
+
+            self.current = self.bitmap.word(self.next);
+            self.base = self.next * WORD_BITS;
+            self.next += 1;
+        }
+
+        let bit = self.current.trailing_zeros() as usize;
+        // Clear lowest set bit
+        self.current &= self.current - 1;
+        Some(self.base + bit)
+    }
+}
+
+impl<'a> MutBitmap<'a> {
+    pub fn empty() -> MutBitmap<'static> {
+        MutBitmap { words: &mut [], len: 0 }
+    }
+
+    pub fn new(words: &'a mut [Word], len: usize) -> MutBitmap<'a> {
+        assert!(len <= words.len() * WORD_BITS);
+        MutBitmap { words: words, len: len }
+    }
+
+    /// Mutable bitmap over raw (column) memory. A null pointer produces an empty bitmap.
+    ///
+    /// The pointer has to be aligned to `Word` and point to enough words for `len` bits.
+    pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> MutBitmap<'a> {
+        if ptr.is_null() {
+            return MutBitmap { words: &mut [], len: 0 }
+        }
+
+        let words = slice::from_raw_parts_mut(ptr as *mut Word, words_for(len));
+        MutBitmap { words: words, len: len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn get(&self, idx: usize) -> bool {
+        self.as_bitmap().get(idx)
+    }
+
+    /// Set bit `idx` to `value`. Panics if out of bounds.
+    #[inline]
+    pub fn set(&mut self, idx: usize, value: bool) {
+        assert!(idx < self.len, "bit {} out of bounds ({})", idx, self.len);
+        let mask = 1 << (idx % WORD_BITS);
+
+        if value {
+            self.words[idx / WORD_BITS] |= mask;
+        } else {
+            self.words[idx / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Set bits in the range `from .. to` to value
+    pub fn set_range(&mut self, from: usize, to: usize, value: bool) {
+        assert!(from <= to && to <= self.len);
+        let mut idx = from;
+
+        // Leading bits up to word boundary
+        while idx < to && idx % WORD_BITS != 0 {
+            self.set(idx, value);
+            idx += 1;
+        }
+
+        // Whole words
+        while idx + WORD_BITS <= to {
+            self.words[idx / WORD_BITS] = if value { !0 } else { 0 };
+            idx += WORD_BITS;
+        }
+
+        while idx < to {
+            self.set(idx, value);
+            idx += 1;
+        }
+    }
+
+    pub fn as_bitmap(&self) -> Bitmap {
+        Bitmap { words: self.words, offset: 0, len: self.len }
+    }
+
+    /// In-place AND with a bitmap of the same length
+    pub fn and_assign(&mut self, rhs: &Bitmap) {
+        assert_eq!(self.len, rhs.len, "Bitmap length mismatch");
+        for idx in 0 .. words_for(self.len) {
+            self.words[idx] &= rhs.word(idx);
+        }
+    }
+
+    /// In-place OR with a bitmap of the same length
+    pub fn or_assign(&mut self, rhs: &Bitmap) {
+        assert_eq!(self.len, rhs.len, "Bitmap length mismatch");
+        for idx in 0 .. words_for(self.len) {
+            self.words[idx] |= rhs.word(idx);
+        }
+    }
+
+    /// In-place NOT. Bits past the end of the bitmap are left cleared.
+    pub fn not_assign(&mut self) {
+        let count = words_for(self.len);
+        for idx in 0 .. count {
+            self.words[idx] = !self.words[idx];
+        }
+
+        if count > 0 {
+            self.words[count - 1] &= tail_mask(self.len);
+        }
+    }
+
+    /// Copy `src` bits into this bitmap starting at bit `at`
+    pub fn copy_from(&mut self, at: usize, src: &Bitmap) {
+        for idx in 0 .. src.len() {
+            self.set(at + idx, src.get(idx));
+        }
+    }
+}
+
+impl OwnedBitmap {
+    /// Bitmap of `len` bits all set to `value`
+    pub fn new(len: usize, value: bool) -> OwnedBitmap {
+        let mut out = OwnedBitmap { words: vec![0; words_for(len)], len: len };
+        if value {
+            out.as_mut_bitmap().set_range(0, len, true);
+        }
+        out
+    }
+
+    pub fn from_bools(bits: &[bool]) -> OwnedBitmap {
+        let mut out = OwnedBitmap::new(bits.len(), false);
+        {
+            let mut m = out.as_mut_bitmap();
+            for (idx, bit) in bits.iter().enumerate() {
+                if *bit {
+                    m.set(idx, true);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bitmap(&self) -> Bitmap {
+        Bitmap { words: self.words.as_slice(), offset: 0, len: self.len }
+    }
+
+    pub fn as_mut_bitmap(&mut self) -> MutBitmap {
+        MutBitmap { words: self.words.as_mut_slice(), len: self.len }
+    }
+
+    pub fn words(&self) -> &[Word] {
+        self.words.as_slice()
+    }
+
+    /// Underlying words, bits past `len()` in the last word are unspecified
+    pub fn into_words(self) -> Vec<Word> {
+        self.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_get_count() {
+        let mut bm = OwnedBitmap::new(130, false);
+        {
+            let mut m = bm.as_mut_bitmap();
+            m.set(0, true);
+            m.set(64, true);
+            m.set(129, true);
+            m.set_range(70, 75, true);
+        }
+
+        let b = bm.as_bitmap();
+        assert!(b.get(0) && b.get(64) && b.get(129) && !b.get(1));
+        assert!(!b.is_set(500));
+        assert_eq!(b.count_ones(), 8);
+        assert_eq!(b.iter_ones().collect::<Vec<_>>(), vec![0, 64, 70, 71, 72, 73, 74, 129]);
+    }
+
+    #[test]
+    fn slice_offset() {
+        let bm = OwnedBitmap::from_bools(&[true, false, true, true, false, true]);
+        let s = bm.as_bitmap().slice(2, 3);
+
+        assert_eq!(s.len(), 3);
+        assert!(s.get(0) && s.get(1) && !s.get(2));
+        assert_eq!(s.count_ones(), 2);
+        assert_eq!(s.word(0), 0b011);
+        assert_eq!(s.not().as_bitmap().iter_ones().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn and_or_not() {
+        let a = OwnedBitmap::from_bools(&[true, true, false, false]);
+        let b = OwnedBitmap::from_bools(&[true, false, true, false]);
+
+        assert_eq!(a.as_bitmap().and(&b.as_bitmap()).words()[0], 0b0001);
+        assert_eq!(a.as_bitmap().or(&b.as_bitmap()).words()[0], 0b0111);
+        assert_eq!(a.as_bitmap().not().words()[0], 0b1100);
+    }
+}
@@ -1,21 +1,25 @@
 // vim : set ts=4 sw=4 et :
 
 // libstd
+use std::cmp::{max, min};
 use std::mem;
 use std::slice;
-use std::ops::{Index, IndexMut};
+use std::str;
+use std::ops::{Index, IndexMut, Range};
 
 // DBKit
 use ::allocator::{Allocator, OwnedChunk, ChainedArena, MIN_ALIGN};
-use ::types::ValueInfo;
+use ::bitmaps::{self, Bitmap, MutBitmap, OwnedBitmap};
+use ::dictionary::{Dictionary, DictionaryCode};
+use ::kernel;
+use ::selection::{self, Selection};
+use ::types::{self, ListEntry, RawData, Type, Value, ValueInfo};
 use ::schema::{Attribute, Schema};
+use ::shared::{SharedColumn, SharedView};
 use ::error::DBError;
 use ::row::{RowOffset, RowRange};
 use ::util::math::*;
 
-pub type BoolBitmap<'a> = &'a [u8];
-pub type MutBoolBitmap<'a> = &'a mut [u8];
-
 /// Starting size for the VARLEN arena
 const ARENA_MIN_SIZE : usize = MIN_ALIGN;
 
@@ -27,14 +31,44 @@ pub struct ColumnRows<'a, T: ValueInfo>
     where <T as ValueInfo>::Store: 'a
 {
     pub values: &'a [T::Store],
-    pub nulls: BoolBitmap<'a>,
+    /// Set bits are NULL rows. Empty for non-nullable columns.
+    pub nulls: Bitmap<'a>,
 }
 
 pub struct ColumnRowsMut<'a, T: ValueInfo>
     where <T as ValueInfo>::Store: 'a
 {
     pub values: &'a mut [T::Store],
-    pub nulls: MutBoolBitmap<'a>,
+    /// Set bits are NULL rows. Empty for non-nullable columns.
+    pub nulls: MutBitmap<'a>,
+}
+
+/// Physical representation of the column row data
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Encoding {
+    /// Row vector contains the values (`ValueInfo::Store`)
+    Plain,
+    /// Row vector contains `DictionaryCode`s indexing into the column dictionary (TEXT / BLOB)
+    Dictionary,
+    /// Row vector and null vector contain a single value repeated for every row
+    Constant,
+    /// Row vector and null vector contain one value per run of equal values. Runs are described by
+    /// `RefColumn::run_ends`.
+    RunLength,
+    /// Row vector contains `u64` offsets into `RefColumn::varlen_data`, one more than there are
+    /// rows. Row `i` is the bytes between offsets `i` and `i + 1` (TEXT / BLOB).
+    Offsets,
+}
+
+impl Encoding {
+    /// Size of a single row in the row vector
+    pub fn row_size(self, attr: &Attribute) -> usize {
+        match self {
+            Encoding::Plain | Encoding::Constant | Encoding::RunLength => attr.size_of(),
+            Encoding::Dictionary => mem::size_of::<DictionaryCode>(),
+            Encoding::Offsets => mem::size_of::<u64>(),
+        }
+    }
 }
 
 /// Trait representing a reference to column data.
@@ -45,15 +79,63 @@ pub trait RefColumn<'re> {
 
     /// Will panic if there's no row data
     fn rows_raw_slice(&'re self) -> &'re [u8];
-    /// Will panic if there's no null data
-    fn nulls_raw_slice(&'re self) -> &'re [u8];
 
     /// Pointer to the beginning of the raw row data.
     /// ptr can be nil
     unsafe fn rows_ptr(&self) -> *const u8;
-    /// Pointer to the beginning of the raw row data.
+    /// Pointer to the words of the null bitmap (see `bitmaps`), the first row is at bit
+    /// `nulls_offset()`.
     /// ptr can be nil
     unsafe fn nulls_ptr(&self) -> *const u8;
+
+    /// Bit offset of the first row in the null bitmap. Non-zero for aliases of a row range.
+    fn nulls_offset(&self) -> usize {
+        0
+    }
+
+    /// Physical representation of the row data. Consumers that only understand plain data should
+    /// decode other encodings first (eg. `dictionary::decode_column`).
+    fn encoding(&self) -> Encoding {
+        Encoding::Plain
+    }
+
+    /// Values of a dictionary encoded column, indexed by the codes stored in the row data.
+    fn dictionary(&self) -> Option<&[RawData]> {
+        None
+    }
+
+    /// Bytes of the values of an `Offsets` encoded column, the row data holds offsets into it.
+    fn varlen_data(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Memory the `RawData` values of a plain encoded TEXT / BLOB column are stored in (the arena
+    /// chunks). `None` if the column doesn't know, eg. an alias of another column.
+    fn varlen_storage(&self) -> Option<Vec<&[u8]>> {
+        None
+    }
+
+    /// Exclusive end row of each run of a `RunLength` encoded column, in increasing order.
+    fn run_ends(&self) -> Option<&[RowOffset]> {
+        None
+    }
+
+    /// Nested column (the element column of a LIST, fields of a STRUCT)
+    fn child(&'re self, _pos: usize) -> Option<&'re RefColumn<'re>> {
+        None
+    }
+
+    /// Row in the original element column the first row of the (aliased) element column
+    /// corresponds to. `ListEntry` offsets are relative to the original element column.
+    fn list_base(&self) -> usize {
+        0
+    }
+
+    /// Rows of the element column of a LIST column in use, the element column's capacity can be
+    /// larger.
+    fn element_rows(&'re self) -> RowOffset {
+        self.child(0).map_or(0, |elements| elements.capacity())
+    }
 }
 
 /// Helper badness for converting raw column data into a typed slice of rows.
@@ -91,18 +173,248 @@ pub fn column_row_data<'c, T: ValueInfo>(col: &'c RefColumn) -> Result<ColumnRow
     let attr = col.attribute();
     let rows = col.capacity();
 
-    if attr.dtype != T::ENUM {
+    // FIXED_BINARY rows are wider than the Store type
+    if attr.dtype != T::ENUM || attr.size_of() != mem::size_of::<T::Store>() {
         return Err(DBError::AttributeType(attr.name.clone()))
     }
 
+    if col.encoding() != Encoding::Plain {
+        return Err(DBError::ColumnEncoding(attr.name.clone()))
+    }
+
     unsafe {
         Ok(ColumnRows{
             values: rows_from_rawptr_const::<T::Store>(col.rows_ptr(), rows),
-            nulls: rows_from_rawptr_const::<u8>(col.nulls_ptr(), rows),
+            nulls: column_nulls(col),
         })
     }
 }
 
+/// Null bitmap of a column, set bits are NULL rows. Empty for non-nullable columns.
+///
+/// There's a bit per stored row (see `stored_row`), not per row, for `Constant` and `RunLength`
+/// encoded columns.
+pub fn column_nulls<'c>(col: &'c RefColumn) -> Bitmap<'c> {
+    if !col.attribute().nullable {
+        return Bitmap::empty()
+    }
+
+    unsafe { Bitmap::from_raw(col.nulls_ptr(), col.nulls_offset(), stored_rows(col)) }
+}
+
+/// Number of rows in the row data and null vector. `Constant` columns store one row,
+/// `RunLength` columns one row per run, other encodings one row for every row.
+pub fn stored_rows(col: &RefColumn) -> usize {
+    match col.encoding() {
+        Encoding::Constant => 1,
+        Encoding::RunLength => col.run_ends().map_or(0, |ends| ends.len()),
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets => col.capacity(),
+    }
+}
+
+/// Position of the row in the row data and null vector
+pub fn stored_row(col: &RefColumn, row: RowOffset) -> RowOffset {
+    match col.encoding() {
+        Encoding::Constant => 0,
+        Encoding::RunLength => {
+            // A row equal to a run end is the first row of the next run
+            let ends = col.run_ends().unwrap_or(&[]);
+            ends.binary_search(&row).map(|pos| pos + 1).unwrap_or_else(|pos| pos)
+        }
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets => row,
+    }
+}
+
+/// Bytes of the TEXT / BLOB value at a stored row (see `stored_row`), in any encoding
+pub fn varlen_at<'c>(col: &'c RefColumn<'c>, stored: RowOffset) -> &'c [u8] {
+    let raw = col.rows_raw_slice();
+    let rows = stored_rows(col);
+
+    unsafe {
+        match col.encoding() {
+            Encoding::Dictionary => {
+                let codes = rows_from_rawptr_const::<DictionaryCode>(raw.as_ptr(), rows);
+                col.dictionary().unwrap()[codes[stored] as usize].as_ref()
+            }
+            Encoding::Offsets => {
+                let offsets = rows_from_rawptr_const::<u64>(raw.as_ptr(), raw.len() / 8);
+                let data = col.varlen_data().unwrap();
+                &data[offsets[stored] as usize .. offsets[stored + 1] as usize]
+            }
+            _ => rows_from_rawptr_const::<RawData>(raw.as_ptr(), rows)[stored].as_ref(),
+        }
+    }
+}
+
+/// Reference to the stored value at a stored row (see `stored_row`)
+#[inline]
+fn stored_value<'c, T: ValueInfo>(col: &'c RefColumn<'c>, stored: RowOffset) -> &'c T::Store {
+    unsafe { &rows_from_rawptr_const::<T::Store>(col.rows_ptr(), stored_rows(col))[stored] }
+}
+
+/// Value of a single row of a column, in any encoding. LIST and STRUCT values are read
+/// recursively. Meant for tests, debugging and row at a time access; it's too slow for kernels.
+pub fn column_value<'c>(col: &'c RefColumn<'c>, row: RowOffset) -> Result<Value<'c>, DBError> {
+    if row >= col.capacity() {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    let attr = col.attribute();
+    let idx = stored_row(col, row);
+
+    if column_nulls(col).is_set(idx) {
+        return Ok(Value::NULL)
+    }
+
+    let value = match attr.dtype {
+        Type::UINT8 => Value::UINT8(*stored_value::<types::UInt8>(col, idx)),
+        Type::UINT16 => Value::UINT16(*stored_value::<types::UInt16>(col, idx)),
+        Type::UINT32 => Value::UINT32(*stored_value::<types::UInt32>(col, idx)),
+        Type::UINT64 => Value::UINT64(*stored_value::<types::UInt64>(col, idx)),
+        Type::INT8 => Value::INT8(*stored_value::<types::Int8>(col, idx)),
+        Type::INT16 => Value::INT16(*stored_value::<types::Int16>(col, idx)),
+        Type::INT32 => Value::INT32(*stored_value::<types::Int32>(col, idx)),
+        Type::INT64 => Value::INT64(*stored_value::<types::Int64>(col, idx)),
+        Type::FLOAT32 => Value::FLOAT32(*stored_value::<types::Float32>(col, idx)),
+        Type::FLOAT64 => Value::FLOAT64(*stored_value::<types::Float64>(col, idx)),
+        Type::BOOLEAN => Value::BOOLEAN(*stored_value::<types::Boolean>(col, idx)),
+        Type::DATE => Value::DATE(*stored_value::<types::Date>(col, idx)),
+        Type::TIMESTAMP => Value::TIMESTAMP(*stored_value::<types::Timestamp>(col, idx)),
+        Type::INTERVAL => Value::INTERVAL(*stored_value::<types::Interval>(col, idx)),
+        Type::DECIMAL => Value::DECIMAL(types::DecimalValue {
+            value: *stored_value::<types::Decimal>(col, idx),
+            precision: attr.modifiers.precision,
+            scale: attr.modifiers.scale,
+        }),
+        Type::UUID => Value::UUID(*stored_value::<types::Uuid>(col, idx)),
+        Type::FIXED_BINARY => {
+            let width = attr.size_of();
+            Value::FIXED_BINARY(&col.rows_raw_slice()[idx * width .. (idx + 1) * width])
+        }
+        Type::TEXT => {
+            let text = str::from_utf8(varlen_at(col, idx))
+                .map_err(|_| DBError::ValueParse(format!("{} (not UTF-8)", attr.name)))?;
+            Value::TEXT(text)
+        }
+        Type::BLOB => Value::BLOB(varlen_at(col, idx)),
+        Type::LIST => {
+            let lists = list_row_data(col)?;
+            let mut items = Vec::with_capacity(lists.range(idx).len());
+
+            for elem in lists.range(idx) {
+                items.push(column_value(lists.elements, elem)?);
+            }
+
+            Value::LIST(items)
+        }
+        Type::STRUCT => {
+            let mut fields = Vec::with_capacity(attr.children.len());
+
+            for pos in 0 .. attr.children.len() {
+                let field = col.child(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+                fields.push(column_value(field, idx)?);
+            }
+
+            Value::STRUCT(fields)
+        }
+    };
+
+    Ok(value)
+}
+
+/// Row data of a FIXED_BINARY / UUID column. Rows are `width` bytes each.
+pub struct FixedRows<'a> {
+    pub width: usize,
+    pub data: &'a [u8],
+    pub nulls: Bitmap<'a>,
+}
+
+impl<'a> FixedRows<'a> {
+    /// Bytes of the value at row
+    pub fn get(&self, row: RowOffset) -> &'a [u8] {
+        &self.data[row * self.width .. (row + 1) * self.width]
+    }
+}
+
+/// Row data of a fixed width binary column (FIXED_BINARY, UUID)
+pub fn fixed_row_data<'c>(col: &'c RefColumn<'c>) -> Result<FixedRows<'c>, DBError> {
+    let attr = col.attribute();
+
+    if attr.dtype != Type::FIXED_BINARY && attr.dtype != Type::UUID {
+        return Err(DBError::AttributeType(attr.name.clone()))
+    }
+
+    if col.encoding() != Encoding::Plain {
+        return Err(DBError::ColumnEncoding(attr.name.clone()))
+    }
+
+    let width = attr.size_of();
+    let rows = col.capacity();
+
+    unsafe {
+        Ok(FixedRows {
+            width: width,
+            data: rows_from_rawptr_const::<u8>(col.rows_ptr(), rows * width),
+            nulls: column_nulls(col),
+        })
+    }
+}
+
+/// Row data of a LIST column
+pub struct ListRows<'a> {
+    pub entries: &'a [ListEntry],
+    pub nulls: Bitmap<'a>,
+    /// Element column
+    pub elements: &'a RefColumn<'a>,
+    base: usize,
+}
+
+impl<'a> ListRows<'a> {
+    /// Range of rows in the `elements` column making up the list at row
+    pub fn range(&self, row: RowOffset) -> Range<RowOffset> {
+        let entry = self.entries[row];
+        let start = entry.offset as usize - self.base;
+        start .. start + entry.len as usize
+    }
+}
+
+/// List entries, null vector and element column of a LIST column.
+pub fn list_row_data<'c>(col: &'c RefColumn<'c>) -> Result<ListRows<'c>, DBError> {
+    let rows = column_row_data::<types::List>(col)?;
+    let elements = col.child(0)
+        .ok_or(DBError::AttributeMissing(format!("{}.element", col.attribute().name)))?;
+
+    Ok(ListRows {
+        entries: rows.values,
+        nulls: rows.nulls,
+        elements: elements,
+        base: col.list_base(),
+    })
+}
+
+/// VARLEN arena usage of a column or block, used to decide when to compact the arenas (see
+/// `Block::compact_arenas`).
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct ArenaStats {
+    /// Bytes of the values referenced by rows
+    pub live: usize,
+    /// Bytes of the allocated arena chunks
+    pub allocated: usize,
+}
+
+impl ArenaStats {
+    /// Allocated bytes not referenced by any row: overwritten values and unused chunk space
+    pub fn garbage(&self) -> usize {
+        self.allocated.saturating_sub(self.live)
+    }
+
+    fn add(&mut self, other: ArenaStats) {
+        self.live += other.live;
+        self.allocated += other.allocated;
+    }
+}
+
 /// Typed Data Column. Contains a vector of column rows, and optionally a nul vector.
 ///
 /// Knows its capacity but not size, has no concept of current. Those properties are fulfilled by
@@ -113,15 +425,33 @@ pub struct Column<'alloc> {
     raw_nulls: OwnedChunk<'alloc>,
     raw: OwnedChunk<'alloc>,
     /// Used to store varlen column values
-    arena: ChainedArena<'alloc>
+    arena: ChainedArena<'alloc>,
+    /// Distinct values of dictionary encoded VARLEN columns
+    dictionary: Option<Dictionary<'alloc>>,
+    /// Nested columns (LIST element column, STRUCT fields)
+    children: Vec<Column<'alloc>>,
+    /// Number of used rows in the LIST element column
+    child_rows: RowOffset,
+    capacity: RowOffset,
 }
 
 /// Typed Data Column that references another column
 #[derive(Clone)]
 pub struct AliasColumn<'parent> {
     attr: Attribute,
-    raw_nulls: &'parent [u8],
+    nulls: Bitmap<'parent>,
     raw: &'parent [u8],
+    dictionary: Option<&'parent [RawData]>,
+    varlen_data: Option<&'parent [u8]>,
+    encoding: Encoding,
+    /// Run ends (relative to the alias) of `RunLength` encoded columns
+    run_ends: Vec<RowOffset>,
+    children: Vec<AliasColumn<'parent>>,
+    list_base: usize,
+    rows: RowOffset,
+    /// Null vector replacing `nulls`, when the NULLs of parent STRUCTs are folded in (see
+    /// `alias_nested_column`)
+    folded_nulls: Option<OwnedBitmap>,
 }
 
 /// Create another read only alias of a column
@@ -132,31 +462,121 @@ pub fn alias_column<'a>(src: &'a RefColumn<'a>, range: Option<RowRange>)
 {
     let (offset, rows) = range.map_or((0, src.capacity()), |r| (r.offset, r.rows));
 
-    let size_of = src.attribute().dtype.size_of();
-    let start = offset * size_of;
-    let len = rows + size_of;
-
     if offset + rows > src.capacity() {
         return Err(DBError::RowOutOfBounds)
     }
 
+    // Constant / RLE columns alias the stored rows (runs) covering the range
+    let (stored, run_ends) = match src.encoding() {
+        Encoding::Constant => (0 .. 1, Vec::new()),
+        Encoding::RunLength => alias_runs(src, offset, rows),
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets =>
+            (offset .. offset + rows, Vec::new()),
+    };
+
+    // `Offsets` columns also need the offset past the last row
+    let extra = if src.encoding() == Encoding::Offsets { 1 } else { 0 };
+
+    let size_of = src.encoding().row_size(src.attribute());
     let raw = src.rows_raw_slice();
-    let col = &raw[start .. start + len];
+    let col = &raw[stored.start * size_of .. (stored.end + extra) * size_of];
 
     let nulls = if src.attribute().nullable {
-        let raw = src.nulls_raw_slice();
-        &raw[offset .. offset + rows]
+        column_nulls(src).slice(stored.start, stored.end - stored.start)
     } else {
-        &[]
+        Bitmap::empty()
+    };
+
+    let (children, list_base) = match src.attribute().dtype {
+        Type::LIST => alias_list_elements(src, col, nulls)?,
+        Type::STRUCT => (alias_struct_fields(src, offset, rows)?, 0),
+        _ => (Vec::new(), 0),
     };
 
     Ok(AliasColumn {
         attr: src.attribute().clone(),
         raw: col,
-        raw_nulls: nulls,
+        nulls: nulls,
+        dictionary: src.dictionary(),
+        varlen_data: src.varlen_data(),
+        encoding: src.encoding(),
+        run_ends: run_ends,
+        children: children,
+        list_base: list_base,
+        rows: rows,
+        folded_nulls: None,
     })
 }
 
+/// Runs overlapping the row range, and their ends relative to the range
+fn alias_runs(src: &RefColumn, offset: RowOffset, rows: RowOffset)
+    -> (Range<RowOffset>, Vec<RowOffset>)
+{
+    if rows == 0 {
+        return (0 .. 0, Vec::new())
+    }
+
+    let ends = src.run_ends().unwrap_or(&[]);
+    let first = stored_row(src, offset);
+    let last = stored_row(src, offset + rows - 1);
+
+    let run_ends = ends[first .. last + 1].iter()
+        .map(|end| min(*end, offset + rows) - offset)
+        .collect();
+
+    (first .. last + 1, run_ends)
+}
+
+/// STRUCT fields are aliased using the same row range as the parent
+fn alias_struct_fields<'a>(src: &'a RefColumn<'a>, offset: RowOffset, rows: RowOffset)
+    -> Result<Vec<AliasColumn<'a>>, DBError>
+{
+    let count = src.attribute().children.len();
+    let mut out = Vec::with_capacity(count);
+
+    for pos in 0 .. count {
+        let field = src.child(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))?;
+        out.push(alias_column(field, Some(RowRange { offset: offset, rows: rows }))?);
+    }
+
+    Ok(out)
+}
+
+/// Alias only the part of the element column referenced by the aliased LIST rows.
+fn alias_list_elements<'a>(src: &'a RefColumn<'a>, raw: &'a [u8], nulls: Bitmap<'a>)
+    -> Result<(Vec<AliasColumn<'a>>, usize), DBError>
+{
+    let entries = unsafe {
+        rows_from_rawptr_const::<ListEntry>(raw.as_ptr(), raw.len() / mem::size_of::<ListEntry>())
+    };
+
+    // Rows that are NULL don't reference any elements
+    let mut lo = usize::max_value();
+    let mut hi = 0;
+
+    for (idx, entry) in entries.iter().enumerate() {
+        if nulls.is_set(idx) {
+            continue
+        }
+
+        lo = min(lo, entry.offset as usize);
+        hi = max(hi, entry.offset as usize + entry.len as usize);
+    }
+
+    let base = src.list_base();
+    if lo > hi {
+        lo = base;
+        hi = base;
+    }
+
+    let elements = src.child(0)
+        .ok_or(DBError::AttributeMissing(format!("{}.element", src.attribute().name)))?;
+    let range = RowRange { offset: lo - base, rows: hi - lo };
+
+    Ok((vec![alias_column(elements, Some(range))?], lo))
+}
+
 impl<'parent> RefColumn<'parent> for AliasColumn<'parent> {
     fn attribute(&self) -> &Attribute {
         &self.attr
@@ -164,7 +584,7 @@ impl<'parent> RefColumn<'parent> for AliasColumn<'parent> {
 
     /// Row capacity
     fn capacity(&self) -> usize {
-        self.raw.len() / self.attr.dtype.size_of()
+        self.rows
     }
 
     /// Pointer to the beginning of the raw row data
@@ -172,17 +592,45 @@ impl<'parent> RefColumn<'parent> for AliasColumn<'parent> {
         self.raw.as_ptr()
     }
 
-    /// Pointer to the beginning of the raw row data
+    /// Pointer to the words of the null bitmap
     unsafe fn nulls_ptr(&self) -> *const u8 {
-        self.raw_nulls.as_ptr()
+        match self.folded_nulls {
+            Some(ref nulls) => nulls.as_bitmap().words_ptr(),
+            None => self.nulls.words_ptr(),
+        }
+    }
+
+    fn nulls_offset(&self) -> usize {
+        if self.folded_nulls.is_some() { 0 } else { self.nulls.offset() }
     }
 
     fn rows_raw_slice(&'parent self) -> &'parent [u8] {
         self.raw
     }
 
-    fn nulls_raw_slice(&'parent self) -> &'parent [u8] {
-        self.raw_nulls
+    fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    fn dictionary(&self) -> Option<&[RawData]> {
+        self.dictionary
+    }
+
+    fn varlen_data(&self) -> Option<&[u8]> {
+        self.varlen_data
+    }
+
+    fn run_ends(&self) -> Option<&[RowOffset]> {
+        if self.encoding == Encoding::RunLength { Some(&self.run_ends) } else { None }
+    }
+
+    fn child(&'parent self, pos: usize) -> Option<&'parent RefColumn<'parent>> {
+        self.children.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+
+    fn list_base(&self) -> usize {
+        self.list_base
     }
 }
 
@@ -193,7 +641,7 @@ impl<'alloc> RefColumn<'alloc> for Column<'alloc> {
 
     /// Row capacity
     fn capacity(&self) -> usize {
-        self.raw.len() / self.attr.dtype.size_of()
+        self.capacity
     }
 
     /// Pointer to the beginning of the raw row data
@@ -201,7 +649,7 @@ impl<'alloc> RefColumn<'alloc> for Column<'alloc> {
         self.raw.as_ptr()
     }
 
-    /// Pointer to the beginning of the raw row data
+    /// Pointer to the words of the null bitmap
     unsafe fn nulls_ptr(&self) -> *const u8 {
         self.raw_nulls.as_ptr()
     }
@@ -211,45 +659,112 @@ impl<'alloc> RefColumn<'alloc> for Column<'alloc> {
             .map_or(&[], |f| f as &'alloc [u8])
     }
 
-    fn nulls_raw_slice(&'alloc self) -> &'alloc [u8] {
-        self.raw_nulls.data.as_ref()
-            .map_or(&[], |f| f as &'alloc [u8])
+    fn encoding(&self) -> Encoding {
+        if self.dictionary.is_some() { Encoding::Dictionary } else { Encoding::Plain }
+    }
+
+    fn dictionary(&self) -> Option<&[RawData]> {
+        self.dictionary.as_ref().map(|d| d.values())
+    }
+
+    fn varlen_storage(&self) -> Option<Vec<&[u8]>> {
+        Some(self.arena.chunks())
+    }
+
+    fn child(&'alloc self, pos: usize) -> Option<&'alloc RefColumn<'alloc>> {
+        self.children.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+
+    fn element_rows(&'alloc self) -> RowOffset {
+        self.child_rows
     }
 }
 
 impl<'alloc> Column<'alloc> {
     fn new(a: &'alloc Allocator, attr: Attribute) -> Column<'alloc> {
+        let children = attr.children.iter()
+            .map(|child| Column::new(a, child.clone()))
+            .collect();
+
         Column {
             allocator: a,
             attr: attr,
             raw_nulls: OwnedChunk::empty(),
             raw: OwnedChunk::empty(),
             arena: ChainedArena::new(a, ARENA_MIN_SIZE, ARENA_MAX_SIZE),
+            dictionary: None,
+            children: children,
+            child_rows: 0,
+            capacity: 0,
+        }
+    }
+
+    /// Mutable reference to nested column
+    pub fn child_mut(&mut self, pos: usize) -> Option<&mut Column<'alloc>> {
+        self.children.get_mut(pos)
+    }
+
+    /// Nested columns (LIST element column, STRUCT fields)
+    pub fn children(&self) -> &[Column<'alloc>] {
+        &self.children
+    }
+
+    /// Reserve `count` rows at the end of a LIST element column, growing it if needed.
+    ///
+    /// Returns the first reserved row of the element column.
+    pub fn list_reserve(&mut self, count: usize) -> Result<RowOffset, DBError> {
+        if self.attr.dtype != Type::LIST {
+            return Err(DBError::AttributeType(self.attr.name.clone()))
+        }
+
+        let start = self.child_rows;
+        let needed = start + count;
+
+        if needed > u32::max_value() as usize {
+            return Err(DBError::MemoryLimit)
+        }
+
+        let elements = &mut self.children[0];
+        if needed > elements.capacity() {
+            let new_cap = round_up(max(needed, elements.capacity() * 2), 1024);
+            if let Some(err) = elements.set_capacity(new_cap) {
+                return Err(err)
+            }
         }
+
+        self.child_rows = needed;
+        Ok(start)
     }
 
     pub fn arena(&mut self) -> &mut ChainedArena<'alloc> {
         &mut self.arena
     }
 
-    pub fn nulls_mut(&mut self) -> Result<MutBoolBitmap, DBError> {
+    /// Null bitmap, set bits are NULL rows
+    pub fn nulls_mut(&mut self) -> Result<MutBitmap, DBError> {
         if !self.attr.nullable {
             return Err(DBError::AttributeNullability(self.attr.name.clone()))
         }
 
-        let out: MutBoolBitmap = match self.raw_nulls.data {
-            Some(ref mut slice) => slice,
-            _ => &mut[],
-        };
+        unsafe { Ok(MutBitmap::from_raw(self.raw_nulls.as_mut_ptr(), self.capacity)) }
+    }
 
-        Ok(out)
+    /// Raw row data, `capacity` rows of the encoding's row size
+    pub fn rows_raw_mut(&mut self) -> &mut [u8] {
+        let size = self.capacity * self.encoding().row_size(&self.attr);
+        unsafe { rows_from_rawptr::<u8>(self.raw.as_mut_ptr(), size) }
     }
 
     pub fn rows_mut<T: ValueInfo>(&mut self) -> Result<&mut [T::Store], DBError> {
-        if self.attr.dtype != T::ENUM {
+        if self.attr.dtype != T::ENUM || self.attr.size_of() != mem::size_of::<T::Store>() {
             return Err(DBError::AttributeType(self.attr.name.clone()))
         }
 
+        if self.dictionary.is_some() {
+            return Err(DBError::ColumnEncoding(self.attr.name.clone()))
+        }
+
         unsafe {
             let ptr: *mut T::Store = mem::transmute(self.raw.as_mut_ptr());
             let out = if ptr.is_null() {
@@ -263,10 +778,14 @@ impl<'alloc> Column<'alloc> {
     }
 
     pub fn row_data_mut<T: ValueInfo>(&mut self) -> Result<ColumnRowsMut<T>, DBError> {
-        if self.attr.dtype != T::ENUM {
+        if self.attr.dtype != T::ENUM || self.attr.size_of() != mem::size_of::<T::Store>() {
             return Err(DBError::AttributeType(self.attr.name.clone()))
         }
 
+        if self.dictionary.is_some() {
+            return Err(DBError::ColumnEncoding(self.attr.name.clone()))
+        }
+
         unsafe {
             let ptr: *mut T::Store = mem::transmute(self.raw.as_mut_ptr());
             let rows = if ptr.is_null() {
@@ -275,45 +794,288 @@ impl<'alloc> Column<'alloc> {
                 slice::from_raw_parts_mut(ptr, self.capacity())
             };
 
-            let nulls: MutBoolBitmap = match self.raw_nulls.data {
-                Some(ref mut slice) => slice,
-                _ => &mut[],
+            let nulls = if self.attr.nullable {
+                MutBitmap::from_raw(self.raw_nulls.as_mut_ptr(), self.capacity)
+            } else {
+                MutBitmap::empty()
             };
 
             Ok(ColumnRowsMut{ values: rows, nulls: nulls})
         }
     }
 
+    /// Set a VARLEN (TEXT / BLOB) row value. The data is copied into the column's arena, or
+    /// added to the dictionary for dictionary encoded columns.
+    pub fn set_varlen(&mut self, row: RowOffset, data: &[u8]) -> Result<(), DBError> {
+        if self.attr.dtype != Type::TEXT && self.attr.dtype != Type::BLOB {
+            return Err(DBError::AttributeType(self.attr.name.clone()))
+        }
+
+        let capacity = self.capacity();
+
+        if let Some(ref mut dict) = self.dictionary {
+            let code = dict.intern(data)?;
+            let codes = unsafe {
+                rows_from_rawptr::<DictionaryCode>(self.raw.as_mut_ptr(), capacity)
+            };
+
+            codes[row] = code;
+            return Ok(())
+        }
+
+        let ptr = self.arena.append(data)?.1;
+        let values = unsafe { rows_from_rawptr::<RawData>(self.raw.as_mut_ptr(), capacity) };
+        values[row] = RawData { data: ptr, size: data.len() };
+        Ok(())
+    }
+
+    /// Set a fixed width binary (FIXED_BINARY / UUID) row value. The data has to be exactly as
+    /// wide as the column type.
+    pub fn set_fixed(&mut self, row: RowOffset, data: &[u8]) -> Result<(), DBError> {
+        if self.attr.dtype != Type::FIXED_BINARY && self.attr.dtype != Type::UUID {
+            return Err(DBError::AttributeType(self.attr.name.clone()))
+        }
+
+        let width = self.attr.size_of();
+
+        if data.len() != width {
+            return Err(DBError::ValueParse(
+                format!("{} bytes into {}({})", data.len(), self.attr.dtype.name(), width)))
+        }
+
+        let capacity = self.capacity();
+        let rows = unsafe { rows_from_rawptr::<u8>(self.raw.as_mut_ptr(), capacity * width) };
+        rows[row * width .. (row + 1) * width].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Values are stored in the column's arena (rather than a dictionary)
+    fn has_arena_values(&self) -> bool {
+        let varlen = self.attr.dtype == Type::TEXT || self.attr.dtype == Type::BLOB;
+        varlen && self.dictionary.is_none()
+    }
+
+    /// Rows of the nested columns used by the first `rows` rows
+    fn nested_rows(&self, rows: RowOffset) -> RowOffset {
+        if self.attr.dtype == Type::LIST { self.child_rows } else { rows }
+    }
+
+    /// Bytes of the non NULL values of the first `rows` rows
+    fn live_bytes(&self, rows: RowOffset) -> usize {
+        unsafe {
+            let values = rows_from_rawptr_const::<RawData>(self.raw.as_ptr(), self.capacity);
+            let nulls = Bitmap::from_raw(self.raw_nulls.as_ptr(), 0, self.capacity);
+
+            (0 .. rows)
+                .filter(|row| !(self.attr.nullable && nulls.get(*row)))
+                .map(|row| values[row].size)
+                .sum()
+        }
+    }
+
+    /// VARLEN arena usage of the first `rows` rows, including the nested columns
+    pub fn arena_stats(&self, rows: RowOffset) -> ArenaStats {
+        let mut stats = ArenaStats { live: 0, allocated: self.arena.allocated() };
+
+        if self.has_arena_values() {
+            stats.live = self.live_bytes(rows);
+        }
+
+        let nested = self.nested_rows(rows);
+        for child in &self.children {
+            stats.add(child.arena_stats(nested));
+        }
+
+        stats
+    }
+
+    /// Copy the VARLEN values of the first `rows` rows (and of the nested columns) into a new arena
+    /// sized to fit them, and release the old arena. Values that have been overwritten are freed
+    /// this way. Rows past `rows` and NULL rows are reset to empty values.
+    pub fn compact_arena(&mut self, rows: RowOffset) -> Result<(), DBError> {
+        let nested = self.nested_rows(rows);
+        for child in &mut self.children {
+            child.compact_arena(nested)?;
+        }
+
+        if !self.has_arena_values() {
+            return Ok(())
+        }
+
+        let live = self.live_bytes(rows);
+        let mut arena = ChainedArena::new(self.allocator, max(min(live, ARENA_MAX_SIZE), 1),
+                                          ARENA_MAX_SIZE);
+
+        unsafe {
+            let values = rows_from_rawptr::<RawData>(self.raw.as_mut_ptr(), self.capacity);
+            let nulls = Bitmap::from_raw(self.raw_nulls.as_ptr(), 0, self.capacity);
+
+            // Rows are only updated once all values are copied, so they don't point into the new
+            // arena if copying fails
+            let mut moved = Vec::with_capacity(rows);
+            for row in 0 .. rows {
+                let ptr = if self.attr.nullable && nulls.get(row) {
+                    ::std::ptr::null_mut()
+                } else {
+                    arena.append(values[row].as_ref())?.1
+                };
+
+                moved.push(ptr);
+            }
+
+            for (row, value) in values.iter_mut().enumerate() {
+                *value = match moved.get(row) {
+                    Some(ptr) if !ptr.is_null() => RawData { data: *ptr, size: value.size },
+                    _ => RawData { data: ::std::ptr::null_mut(), size: 0 },
+                };
+            }
+        }
+
+        self.arena = arena;
+        Ok(())
+    }
+
+    /// Convert the first `rows` of a TEXT / BLOB column into dictionary encoding. Values set after
+    /// the conversion are added to the dictionary.
+    pub fn encode_dictionary(&mut self, rows: RowOffset) -> Result<(), DBError> {
+        if self.dictionary.is_some() {
+            return Ok(())
+        }
+
+        if self.attr.dtype != Type::TEXT && self.attr.dtype != Type::BLOB {
+            return Err(DBError::AttributeType(self.attr.name.clone()))
+        }
+
+        let capacity = self.capacity();
+        let mut dict = Dictionary::new(self.allocator);
+
+        let mut codes_chunk = if capacity > 0 {
+            self.allocator.allocate(capacity * mem::size_of::<DictionaryCode>())?
+        } else {
+            OwnedChunk::empty()
+        };
+
+        unsafe {
+            let values = rows_from_rawptr_const::<RawData>(self.raw.as_ptr(), capacity);
+            let nulls = Bitmap::from_raw(self.raw_nulls.as_ptr(), 0, capacity);
+            let codes = rows_from_rawptr::<DictionaryCode>(codes_chunk.as_mut_ptr(), capacity);
+
+            for idx in 0 .. rows {
+                codes[idx] = if self.attr.nullable && nulls.get(idx) {
+                    0
+                } else {
+                    dict.intern(values[idx].as_ref())?
+                };
+            }
+        }
+
+        // Values now live in the dictionary arena, release the old row data and arena
+        self.raw = codes_chunk;
+        self.arena = ChainedArena::new(self.allocator, ARENA_MIN_SIZE, ARENA_MAX_SIZE);
+        self.dictionary = Some(dict);
+        Ok(())
+    }
+
+    /// Convert the first `rows` of a dictionary encoded column back into plain TEXT / BLOB data.
+    /// The column is left dictionary encoded if it fails.
+    pub fn decode_dictionary(&mut self, rows: RowOffset) -> Result<(), DBError> {
+        let capacity = self.capacity;
+
+        if self.dictionary.is_none() {
+            return Ok(())
+        }
+
+        if rows > capacity {
+            return Err(DBError::RowOutOfBounds)
+        }
+
+        let mut values_chunk = if capacity > 0 {
+            self.allocator.allocate(capacity * mem::size_of::<RawData>())?
+        } else {
+            OwnedChunk::empty()
+        };
+
+        unsafe {
+            let dict = self.dictionary.as_ref().unwrap().values();
+            let codes = rows_from_rawptr_const::<DictionaryCode>(self.raw.as_ptr(), capacity);
+            let nulls = Bitmap::from_raw(self.raw_nulls.as_ptr(), 0, capacity);
+            let values = rows_from_rawptr::<RawData>(values_chunk.as_mut_ptr(), capacity);
+
+            for idx in 0 .. rows {
+                if self.attr.nullable && nulls.get(idx) {
+                    values[idx] = RawData { data: ::std::ptr::null_mut(), size: 0 };
+                    continue;
+                }
+
+                let code = codes[idx] as usize;
+                let data: &[u8] = match dict.get(code) {
+                    Some(value) => value.as_ref(),
+                    None => return Err(DBError::InvalidColumn(
+                        format!("{} (dictionary code {} out of range)", self.attr.name, code))),
+                };
+                let ptr = self.arena.append(data)?.1;
+                values[idx] = RawData { data: ptr, size: data.len() };
+            }
+        }
+
+        // Only switch the encoding once all the values are decoded
+        self.raw = values_chunk;
+        self.dictionary = None;
+        Ok(())
+    }
+
     /// Change the capacity of the Column
     pub fn set_capacity(&mut self, rows: RowOffset) -> Option<DBError> {
-        let new_size = rows * self.attr.dtype.size_of();
+        let new_size = rows * self.encoding().row_size(&self.attr);
 
-        if self.raw.is_null() {
-            match self.allocator.allocate(new_size) {
-                Ok(chunk) => self.raw = chunk,
-                Err(e) => return Some(e)
+        // STRUCT columns don't have row data, only a null vector
+        if new_size > 0 {
+            if self.raw.is_null() {
+                match self.allocator.allocate(new_size) {
+                    Ok(chunk) => self.raw = chunk,
+                    Err(e) => return Some(e)
+                }
+            } else {
+                let status = self.raw.resize(new_size);
+                if status.is_some() {
+                    return status;
+                }
             }
+        }
 
-            if self.attr.nullable {
-                match self.allocator.allocate(rows) {
+        if self.attr.nullable {
+            let nulls_size = bitmaps::bytes_for(rows);
+
+            if self.raw_nulls.is_null() {
+                match self.allocator.allocate(nulls_size) {
                     Ok(chunk) => self.raw_nulls = chunk,
                     Err(e) => return Some(e)
                 }
+            } else {
+                let nulls_status = self.raw_nulls.resize(nulls_size);
+                if nulls_status.is_some() {
+                    return nulls_status;
+                }
             }
-        } else {
-            let status = self.raw.resize(new_size);
-            if status.is_some() {
-                return status;
+
+            // New rows start out as not NULL
+            let old = min(self.capacity, rows);
+            unsafe {
+                MutBitmap::from_raw(self.raw_nulls.as_mut_ptr(), rows).set_range(old, rows, false);
             }
+        }
 
-            if self.attr.nullable {
-                let nulls_status = self.raw_nulls.resize(rows);
-                if nulls_status.is_some() {
-                    return nulls_status;
+        // STRUCT fields have a value for every row, LIST elements grow independently
+        if self.attr.dtype == Type::STRUCT {
+            for child in &mut self.children {
+                let status = child.set_capacity(rows);
+                if status.is_some() {
+                    return status;
                 }
             }
         }
 
+        self.capacity = rows;
         None
     }
 }
@@ -326,6 +1088,26 @@ pub trait View<'v> {
 
     /// Number of rows
     fn rows(&self) -> RowOffset;
+
+    /// Rows of the view that are visible to consumers, `None` if all rows are. The positions of
+    /// the rows that aren't selected are still part of the view (and its columns).
+    fn selection(&self) -> Option<&Selection> {
+        None
+    }
+
+    /// Value of a single cell, `Value::NULL` for NULL rows. See `row::Row` and `ValueGetter` for
+    /// typed access. Meant for reading results row at a time, it's too slow for kernels.
+    ///
+    /// Rows past the view's rows, or not in its selection, are `DBError::RowOutOfBounds`.
+    fn value(&'v self, col: usize, row: RowOffset) -> Result<Value<'v>, DBError> {
+        if row >= self.rows() || self.selection().map_or(false, |sel| !sel.is_selected(row)) {
+            return Err(DBError::RowOutOfBounds)
+        }
+
+        let column = self.column(col)
+            .ok_or(DBError::make_column_unknown_pos(col))?;
+        column_value(column, row)
+    }
 }
 
 /// An implementation of a View that doesn't "own" the data but aliases it
@@ -334,6 +1116,95 @@ pub struct RefView<'a> {
     schema: Schema,
     columns: Vec<AliasColumn<'a>>,
     rows: RowOffset,
+    selection: Option<Selection>,
+}
+
+/// Resolve a column by its position path (see `Schema::resolve_path`), descending into STRUCT
+/// fields.
+///
+/// The null vector of a field doesn't include the NULLs of its parent STRUCTs, use
+/// `alias_nested_column` for that.
+pub fn nested_column<'a>(src: &'a View<'a>, path: &[usize]) -> Result<&'a RefColumn<'a>, DBError> {
+    nested_columns(src, path)
+        .map(|columns| columns[columns.len() - 1])
+}
+
+/// Columns along a position path, from the top level column to the resolved field
+fn nested_columns<'a>(src: &'a View<'a>, path: &[usize])
+    -> Result<Vec<&'a RefColumn<'a>>, DBError>
+{
+    let first = match path.first() {
+        Some(pos) => *pos,
+        None => return Err(DBError::AttributeMissing(String::from("(empty path)"))),
+    };
+
+    let mut col = src.column(first)
+        .ok_or(DBError::make_column_unknown_pos(first))?;
+    let mut out = vec![col];
+
+    for pos in &path[1..] {
+        col = col.child(*pos)
+            .ok_or(DBError::make_column_unknown_pos(*pos))?;
+        out.push(col);
+    }
+
+    Ok(out)
+}
+
+/// Alias a column resolved by its position path (see `nested_column`).
+///
+/// A field of a NULL STRUCT row is NULL: the NULLs of the parent STRUCTs are folded into the
+/// alias null vector, and the alias is nullable if any of the parents is.
+pub fn alias_nested_column<'a>(src: &'a View<'a>, path: &[usize], range: Option<RowRange>)
+    -> Result<AliasColumn<'a>, DBError>
+{
+    let columns = nested_columns(src, path)?;
+    let (col, parents) = columns.split_last().unwrap();
+    let mut alias = alias_column(*col, range)?;
+
+    if parents.iter().any(|p| p.attribute().nullable) {
+        let range = range.unwrap_or(RowRange { offset: 0, rows: col.capacity() });
+        alias.folded_nulls = Some(fold_parent_nulls(*col, parents, range)?);
+        alias.attr.nullable = true;
+    }
+
+    Ok(alias)
+}
+
+/// Null vector of the `range` rows of a STRUCT field, with the NULLs of its parent STRUCTs
+/// folded in.
+pub fn fold_parent_nulls(col: &RefColumn, parents: &[&RefColumn], range: RowRange)
+    -> Result<OwnedBitmap, DBError>
+{
+    // The field's own null vector needs to have a bit per row
+    match col.encoding() {
+        Encoding::Constant | Encoding::RunLength =>
+            return Err(DBError::ColumnEncoding(col.attribute().name.clone())),
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets => {},
+    }
+
+    let own = column_nulls(col);
+    let parent_nulls: Vec<(&RefColumn, Bitmap)> = parents.iter()
+        .map(|p| (*p, column_nulls(*p)))
+        .collect();
+
+    let mut out = OwnedBitmap::new(range.rows, false);
+
+    {
+        let mut bits = out.as_mut_bitmap();
+
+        for idx in 0 .. range.rows {
+            let row = range.offset + idx;
+            let null = own.is_set(row) || parent_nulls.iter()
+                .any(|&(p, ref nulls)| nulls.is_set(stored_row(p, row)));
+
+            if null {
+                bits.set(idx, true);
+            }
+        }
+    }
+
+    Ok(out)
 }
 
 /// Take a view and create a vector of column aliases
@@ -343,8 +1214,11 @@ pub fn alias_columns<'a>(src: &'a View<'a>, range: Option<RowRange>)
     let count = src.schema().count();
     let mut out: Vec<AliasColumn> = Vec::with_capacity(count);
 
+    // Rows past the view's rows (up to the column capacity) aren't initialized
+    let range = range.unwrap_or(RowRange { offset: 0, rows: src.rows() });
+
     for pos in 0 .. count {
-        let col = alias_column(src.column(pos).unwrap(), range)?;
+        let col = alias_column(src.column(pos).unwrap(), Some(range))?;
         out.push(col);
     }
 
@@ -364,9 +1238,14 @@ impl<'a> View<'a> for RefView<'a> {
     fn rows(&self) -> RowOffset {
         self.rows
     }
+
+    fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
 }
 
-/// Create window into another view
+/// Create window into another view. The window keeps the part of the source selection that falls
+/// into it.
 pub fn window_alias<'a>(src: &'a View<'a>, range: Option<RowRange>)
     -> Result<RefView<'a>, DBError>
 {
@@ -381,14 +1260,32 @@ pub fn window_alias<'a>(src: &'a View<'a>, range: Option<RowRange>)
             schema: schema.clone(),
             rows: rows,
             columns: alias_columns(src, range)?,
+            selection: src.selection().map(|s| s.slice(offset, rows)),
         })
     }
 }
 
 impl<'a> RefView<'a> {
     pub fn new(schema: Schema, columns: Vec<AliasColumn<'a>>, rows: RowOffset) -> RefView<'a> {
-        RefView { schema: schema, columns: columns, rows: rows }
+        RefView { schema: schema, columns: columns, rows: rows, selection: None }
     }
+
+    /// Only expose the selected rows to consumers (replacing the current selection)
+    pub fn with_selection(mut self, selection: Option<Selection>) -> Result<RefView<'a>, DBError> {
+        if let Some(ref sel) = selection {
+            sel.check(self.rows)?;
+        }
+
+        self.selection = selection;
+        Ok(self)
+    }
+}
+
+/// Materialize the selected rows of a view into a dense `Block` (without a selection). VARLEN
+/// values are copied into the new block, dictionary encoded columns are decoded.
+pub fn compact<'b, 'a>(alloc: &'b Allocator, src: &'a View<'a>) -> Result<Block<'b>, DBError> {
+    let rows: Vec<RowOffset> = selection::selected_rows(src.selection(), src.rows()).collect();
+    kernel::take(alloc, src, &rows)
 }
 
 /// A container for column data conforming to a pre-defined schema. This container is the owner of
@@ -494,10 +1391,137 @@ impl<'b> Block<'b> {
         }
     }
 
+    /// Append all (selected) rows of a view with the same schema, see `concat`
+    pub fn append_view<'v>(&mut self, src: &'v View<'v>) -> Result<(), DBError> {
+        self.concat(&[src])
+    }
+
+    /// Append the rows of several views with the same schema as the block, in order. Only the
+    /// selected rows of views with a selection are appended. The capacity is grown once and VARLEN
+    /// values are copied into the block's arenas, so the block doesn't borrow from the views.
+    pub fn concat<'v>(&mut self, srcs: &[&'v View<'v>]) -> Result<(), DBError> {
+        for &src in srcs {
+            check_append_schema(&self.schema, src.schema())?;
+        }
+
+        let added: usize = srcs.iter()
+            .map(|src| selection::selected_count(src.selection(), src.rows()))
+            .sum();
+
+        let total = self.rows + added;
+        if total > self.capacity {
+            if let Some(err) = self.set_capacity(round_up(total, 1024)) {
+                return Err(err)
+            }
+        }
+
+        for &src in srcs {
+            let at = self.rows;
+            let selected: Option<Vec<RowOffset>> = src.selection()
+                .map(|sel| sel.iter().collect());
+
+            for (pos, dst) in self.columns.iter_mut().enumerate() {
+                let col = src.column(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+
+                match selected {
+                    Some(ref rows) => kernel::gather(col, src.rows(), dst, at, rows)?,
+                    None => kernel::copy_range(col, src.rows(), dst, at, 0 .. src.rows())?,
+                }
+            }
+
+            self.rows += selected.map_or(src.rows(), |rows| rows.len());
+        }
+
+        Ok(())
+    }
+
     /// Mutable reference to column and its data.
     pub fn column_mut(&mut self, pos: usize) -> Option<&mut Column<'b>> {
         self.columns.get_mut(pos)
     }
+
+    /// Dictionary encode a TEXT / BLOB column (existing rows and rows added later)
+    pub fn encode_dictionary(&mut self, pos: usize) -> Result<(), DBError> {
+        let rows = self.rows;
+        self.columns.get_mut(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))
+            .and_then(|c| c.encode_dictionary(rows))
+    }
+
+    /// Convert a dictionary encoded column back into plain data
+    pub fn decode_dictionary(&mut self, pos: usize) -> Result<(), DBError> {
+        let rows = self.rows;
+        self.columns.get_mut(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))
+            .and_then(|c| c.decode_dictionary(rows))
+    }
+
+    /// VARLEN arena usage of all the columns
+    pub fn arena_stats(&self) -> ArenaStats {
+        let mut stats = ArenaStats::default();
+        for col in &self.columns {
+            stats.add(col.arena_stats(self.rows));
+        }
+
+        stats
+    }
+
+    /// Compact the VARLEN arenas of all the columns (see `Column::compact_arena`). Values
+    /// overwritten by setting rows again stay in the arenas until they're compacted.
+    pub fn compact_arenas(&mut self) -> Result<(), DBError> {
+        let rows = self.rows;
+        for col in &mut self.columns {
+            col.compact_arena(rows)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Block<'static> {
+    /// Move the columns into reference counted buffers, without copying the data. The resulting
+    /// view isn't tied to the block's lifetime (see `shared`).
+    pub fn into_shared(self) -> SharedView {
+        let rows = self.rows;
+        let columns = self.columns.into_iter()
+            .map(|col| SharedColumn::new(col, rows))
+            .collect();
+
+        SharedView::new(self.schema, columns, rows)
+    }
+}
+
+/// Appended views have to have the same column types (and type modifiers). Nullable columns can't
+/// be appended to non-nullable ones.
+fn check_append_schema(dst: &Schema, src: &Schema) -> Result<(), DBError> {
+    if dst.count() != src.count() {
+        return Err(DBError::ExpressionInputCount(format!("{} != {}", src.count(), dst.count())))
+    }
+
+    for (dattr, sattr) in dst.iter().zip(src.iter()) {
+        check_append_attr(dattr, sattr)?;
+    }
+
+    Ok(())
+}
+
+fn check_append_attr(dst: &Attribute, src: &Attribute) -> Result<(), DBError> {
+    if dst.dtype != src.dtype || dst.modifiers != src.modifiers
+        || dst.children.len() != src.children.len()
+    {
+        return Err(DBError::AttributeType(dst.name.clone()))
+    }
+
+    if src.nullable && !dst.nullable {
+        return Err(DBError::make_column_not_nullable(dst.name.clone()))
+    }
+
+    for (dchild, schild) in dst.children.iter().zip(src.children.iter()) {
+        check_append_attr(dchild, schild)?;
+    }
+
+    Ok(())
 }
 
 impl<'a> Index<usize> for Block<'a> {
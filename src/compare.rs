@@ -0,0 +1,294 @@
+// vim : set ts=4 sw=4 et :
+
+//! Comparison of the schema and rows of two views, with a report of the differences. Meant for
+//! tests, see `assert_views_eq!`.
+
+use std::fmt;
+
+use ::block::{View, column_value};
+use ::error::DBError;
+use ::format::{FormatOptions, format_value};
+use ::schema::Schema;
+use ::selection;
+use ::types::Value;
+
+/// Mismatching cells listed in a report by default
+pub const DEFAULT_MAX_MISMATCHES: usize = 10;
+
+/// Options of `views_equal` / `diff_views`
+#[derive(Clone)]
+pub struct CompareOptions {
+    /// Largest absolute difference of equal FLOAT32 / FLOAT64 values
+    pub float_tolerance: f64,
+    /// Compare the rows as a multiset, ignoring their order
+    pub ignore_order: bool,
+    /// NULL is equal to NULL. Otherwise a NULL cell never matches (SQL semantics).
+    pub null_equals_null: bool,
+    /// Mismatches listed in the report, the rest are only counted
+    pub max_mismatches: usize,
+}
+
+impl Default for CompareOptions {
+    fn default() -> CompareOptions {
+        CompareOptions {
+            float_tolerance: 0.0,
+            ignore_order: false,
+            null_equals_null: true,
+            max_mismatches: DEFAULT_MAX_MISMATCHES,
+        }
+    }
+}
+
+/// Single difference between two views
+#[derive(Clone, PartialEq, Debug)]
+pub enum Mismatch {
+    /// Number of columns (left, right)
+    ColumnCount(usize, usize),
+    /// `name:type` of the column at the position (left, right)
+    Column(usize, String, String),
+    /// Number of (selected) rows (left, right)
+    RowCount(usize, usize),
+    /// Row, column and the rendered cell values (left, right). Rows are positions among the
+    /// selected rows, or in sorted order when ignoring the row order.
+    Cell(usize, usize, String, String),
+}
+
+/// Differences between two views, empty if the views are equal
+pub struct ViewDiff {
+    /// The first `max_mismatches` differences
+    pub mismatches: Vec<Mismatch>,
+    /// Total number of differences
+    pub total: usize,
+}
+
+impl ViewDiff {
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    fn push(&mut self, mismatch: Mismatch, options: &CompareOptions) {
+        if self.mismatches.len() < options.max_mismatches {
+            self.mismatches.push(mismatch);
+        }
+        self.total += 1;
+    }
+}
+
+/// Compare two views, see `diff_views`
+pub fn views_equal<'a, 'b>(left: &'a View<'a>, right: &'b View<'b>, options: &CompareOptions)
+    -> Result<bool, DBError>
+{
+    diff_views(left, right, options).map(|diff| diff.is_empty())
+}
+
+/// Compare the schema and the (selected) rows of two views. Columns have to have the same names
+/// and types, nullability isn't compared. Rows are only compared if the schemas match.
+pub fn diff_views<'a, 'b>(left: &'a View<'a>, right: &'b View<'b>, options: &CompareOptions)
+    -> Result<ViewDiff, DBError>
+{
+    let mut diff = ViewDiff { mismatches: Vec::new(), total: 0 };
+
+    if !diff_schema(left.schema(), right.schema(), options, &mut diff) {
+        return Ok(diff)
+    }
+
+    let mut lrows = read_rows(left)?;
+    let mut rrows = read_rows(right)?;
+
+    if options.ignore_order {
+        lrows.sort();
+        rrows.sort();
+    }
+
+    if lrows.len() != rrows.len() {
+        diff.push(Mismatch::RowCount(lrows.len(), rrows.len()), options);
+    }
+
+    let schema = left.schema();
+    let format = FormatOptions::default();
+
+    for (row, (lrow, rrow)) in lrows.iter().zip(rrows.iter()).enumerate() {
+        for (col, (lvalue, rvalue)) in lrow.iter().zip(rrow.iter()).enumerate() {
+            if !cell_eq(lvalue, rvalue, options) {
+                let attr = &schema[col];
+                let cell = Mismatch::Cell(row, col,
+                                          format_value(lvalue, attr, &format),
+                                          format_value(rvalue, attr, &format));
+                diff.push(cell, options);
+            }
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Returns true if the schemas match (and rows can be compared)
+fn diff_schema(left: &Schema, right: &Schema, options: &CompareOptions, diff: &mut ViewDiff)
+    -> bool
+{
+    if left.count() != right.count() {
+        diff.push(Mismatch::ColumnCount(left.count(), right.count()), options);
+        return false
+    }
+
+    let mut matching = true;
+
+    for (pos, (lattr, rattr)) in left.iter().zip(right.iter()).enumerate() {
+        let lname = format!("{}:{}", lattr.name, lattr.type_name());
+        let rname = format!("{}:{}", rattr.name, rattr.type_name());
+
+        if lname != rname {
+            diff.push(Mismatch::Column(pos, lname, rname), options);
+            matching = false;
+        }
+    }
+
+    matching
+}
+
+fn read_rows<'v>(view: &'v View<'v>) -> Result<Vec<Vec<Value<'v>>>, DBError> {
+    let count = view.schema().count();
+    let mut out = Vec::with_capacity(selection::selected_count(view.selection(), view.rows()));
+
+    for row in selection::selected_rows(view.selection(), view.rows()) {
+        let mut values = Vec::with_capacity(count);
+
+        for pos in 0 .. count {
+            let col = view.column(pos)
+                .ok_or(DBError::make_column_unknown_pos(pos))?;
+            values.push(column_value(col, row)?);
+        }
+
+        out.push(values);
+    }
+
+    Ok(out)
+}
+
+fn float_eq(lhs: f64, rhs: f64, tolerance: f64) -> bool {
+    lhs == rhs || (lhs.is_nan() && rhs.is_nan()) || (lhs - rhs).abs() <= tolerance
+}
+
+fn cell_eq(lhs: &Value, rhs: &Value, options: &CompareOptions) -> bool {
+    match (lhs, rhs) {
+        (&Value::NULL, &Value::NULL) => options.null_equals_null,
+        (&Value::FLOAT32(l), &Value::FLOAT32(r)) =>
+            float_eq(l as f64, r as f64, options.float_tolerance),
+        (&Value::FLOAT64(l), &Value::FLOAT64(r)) => float_eq(l, r, options.float_tolerance),
+        (&Value::LIST(ref l), &Value::LIST(ref r)) |
+        (&Value::STRUCT(ref l), &Value::STRUCT(ref r)) =>
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)| cell_eq(l, r, options)),
+        _ => lhs == rhs,
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::ColumnCount(l, r) => write!(f, "column count: {} != {}", l, r),
+            Mismatch::Column(pos, ref l, ref r) => write!(f, "column {}: {} != {}", pos, l, r),
+            Mismatch::RowCount(l, r) => write!(f, "row count: {} != {}", l, r),
+            Mismatch::Cell(row, col, ref l, ref r) =>
+                write!(f, "row {}, column {}: {} != {}", row, col, l, r),
+        }
+    }
+}
+
+/// One mismatch per line, followed by the number of mismatches that weren't listed
+impl fmt::Display for ViewDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for mismatch in &self.mismatches {
+            writeln!(f, "{}", mismatch)?;
+        }
+
+        if self.total > self.mismatches.len() {
+            writeln!(f, "... and {} more", self.total - self.mismatches.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Assert that two views are equal (see `compare::diff_views`), panics with the difference
+/// report otherwise. Takes an optional `CompareOptions`.
+#[macro_export]
+macro_rules! assert_views_eq {
+    ($left:expr, $right:expr) => {
+        assert_views_eq!($left, $right, $crate::compare::CompareOptions::default())
+    };
+    ($left:expr, $right:expr, $options:expr) => {{
+        let diff = $crate::compare::diff_views($left, $right, &$options)
+            .expect("Error comparing views");
+
+        if !diff.is_empty() {
+            panic!("assertion failed: views are not equal\n{}", diff)
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use schema::Attribute;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn compare_rows() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("score", false, Type::FLOAT64),
+        ]).unwrap();
+
+        let mut left = Table::new(&allocator::GLOBAL, &schema, None);
+        let mut right = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut left)
+                .add_row().set(1 as i32).set(0.5 as f64)
+                .add_row().set(NULL_VALUE).set(1.0 as f64)
+                .add_row().set(3 as i32).set(2.0 as f64)
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+
+            let status = TableAppender::new(&mut right)
+                .add_row().set(3 as i32).set(2.0 as f64)
+                .add_row().set(1 as i32).set(0.5000001 as f64)
+                .add_row().set(NULL_VALUE).set(1.0 as f64)
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let diff = diff_views(left.block_ref(), right.block_ref(), &Default::default()).unwrap();
+        assert_eq!(diff.total, 6);
+        assert_eq!(diff.mismatches[0], Mismatch::Cell(0, 0, "1".into(), "3".into()));
+
+        let options = CompareOptions {
+            ignore_order: true,
+            float_tolerance: 1e-3,
+            .. Default::default()
+        };
+        assert_views_eq!(left.block_ref(), right.block_ref(), options);
+
+        let options = CompareOptions { null_equals_null: false, .. options };
+        assert!(!views_equal(left.block_ref(), right.block_ref(), &options).unwrap());
+    }
+
+    #[test]
+    fn compare_schema() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("score", false, Type::FLOAT64),
+        ]).unwrap();
+
+        let other = Schema::make_one_attr("id", true, Type::INT64);
+
+        let left = Table::new(&allocator::GLOBAL, &schema, None);
+        let right = Table::new(&allocator::GLOBAL, &other, None);
+
+        let diff = diff_views(left.block_ref(), right.block_ref(), &Default::default()).unwrap();
+        assert_eq!(diff.mismatches, vec![Mismatch::ColumnCount(2, 1)]);
+        assert_eq!(diff.to_string(), "column count: 2 != 1\n");
+    }
+}
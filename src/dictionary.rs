@@ -0,0 +1,249 @@
+// vim : set ts=4 sw=4 et :
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use ::allocator::{Allocator, ChainedArena, MIN_ALIGN};
+use ::bitmaps::Bitmap;
+use ::block::{Block, Encoding, RefColumn, column_nulls};
+use ::error::DBError;
+use ::row::RowOffset;
+use ::schema::Schema;
+use ::types::RawData;
+
+/// Native type of the dictionary codes stored in the row vector of dictionary encoded columns
+pub type DictionaryCode = u32;
+
+/// Starting size for the dictionary value arena
+const ARENA_MIN_SIZE : usize = MIN_ALIGN;
+
+/// Limit on dictionary arena chunk size, same as the `Column` VARLEN limit
+const ARENA_MAX_SIZE : usize = 16 * 1024 * 1024;
+
+/// Hash-able wrapper comparing the bytes `RawData` points to
+struct DictionaryKey(RawData);
+
+impl Hash for DictionaryKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let bytes: &[u8] = self.0.as_ref();
+        bytes.hash(state)
+    }
+}
+
+impl PartialEq for DictionaryKey {
+    fn eq(&self, other: &DictionaryKey) -> bool {
+        let lhs: &[u8] = self.0.as_ref();
+        let rhs: &[u8] = other.0.as_ref();
+        lhs == rhs
+    }
+}
+
+impl Eq for DictionaryKey {}
+
+/// Set of distinct VARLEN values of a dictionary encoded column.
+///
+/// Values are stored in their own arena, codes are the index of the value in `values()`.
+pub struct Dictionary<'alloc> {
+    values: Vec<RawData>,
+    index: HashMap<DictionaryKey, DictionaryCode>,
+    arena: ChainedArena<'alloc>,
+}
+
+// RawData pointers only point into the dictionary's own arena
+unsafe impl<'alloc> Send for Dictionary<'alloc> {}
+unsafe impl<'alloc> Sync for Dictionary<'alloc> {}
+
+impl<'alloc> Dictionary<'alloc> {
+    pub fn new(alloc: &'alloc Allocator) -> Dictionary<'alloc> {
+        Dictionary {
+            values: Vec::new(),
+            index: HashMap::new(),
+            arena: ChainedArena::new(alloc, ARENA_MIN_SIZE, ARENA_MAX_SIZE),
+        }
+    }
+
+    /// Distinct values, indexed by code
+    pub fn values(&self) -> &[RawData] {
+        self.values.as_slice()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Lookup code of an existing value
+    pub fn find(&self, data: &[u8]) -> Option<DictionaryCode> {
+        let key = DictionaryKey(RawData { data: data.as_ptr() as *mut u8, size: data.len() });
+        self.index.get(&key).cloned()
+    }
+
+    /// Return the code for the value, adding it to the dictionary if it's not present
+    pub fn intern(&mut self, data: &[u8]) -> Result<DictionaryCode, DBError> {
+        if let Some(code) = self.find(data) {
+            return Ok(code)
+        }
+
+        if self.values.len() > DictionaryCode::max_value() as usize {
+            return Err(DBError::MemoryLimit)
+        }
+
+        let ptr = self.arena.append(data)?.1;
+        let value = RawData { data: ptr, size: data.len() };
+        let code = self.values.len() as DictionaryCode;
+
+        self.values.push(value);
+        self.index.insert(DictionaryKey(value), code);
+        Ok(code)
+    }
+}
+
+/// Row data of dictionary encoded column. Codes index into values.
+pub struct DictionaryRows<'a> {
+    pub codes: &'a [DictionaryCode],
+    pub nulls: Bitmap<'a>,
+    pub values: &'a [RawData],
+}
+
+/// Dictionary codes, null vector and dictionary values of a dictionary encoded column.
+pub fn dictionary_row_data<'c>(col: &'c RefColumn<'c>) -> Result<DictionaryRows<'c>, DBError> {
+    if col.encoding() != Encoding::Dictionary {
+        return Err(DBError::ColumnEncoding(col.attribute().name.clone()))
+    }
+
+    let rows = col.capacity();
+    let raw = col.rows_raw_slice();
+
+    unsafe {
+        let codes: *const DictionaryCode = raw.as_ptr() as *const DictionaryCode;
+
+        Ok(DictionaryRows {
+            codes: ::std::slice::from_raw_parts(codes, rows),
+            nulls: column_nulls(col),
+            values: col.dictionary().unwrap_or(&[]),
+        })
+    }
+}
+
+/// Decode a dictionary encoded column into a single column `Block` with plain TEXT / BLOB data.
+///
+/// Used by operations that don't know how to work on dictionary codes.
+pub fn decode_column<'alloc, 'c>(
+    alloc: &'alloc Allocator,
+    col: &'c RefColumn<'c>,
+    rows: RowOffset
+) -> Result<Block<'alloc>, DBError>
+{
+    let src = dictionary_row_data(col)?;
+    let attr = col.attribute().clone();
+
+    if rows > src.codes.len() {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    let mut out = Block::new(alloc, &Schema::from_attr(attr.clone()));
+    out.add_rows(rows)?;
+
+    {
+        let dst = out.column_mut(0).unwrap();
+
+        for idx in 0 .. rows {
+            if attr.nullable {
+                let null = src.nulls.get(idx);
+                dst.nulls_mut()?.set(idx, null);
+
+                if null {
+                    continue
+                }
+            }
+
+            let code = src.codes[idx] as usize;
+            let value = match src.values.get(code) {
+                Some(value) => value,
+                None => return Err(DBError::InvalidColumn(
+                    format!("{} (dictionary code {} out of range)", attr.name, code))),
+            };
+
+            dst.set_varlen(idx, value.as_ref())?;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use block::{View, column_row_data};
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn encode_decode() {
+        let schema = Schema::make_one_attr("country", false, Type::TEXT);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("us")
+                .add_row().set("pl")
+                .add_row().set("us")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut block = table.take().unwrap();
+        block.encode_dictionary(0).unwrap();
+
+        {
+            let col = block.column(0).unwrap();
+            assert_eq!(col.encoding(), Encoding::Dictionary);
+
+            let rows = dictionary_row_data(col).unwrap();
+            assert_eq!(rows.values.len(), 2);
+            assert_eq!(&rows.codes[0..3], &[0, 1, 0]);
+
+            // Plain accessors refuse to interpret codes as values
+            assert!(column_row_data::<Text>(col).is_err());
+        }
+
+        let rows = block.rows();
+        let decoded = decode_column(&allocator::GLOBAL, block.column(0).unwrap(), rows).unwrap();
+
+        let col = decoded.column(0).unwrap();
+        let values = column_row_data::<Text>(col).unwrap();
+        assert_eq!(values.values[1].as_ref() as &str, "pl");
+        assert_eq!(values.values[2].as_ref() as &str, "us");
+    }
+
+    #[test]
+    fn decode_past_capacity() {
+        let schema = Schema::make_one_attr("country", false, Type::TEXT);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("us")
+                .add_row().set("pl")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut block = table.take().unwrap();
+        block.encode_dictionary(0).unwrap();
+
+        let capacity = block.column(0).unwrap().capacity();
+        assert!(decode_column(&allocator::GLOBAL, block.column(0).unwrap(), capacity + 1).is_err());
+
+        // A failed decode leaves the column dictionary encoded
+        assert!(block.column_mut(0).unwrap().decode_dictionary(capacity + 1).is_err());
+        assert_eq!(block.column(0).unwrap().encoding(), Encoding::Dictionary);
+
+        block.decode_dictionary(0).unwrap();
+        let col = block.column(0).unwrap();
+        assert_eq!(col.encoding(), Encoding::Plain);
+        assert_eq!(column_row_data::<Text>(col).unwrap().values[1].as_ref() as &str, "pl");
+    }
+}
@@ -12,6 +12,8 @@ pub enum DBError {
     IO(IOError),
     // Unknown type (parsing external type information)
     UnknownType(String),
+    /// Malformed value (parsing external value representation)
+    ValueParse(String),
     /// Referencing a missing schema attribute (name or position)
     AttributeMissing(String),
     /// Mismatched expectation about attributes nullability
@@ -20,12 +22,22 @@ pub enum DBError {
     AttributeType(String),
     /// Duplicate attribute in result schema
     AttributeDuplicate(String),
+    /// Column data is not in the expected encoding (eg. dictionary encoded)
+    ColumnEncoding(String),
     ///
     ExpressionInputType(String),
     ExpressionInputCount(String),
     ExpressionNotCost,
+    /// Numeric value does not fit into the destination type
+    NumericOverflow(String),
     ///
     RowOutOfBounds,
+    /// Malformed selection vector (eg. row positions out of order)
+    InvalidSelection(String),
+    /// Serialized data is malformed or fails its checksum
+    CorruptData(String),
+    /// Column data is inconsistent (see `validate`)
+    InvalidColumn(String),
     /// Unknown memory allocation error
     Memory(AllocErr),
     /// Memory allocation limit reached (via policy)
@@ -51,6 +63,8 @@ impl fmt::Display for DBError {
                 write!(f, "IO Error {}", e),
             DBError::UnknownType(ref t) =>
                 write!(f, "Unknown/Unexpected Type {}", t),
+            DBError::ValueParse(ref v) =>
+                write!(f, "Malformed value {}", v),
             DBError::AttributeMissing(ref attr) =>
                 write!(f, "Unknown Attribute {}", attr),
             DBError::AttributeNullability(ref attr) =>
@@ -59,14 +73,24 @@ impl fmt::Display for DBError {
                 write!(f, "Attribute Type Mismatch {}", attr),
             DBError::AttributeDuplicate(ref attr) =>
                 write!(f, "Duplicate Attribute name {} in output schema", attr),
+            DBError::ColumnEncoding(ref attr) =>
+                write!(f, "Unexpected column encoding {}", attr),
             DBError::ExpressionInputType(ref str) =>
                 write!(f, "Invalid expression input type: {}", str),
             DBError::ExpressionInputCount(ref str) =>
                 write!(f, "Invalid expression input count: {}", str),
             DBError::ExpressionNotCost =>
                 write!(f, "Expression expected to be const"),
+            DBError::NumericOverflow(ref str) =>
+                write!(f, "Numeric overflow: {}", str),
             DBError::RowOutOfBounds =>
                 write!(f, "Row out of bounds"),
+            DBError::InvalidSelection(ref str) =>
+                write!(f, "Invalid selection vector: {}", str),
+            DBError::CorruptData(ref str) =>
+                write!(f, "Corrupt data: {}", str),
+            DBError::InvalidColumn(ref str) =>
+                write!(f, "Invalid column data: {}", str),
             DBError::Memory(ref e) =>
                 write!(f, "Memory allocation failure: {}", e),
             DBError::MemoryLimit =>
@@ -0,0 +1,421 @@
+use ::allocator::Allocator;
+use ::block::{Block, Column, View, column_row_data};
+use ::error::DBError;
+use ::expression::*;
+use ::expression::convert::implicit_cast;
+use ::row::RowOffset;
+use ::schema::{self, Attribute, Schema, TypeModifiers};
+use ::selection::{selected_rows, SelectedRows};
+use ::types::*;
+use ::util::decimal;
+use ::util::math::Numeric;
+
+/// Binary arithmetic operator
+#[derive(Clone, Copy, PartialEq)]
+pub enum ArithmeticOp {
+    ADD,
+    SUB,
+}
+
+impl ArithmeticOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ArithmeticOp::ADD => "+",
+            ArithmeticOp::SUB => "-",
+        }
+    }
+}
+
+/// Binary arithmetic expression (`lhs + rhs`, `lhs - rhs`)
+///
+/// Numeric inputs are implicitly cast to their common supertype, which is also the result type
+/// (DECIMAL gains one digit of precision). For temporal arithmetic see `temporal_result_type`.
+pub struct ArithmeticExpr<'b> {
+    pub op: ArithmeticOp,
+    pub lhs: Box<Expr<'b> + 'b>,
+    pub rhs: Box<Expr<'b> + 'b>,
+}
+
+/// Combination of input types for temporal arithmetic
+#[derive(Clone, Copy)]
+enum TemporalKernel {
+    /// TIMESTAMP +- INTERVAL
+    TimestampInterval,
+    /// INTERVAL + TIMESTAMP
+    IntervalTimestamp,
+    /// DATE +- INTERVAL
+    DateInterval,
+    /// INTERVAL + DATE
+    IntervalDate,
+    /// INTERVAL +- INTERVAL
+    IntervalInterval,
+    /// TIMESTAMP - TIMESTAMP
+    TimestampTimestamp,
+    /// DATE - DATE
+    DateDate,
+}
+
+/// Both inputs are of the result type
+struct NumericBound<'alloc: 'b, 'b> {
+    alloc: &'alloc Allocator,
+    schema: Schema,
+    op: ArithmeticOp,
+    lhs: Box<BoundExpr<'alloc> + 'b>,
+    rhs: Box<BoundExpr<'alloc> + 'b>,
+}
+
+struct TemporalBound<'alloc: 'b, 'b> {
+    alloc: &'alloc Allocator,
+    schema: Schema,
+    op: ArithmeticOp,
+    kernel: TemporalKernel,
+    lhs: Box<BoundExpr<'alloc> + 'b>,
+    rhs: Box<BoundExpr<'alloc> + 'b>,
+}
+
+fn temporal_kernel(op: ArithmeticOp, lhs: Type, rhs: Type) -> Option<(TemporalKernel, Type)> {
+    match (op, lhs, rhs) {
+        (_, Type::TIMESTAMP, Type::INTERVAL) =>
+            Some((TemporalKernel::TimestampInterval, Type::TIMESTAMP)),
+        (ArithmeticOp::ADD, Type::INTERVAL, Type::TIMESTAMP) =>
+            Some((TemporalKernel::IntervalTimestamp, Type::TIMESTAMP)),
+        (_, Type::DATE, Type::INTERVAL) =>
+            Some((TemporalKernel::DateInterval, Type::TIMESTAMP)),
+        (ArithmeticOp::ADD, Type::INTERVAL, Type::DATE) =>
+            Some((TemporalKernel::IntervalDate, Type::TIMESTAMP)),
+        (_, Type::INTERVAL, Type::INTERVAL) =>
+            Some((TemporalKernel::IntervalInterval, Type::INTERVAL)),
+        (ArithmeticOp::SUB, Type::TIMESTAMP, Type::TIMESTAMP) =>
+            Some((TemporalKernel::TimestampTimestamp, Type::INTERVAL)),
+        (ArithmeticOp::SUB, Type::DATE, Type::DATE) =>
+            Some((TemporalKernel::DateDate, Type::INT32)),
+        _ => None,
+    }
+}
+
+/// Result type of temporal arithmetic.
+///
+/// - `TIMESTAMP +- INTERVAL`, `INTERVAL + TIMESTAMP` => `TIMESTAMP`
+/// - `DATE +- INTERVAL`, `INTERVAL + DATE` => `TIMESTAMP`
+/// - `INTERVAL +- INTERVAL` => `INTERVAL`
+/// - `TIMESTAMP - TIMESTAMP` => `INTERVAL`
+/// - `DATE - DATE` => `INT32` (days)
+pub fn temporal_result_type(op: ArithmeticOp, lhs: Type, rhs: Type) -> Result<Type, DBError> {
+    temporal_kernel(op, lhs, rhs)
+        .map(|(_, out)| out)
+        .ok_or_else(|| DBError::ExpressionInputType(
+            format!("{} {} {}", lhs.name(), op.symbol(), rhs.name())))
+}
+
+impl<'a> ArithmeticExpr<'a> {
+    pub fn new<L, R>(op: ArithmeticOp, lhs: L, rhs: R) -> ArithmeticExpr<'a>
+        where L: Expr<'a> + 'a, R: Expr<'a> + 'a
+    {
+        ArithmeticExpr { op: op, lhs: box lhs, rhs: box rhs }
+    }
+
+    pub fn add<L, R>(lhs: L, rhs: R) -> ArithmeticExpr<'a>
+        where L: Expr<'a> + 'a, R: Expr<'a> + 'a
+    {
+        ArithmeticExpr::new(ArithmeticOp::ADD, lhs, rhs)
+    }
+
+    pub fn sub<L, R>(lhs: L, rhs: R) -> ArithmeticExpr<'a>
+        where L: Expr<'a> + 'a, R: Expr<'a> + 'a
+    {
+        ArithmeticExpr::new(ArithmeticOp::SUB, lhs, rhs)
+    }
+}
+
+/// Output attribute of a single column bound expression
+fn single_attr<'a, 'b, 'c>(expr: &'c Box<BoundExpr<'a> + 'b>) -> Result<&'c Attribute, DBError> {
+    let schema = expr.schema();
+
+    if schema.count() != 1 {
+        return Err(DBError::ExpressionInputCount(format!("{} != 1", schema.count())))
+    }
+
+    schema.get(0)
+}
+
+/// Input types the operation is evaluated on (after implicit casts) and the result attribute
+enum Binding {
+    Numeric(Type, TypeModifiers, Attribute),
+    Temporal(TemporalKernel, Type, Attribute),
+}
+
+fn binding(op: ArithmeticOp, lattr: &Attribute, rattr: &Attribute) -> Result<Binding, DBError> {
+    let nullable = lattr.nullable || rattr.nullable;
+    let error = || DBError::ExpressionInputType(
+        format!("{} {} {}", lattr.dtype.name(), op.symbol(), rattr.dtype.name()));
+
+    if let Some((kernel, out_type)) = temporal_kernel(op, lattr.dtype, rattr.dtype) {
+        let mut attr = lattr.cast(out_type);
+        attr.nullable = nullable;
+        return Ok(Binding::Temporal(kernel, lattr.dtype, attr))
+    }
+
+    let (dtype, modifiers) = schema::common_supertype_attr(lattr, rattr).ok_or_else(&error)?;
+
+    if dtype.is_numeric() {
+        let out_mods = if dtype == Type::DECIMAL {
+            let (p, s) = (modifiers.precision, modifiers.scale);
+            let (p, s) = decimal::add_result(p, s, p, s);
+            TypeModifiers::decimal(p, s)
+        } else {
+            modifiers
+        };
+
+        let mut attr = lattr.cast_with(dtype, out_mods);
+        attr.nullable = nullable;
+        return Ok(Binding::Numeric(dtype, modifiers, attr))
+    }
+
+    // Mixed temporal types (TIMESTAMP - DATE)
+    if dtype != lattr.dtype || dtype != rattr.dtype {
+        if let Some((kernel, out_type)) = temporal_kernel(op, dtype, dtype) {
+            let mut attr = lattr.cast(out_type);
+            attr.nullable = nullable;
+            return Ok(Binding::Temporal(kernel, dtype, attr))
+        }
+    }
+
+    Err(error())
+}
+
+impl<'b> Expr<'b> for ArithmeticExpr<'b> {
+    fn bind<'a: 'b>(&self, alloc: &'a Allocator, input_schema: &Schema)
+        -> Result<Box<BoundExpr<'a> + 'b>, DBError>
+    {
+        let lhs = self.lhs.bind(alloc, input_schema)?;
+        let rhs = self.rhs.bind(alloc, input_schema)?;
+
+        let binding = binding(self.op, single_attr(&lhs)?, single_attr(&rhs)?)?;
+
+        match binding {
+            Binding::Numeric(dtype, modifiers, out_attr) => Ok(box NumericBound {
+                alloc: alloc,
+                schema: Schema::from_attr(out_attr),
+                op: self.op,
+                lhs: implicit_cast(alloc, lhs, dtype, modifiers)?,
+                rhs: implicit_cast(alloc, rhs, dtype, modifiers)?,
+            }),
+            Binding::Temporal(kernel, dtype, out_attr) => {
+                // Only cast when both sides are brought to a common type
+                let (lhs, rhs) = match kernel {
+                    TemporalKernel::TimestampTimestamp | TemporalKernel::DateDate => (
+                        implicit_cast(alloc, lhs, dtype, Default::default())?,
+                        implicit_cast(alloc, rhs, dtype, Default::default())?,
+                    ),
+                    _ => (lhs, rhs),
+                };
+
+                Ok(box TemporalBound {
+                    alloc: alloc,
+                    schema: Schema::from_attr(out_attr),
+                    op: self.op,
+                    kernel: kernel,
+                    lhs: lhs,
+                    rhs: rhs,
+                })
+            }
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.lhs.is_constant() && self.rhs.is_constant()
+    }
+}
+
+/// Apply a binary function row by row. Rows where either input is NULL produce a NULL.
+fn apply<L, R, O, F>(lhs: &Column, rhs: &Column, out: &mut Column, rows: SelectedRows, f: F)
+    -> Result<(), DBError>
+    where L: ValueInfo, R: ValueInfo, O: ValueInfo,
+          F: Fn(&L::Store, &R::Store) -> Result<O::Store, DBError>
+{
+    let lrows = column_row_data::<L>(lhs)?;
+    let rrows = column_row_data::<R>(rhs)?;
+    let mut dst = out.row_data_mut::<O>()?;
+
+    for idx in rows {
+        if lrows.nulls.is_set(idx) || rrows.nulls.is_set(idx) {
+            dst.nulls.set(idx, true);
+            continue;
+        }
+
+        if !dst.nulls.is_empty() {
+            dst.nulls.set(idx, false);
+        }
+
+        dst.values[idx] = f(&lrows.values[idx], &rrows.values[idx])?;
+    }
+
+    Ok(())
+}
+
+fn numeric_apply<T>(op: ArithmeticOp, l: &Column, r: &Column, dst: &mut Column, rows: SelectedRows)
+    -> Result<(), DBError>
+    where T: ValueInfo, T::Store: Numeric
+{
+    apply::<T, T, T, _>(l, r, dst, rows, |a, b| {
+        let out = match op {
+            ArithmeticOp::ADD => a.checked_add(*b),
+            ArithmeticOp::SUB => a.checked_sub(*b),
+        };
+
+        out.ok_or_else(|| DBError::NumericOverflow(format!("{} {}", T::ENUM.name(), op.symbol())))
+    })
+}
+
+impl<'alloc, 'b> BoundExpr<'alloc> for NumericBound<'alloc, 'b> {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError> {
+        let lhs = self.lhs.evaluate(view, rows)?;
+        let rhs = self.rhs.evaluate(view, rows)?;
+
+        let mut out = Block::new(self.alloc, &self.schema);
+        out.add_rows(rows)?;
+
+        {
+            let (l, r) = (&lhs[0], &rhs[0]);
+            let selected = selected_rows(view.selection(), rows);
+            let dtype = self.schema[0].dtype;
+            let precision = self.schema[0].modifiers.precision;
+            let dst = out.column_mut(0).unwrap();
+            let op = self.op;
+
+            match dtype {
+                // Inputs have the same scale as the result
+                Type::DECIMAL => apply::<Decimal, Decimal, Decimal, _>(l, r, dst, selected, |a, b| {
+                    let out = match op {
+                        ArithmeticOp::ADD => a.checked_add(*b),
+                        ArithmeticOp::SUB => a.checked_sub(*b),
+                    };
+
+                    out.and_then(|v| if decimal::fits(v, precision) { Some(v) } else { None })
+                        .ok_or_else(|| DBError::NumericOverflow(format!("DECIMAL {}", op.symbol())))
+                }),
+                dtype =>
+                    numeric_dispatch!(dtype, T => numeric_apply::<T>(op, l, r, dst, selected)),
+            }?;
+        }
+
+        Ok(out)
+    }
+}
+
+impl<'alloc, 'b> BoundExpr<'alloc> for TemporalBound<'alloc, 'b> {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError> {
+        let lhs = self.lhs.evaluate(view, rows)?;
+        let rhs = self.rhs.evaluate(view, rows)?;
+
+        let mut out = Block::new(self.alloc, &self.schema);
+        out.add_rows(rows)?;
+
+        {
+            let (l, r) = (&lhs[0], &rhs[0]);
+            let selected = selected_rows(view.selection(), rows);
+            let dst = out.column_mut(0).unwrap();
+            let add = self.op == ArithmeticOp::ADD;
+
+            match self.kernel {
+                TemporalKernel::TimestampInterval =>
+                    apply::<Timestamp, Interval, Timestamp, _>(l, r, dst, selected, |ts, iv| {
+                        if add { ts.add_interval(iv) } else { ts.sub_interval(iv) }
+                    }),
+                TemporalKernel::IntervalTimestamp =>
+                    apply::<Interval, Timestamp, Timestamp, _>(l, r, dst, selected, |iv, ts| {
+                        ts.add_interval(iv)
+                    }),
+                TemporalKernel::DateInterval =>
+                    apply::<Date, Interval, Timestamp, _>(l, r, dst, selected, |d, iv| {
+                        let ts = d.to_timestamp();
+                        if add { ts.add_interval(iv) } else { ts.sub_interval(iv) }
+                    }),
+                TemporalKernel::IntervalDate =>
+                    apply::<Interval, Date, Timestamp, _>(l, r, dst, selected, |iv, d| {
+                        d.to_timestamp().add_interval(iv)
+                    }),
+                TemporalKernel::IntervalInterval =>
+                    apply::<Interval, Interval, Interval, _>(l, r, dst, selected, |a, b| {
+                        if add { a.add(b) } else { a.add(&b.negate()) }
+                    }),
+                TemporalKernel::TimestampTimestamp =>
+                    apply::<Timestamp, Timestamp, Interval, _>(l, r, dst, selected, |a, b| {
+                        a.sub(b)
+                    }),
+                TemporalKernel::DateDate =>
+                    apply::<Date, Date, Int32, _>(l, r, dst, selected, |a, b| {
+                        a.0.checked_sub(b.0)
+                            .ok_or(DBError::NumericOverflow(String::from("DATE - DATE")))
+                    }),
+            }?;
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temporal_binding_rules() {
+        let add = |l, r| temporal_result_type(ArithmeticOp::ADD, l, r).ok();
+        let sub = |l, r| temporal_result_type(ArithmeticOp::SUB, l, r).ok();
+
+        assert!(add(Type::TIMESTAMP, Type::INTERVAL) == Some(Type::TIMESTAMP));
+        assert!(add(Type::INTERVAL, Type::TIMESTAMP) == Some(Type::TIMESTAMP));
+        assert!(sub(Type::DATE, Type::INTERVAL) == Some(Type::TIMESTAMP));
+        assert!(sub(Type::TIMESTAMP, Type::TIMESTAMP) == Some(Type::INTERVAL));
+        assert!(sub(Type::DATE, Type::DATE) == Some(Type::INT32));
+
+        // Not meaningful
+        assert!(add(Type::TIMESTAMP, Type::TIMESTAMP).is_none());
+        assert!(sub(Type::INTERVAL, Type::TIMESTAMP).is_none());
+        assert!(add(Type::TIMESTAMP, Type::INT64).is_none());
+    }
+
+    #[test]
+    fn numeric_binding_rules() {
+        let bind = |l: Attribute, r: Attribute| match binding(ArithmeticOp::ADD, &l, &r) {
+            Ok(Binding::Numeric(dtype, mods, out)) => Some((dtype, mods, out)),
+            _ => None,
+        };
+
+        let (dtype, _, out) = bind(
+            Attribute::new("a", false, Type::INT32),
+            Attribute::new("b", true, Type::INT64)).unwrap();
+        assert!(dtype == Type::INT64 && out.dtype == Type::INT64);
+        assert!(out.nullable);
+
+        // INT32 needs 10 integral digits; result gets one more digit
+        let (dtype, mods, out) = bind(
+            Attribute::new("a", false, Type::INT32),
+            Attribute::decimal("b", false, 5, 2).unwrap()).unwrap();
+        assert!(dtype == Type::DECIMAL);
+        assert!(mods == TypeModifiers::decimal(12, 2));
+        assert!(out.modifiers == TypeModifiers::decimal(13, 2));
+
+        assert!(bind(
+            Attribute::new("a", false, Type::INT32),
+            Attribute::new("b", false, Type::TEXT)).is_none());
+
+        // TIMESTAMP - DATE is evaluated as TIMESTAMP - TIMESTAMP
+        let ts = Attribute::new("a", false, Type::TIMESTAMP);
+        let date = Attribute::new("b", false, Type::DATE);
+        match binding(ArithmeticOp::SUB, &ts, &date) {
+            Ok(Binding::Temporal(_, dtype, out)) =>
+                assert!(dtype == Type::TIMESTAMP && out.dtype == Type::INTERVAL),
+            _ => panic!("TIMESTAMP - DATE not bound"),
+        }
+    }
+}
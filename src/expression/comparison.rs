@@ -1,46 +1,148 @@
-use std::cmp::Eq;
 use std::marker::PhantomData;
 
+use ::block::column_row_data;
 use ::expression::*;
+use ::expression::convert::implicit_cast;
 use ::error::DBError;
-use ::types::ValueInfo;
+use ::schema::{self, Attribute};
+use ::selection::selected_rows;
+use ::types::*;
 
 pub struct EqaulsExpr<'a> {
     pub lhs: Box<Expr<'a> + 'a>,
     pub rhs: Box<Expr<'a> + 'a>,
 }
 
-struct EqualsBound<'a, T: 'a + ValueInfo> {
+/// Both inputs are of the same type `T` (implicitly cast to their common supertype)
+struct EqualsBound<'a: 'b, 'b, T: 'a + ValueInfo> {
     alloc: &'a Allocator,
     schema: Schema, // TODO: Can this just be a static?
+    lhs: Box<BoundExpr<'a> + 'b>,
+    rhs: Box<BoundExpr<'a> + 'b>,
+    eq: fn(&T::Store, &T::Store) -> bool,
     phantom: PhantomData<&'a T>,
 }
 
 impl<'a> EqaulsExpr<'a> {
-    pub fn new<T: Expr<'a> + 'a>(lhs: T, rhs: T) -> EqaulsExpr<'a> {
+    pub fn new<L, R>(lhs: L, rhs: R) -> EqaulsExpr<'a>
+        where L: Expr<'a> + 'a, R: Expr<'a> + 'a
+    {
         EqaulsExpr { lhs: box lhs, rhs: box rhs }
     }
 }
 
+fn store_eq<V: PartialEq>(lhs: &V, rhs: &V) -> bool {
+    lhs == rhs
+}
+
+/// The same instant, regardless of the time zone offset
+fn timestamp_eq(lhs: &TimestampValue, rhs: &TimestampValue) -> bool {
+    lhs.micros == rhs.micros
+}
+
+fn bound_equals<'a: 'b, 'b, T: ValueInfo + 'a>(
+    alloc: &'a Allocator,
+    schema: Schema,
+    lhs: Box<BoundExpr<'a> + 'b>,
+    rhs: Box<BoundExpr<'a> + 'b>,
+    eq: fn(&T::Store, &T::Store) -> bool
+) -> Box<BoundExpr<'a> + 'b>
+{
+    box EqualsBound::<T> {
+        alloc: alloc,
+        schema: schema,
+        lhs: lhs,
+        rhs: rhs,
+        eq: eq,
+        phantom: PhantomData,
+    }
+}
+
 impl<'b> Expr<'b> for EqaulsExpr<'b> {
     fn bind <'a: 'b> (&self, alloc: &'a Allocator, input_schema: &Schema) ->
-        Result <Box<BoundExpr<'a> + 'a>, DBError>
+        Result <Box<BoundExpr<'a> + 'b>, DBError>
     {
-        Err(DBError::Unknown)
+        let lhs = self.lhs.bind(alloc, input_schema)?;
+        let rhs = self.rhs.bind(alloc, input_schema)?;
+
+        let (dtype, modifiers, schema) = {
+            let lattr = lhs.schema().get(0)?;
+            let rattr = rhs.schema().get(0)?;
+
+            let (dtype, modifiers) = schema::common_supertype_attr(lattr, rattr)
+                .ok_or_else(|| DBError::ExpressionInputType(
+                    format!("{} = {}", lattr.dtype.name(), rattr.dtype.name())))?;
+
+            let nullable = lattr.nullable || rattr.nullable;
+            let out = Attribute::new(lattr.name.clone(), nullable, Type::BOOLEAN);
+            (dtype, modifiers, Schema::from_attr(out))
+        };
+
+        let lhs = implicit_cast(alloc, lhs, dtype, modifiers)?;
+        let rhs = implicit_cast(alloc, rhs, dtype, modifiers)?;
+
+        let bound = match dtype {
+            Type::UINT8 => bound_equals::<UInt8>(alloc, schema, lhs, rhs, store_eq),
+            Type::UINT16 => bound_equals::<UInt16>(alloc, schema, lhs, rhs, store_eq),
+            Type::UINT32 => bound_equals::<UInt32>(alloc, schema, lhs, rhs, store_eq),
+            Type::UINT64 => bound_equals::<UInt64>(alloc, schema, lhs, rhs, store_eq),
+            Type::INT8 => bound_equals::<Int8>(alloc, schema, lhs, rhs, store_eq),
+            Type::INT16 => bound_equals::<Int16>(alloc, schema, lhs, rhs, store_eq),
+            Type::INT32 => bound_equals::<Int32>(alloc, schema, lhs, rhs, store_eq),
+            Type::INT64 => bound_equals::<Int64>(alloc, schema, lhs, rhs, store_eq),
+            Type::FLOAT32 => bound_equals::<Float32>(alloc, schema, lhs, rhs, store_eq),
+            Type::FLOAT64 => bound_equals::<Float64>(alloc, schema, lhs, rhs, store_eq),
+            Type::BOOLEAN => bound_equals::<Boolean>(alloc, schema, lhs, rhs, store_eq),
+            Type::DATE => bound_equals::<Date>(alloc, schema, lhs, rhs, store_eq),
+            Type::TIMESTAMP =>
+                bound_equals::<Timestamp>(alloc, schema, lhs, rhs, timestamp_eq),
+            Type::INTERVAL => bound_equals::<Interval>(alloc, schema, lhs, rhs, store_eq),
+            // Both sides have the same scale
+            Type::DECIMAL => bound_equals::<Decimal>(alloc, schema, lhs, rhs, store_eq),
+            Type::UUID => bound_equals::<Uuid>(alloc, schema, lhs, rhs, store_eq),
+            // TODO: VARLEN, FIXED_BINARY & nested types
+            other => return Err(DBError::ExpressionInputType(String::from(other.name()))),
+        };
+
+        Ok(bound)
+    }
+
+    fn is_constant(&self) -> bool {
+        self.lhs.is_constant() && self.rhs.is_constant()
     }
 }
 
-impl<'alloc, T: ValueInfo, V: Eq> BoundExpr<'alloc> for EqualsBound<'alloc, T>
-    where T: ValueInfo<Store=V>
-{
-    default fn schema(&self) -> &Schema {
+impl<'alloc, 'b, T: ValueInfo> BoundExpr<'alloc> for EqualsBound<'alloc, 'b, T> {
+    fn schema(&self) -> &Schema {
         &self.schema
     }
 
     fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError> {
+        let lhs = self.lhs.evaluate(view, rows)?;
+        let rhs = self.rhs.evaluate(view, rows)?;
+
         let mut out = Block::new(self.alloc, &self.schema);
+        out.add_rows(rows)?;
+
+        {
+            let lrows = column_row_data::<T>(&lhs[0])?;
+            let rrows = column_row_data::<T>(&rhs[0])?;
+            let mut dst = out.column_mut(0).unwrap().row_data_mut::<Boolean>()?;
+
+            for idx in selected_rows(view.selection(), rows) {
+                if lrows.nulls.is_set(idx) || rrows.nulls.is_set(idx) {
+                    dst.nulls.set(idx, true);
+                    continue;
+                }
+
+                if !dst.nulls.is_empty() {
+                    dst.nulls.set(idx, false);
+                }
+
+                dst.values[idx] = (self.eq)(&lrows.values[idx], &rrows.values[idx]);
+            }
+        }
 
         Ok(out)
     }
 }
-
@@ -1,20 +1,34 @@
+use std::fmt::Write;
 use std::marker::PhantomData;
 use std::string::ToString;
 
 use ::allocator::Allocator;
-use ::block::{Block, View, column_row_data};
+use ::block::{Block, Column, RefColumn, View, column_row_data, fixed_row_data};
 use ::error::DBError;
 use ::expression::*;
 use ::row::RowOffset;
-use ::schema::Schema;
+use ::schema::{self, Attribute, Schema, TypeModifiers};
+use ::selection::{selected_rows, SelectedRows};
 use ::types::*;
 use ::util::copy_value::ValueSetter;
+use ::util::decimal;
+use ::util::math::Numeric;
 
 pub struct CastExpr<'b> {
     pub to: Type,
+    /// Type modifiers of the result, DECIMAL casts default to the modifiers needed to hold the
+    /// input values (for integer / DECIMAL inputs)
+    pub modifiers: Option<TypeModifiers>,
     pub input: Box<Expr<'b> + 'b>,
 }
 
+/// Converts the input column into the output attribute type (both explicit and implicit casts)
+struct CastBound<'alloc: 'b, 'b> {
+    alloc: &'alloc Allocator,
+    schema: Schema,
+    input: Box<BoundExpr<'alloc> + 'b>,
+}
+
 pub struct ToStr<'b> {
     pub input: Box<Expr<'b> + 'b>,
 }
@@ -25,11 +39,62 @@ struct ToStrBound<'alloc, T> {
     pt: PhantomData<T>,
 }
 
+/// DECIMAL needs the input scale to render the stored unscaled value
+struct DecimalToStrBound<'alloc> {
+    alloc: &'alloc Allocator,
+    schema: Schema,
+    scale: u8,
+}
+
+/// FIXED_BINARY values are rendered as (lower case) hex digits
+struct FixedBinaryToStrBound<'alloc> {
+    alloc: &'alloc Allocator,
+    schema: Schema,
+}
+
 impl<'b> Expr<'b> for CastExpr<'b> {
     fn bind<'a: 'b>(&self, alloc: &'a Allocator, input_schema: &Schema)
         -> Result<Box<BoundExpr<'a> + 'b>, DBError>
     {
-        unimplemented!()
+        let input = self.input.bind(alloc, input_schema)?;
+
+        let out_attr = {
+            let schema = input.schema();
+
+            if schema.count() != 1 {
+                return Err(DBError::ExpressionInputCount(format!("{} != 1", schema.count())))
+            }
+
+            let attr = schema.get(0)?;
+
+            let modifiers = match (self.modifiers, self.to) {
+                (Some(m), _) => m,
+                (None, Type::DECIMAL) => schema::decimal_modifiers(attr)
+                    .ok_or_else(|| cast_error(attr.dtype, self.to))?,
+                (None, _) => Default::default(),
+            };
+
+            if attr.dtype == self.to && attr.modifiers == modifiers {
+                return Ok(input)
+            }
+
+            if !can_cast(attr.dtype, self.to, CastMode::Explicit) {
+                return Err(cast_error(attr.dtype, self.to))
+            }
+
+            // TODO: TEXT & binary conversions
+            if !has_cast_kernel(attr.dtype, self.to) {
+                return Err(cast_error(attr.dtype, self.to))
+            }
+
+            attr.cast_with(self.to, modifiers)
+        };
+
+        Ok(box CastBound { alloc: alloc, schema: Schema::from_attr(out_attr), input: input })
+    }
+
+    fn is_constant(&self) -> bool {
+        self.input.is_constant()
     }
 }
 
@@ -37,9 +102,186 @@ impl<'a> CastExpr<'a> {
     pub fn new<T: Expr<'a> + 'a>(to: Type, input: T) -> CastExpr<'a> {
         CastExpr {
             to: to,
+            modifiers: None,
             input: box input,
         }
     }
+
+    /// Cast to a parameterized type, eg. `DECIMAL(precision, scale)`
+    pub fn with_modifiers<T: Expr<'a> + 'a>(to: Type, modifiers: TypeModifiers, input: T)
+        -> CastExpr<'a>
+    {
+        CastExpr {
+            to: to,
+            modifiers: Some(modifiers),
+            input: box input,
+        }
+    }
+}
+
+fn cast_error(from: Type, to: Type) -> DBError {
+    DBError::ExpressionInputType(format!("CAST({} AS {})", from.name(), to.name()))
+}
+
+/// Conversions `cast_column` knows how to evaluate
+fn has_cast_kernel(from: Type, to: Type) -> bool {
+    (from.is_numeric() && to.is_numeric())
+        || (from == Type::DATE && to == Type::TIMESTAMP)
+        || (from == Type::TIMESTAMP && to == Type::DATE)
+}
+
+/// Wrap a bound expression in an implicit cast to `dtype` / `modifiers`.
+///
+/// Used by expression binders to bring inputs to their common supertype. Returns the expression
+/// as is if it already is of the right type.
+pub fn implicit_cast<'a: 'b, 'b>(
+    alloc: &'a Allocator,
+    expr: Box<BoundExpr<'a> + 'b>,
+    dtype: Type,
+    modifiers: TypeModifiers
+) -> Result<Box<BoundExpr<'a> + 'b>, DBError>
+{
+    let out_attr = {
+        let attr = expr.schema().get(0)?;
+
+        if attr.dtype == dtype && attr.modifiers == modifiers {
+            return Ok(expr)
+        }
+
+        if !can_cast(attr.dtype, dtype, CastMode::Implicit) {
+            return Err(DBError::ExpressionInputType(
+                format!("{} to {}", attr.dtype.name(), dtype.name())))
+        }
+
+        attr.cast_with(dtype, modifiers)
+    };
+
+    Ok(box CastBound { alloc: alloc, schema: Schema::from_attr(out_attr), input: expr })
+}
+
+impl<'alloc, 'b> BoundExpr<'alloc> for CastBound<'alloc, 'b> {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError> {
+        let input = self.input.evaluate(view, rows)?;
+
+        let mut out = Block::new(self.alloc, &self.schema);
+        out.add_rows(rows)?;
+
+        let selected = selected_rows(view.selection(), rows);
+        cast_column(&input[0], out.column_mut(0).unwrap(), selected)?;
+        Ok(out)
+    }
+}
+
+/// Convert values row by row, NULLs stay NULL.
+fn cast_rows<F, T, C>(src: &RefColumn, dst: &mut Column, rows: SelectedRows, f: C)
+    -> Result<(), DBError>
+    where F: ValueInfo, T: ValueInfo, C: Fn(&F::Store) -> Result<T::Store, DBError>
+{
+    let src = column_row_data::<F>(src)?;
+    let mut dst = dst.row_data_mut::<T>()?;
+
+    for idx in rows {
+        if src.nulls.is_set(idx) {
+            dst.nulls.set(idx, true);
+            continue
+        }
+
+        if !dst.nulls.is_empty() {
+            dst.nulls.set(idx, false);
+        }
+
+        dst.values[idx] = f(&src.values[idx])?;
+    }
+
+    Ok(())
+}
+
+fn overflow(dtype: Type) -> DBError {
+    DBError::NumericOverflow(String::from(dtype.name()))
+}
+
+fn numeric_cast<F, T>(src: &RefColumn, dst: &mut Column, rows: SelectedRows) -> Result<(), DBError>
+    where F: ValueInfo, T: ValueInfo, F::Store: Numeric, T::Store: Numeric
+{
+    cast_rows::<F, T, _>(src, dst, rows, |v| {
+        let out = if <F::Store as Numeric>::FLOAT {
+            <T::Store as Numeric>::from_f64(v.to_f64())
+        } else {
+            <T::Store as Numeric>::from_i128(v.to_i128())
+        };
+
+        out.ok_or_else(|| overflow(T::ENUM))
+    })
+}
+
+fn numeric_to_decimal<F>(src: &RefColumn, dst: &mut Column, rows: SelectedRows)
+    -> Result<(), DBError>
+    where F: ValueInfo, F::Store: Numeric
+{
+    let mods = dst.attribute().modifiers;
+    let factor = decimal::pow10(mods.scale);
+
+    cast_rows::<F, Decimal, _>(src, dst, rows, |v| {
+        let out = if <F::Store as Numeric>::FLOAT {
+            let scaled = (v.to_f64() * factor as f64).round();
+            if scaled.is_finite() && scaled.abs() < 1e38 { Some(scaled as i128) } else { None }
+        } else {
+            v.to_i128().checked_mul(factor)
+        };
+
+        out.and_then(|v| if decimal::fits(v, mods.precision) { Some(v) } else { None })
+            .ok_or_else(|| overflow(Type::DECIMAL))
+    })
+}
+
+/// DECIMAL to integers rounds (half away from zero)
+fn decimal_to_numeric<T>(src: &RefColumn, dst: &mut Column, rows: SelectedRows)
+    -> Result<(), DBError>
+    where T: ValueInfo, T::Store: Numeric
+{
+    let scale = src.attribute().modifiers.scale;
+    let factor = decimal::pow10(scale) as f64;
+
+    cast_rows::<Decimal, T, _>(src, dst, rows, |v| {
+        let out = if <T::Store as Numeric>::FLOAT {
+            <T::Store as Numeric>::from_f64(*v as f64 / factor)
+        } else {
+            <T::Store as Numeric>::from_i128(decimal::rescale(*v, scale, 0)?)
+        };
+
+        out.ok_or_else(|| overflow(T::ENUM))
+    })
+}
+
+/// Convert the `src` column values into the `dst` column type (and type modifiers)
+fn cast_column(src: &RefColumn, dst: &mut Column, rows: SelectedRows) -> Result<(), DBError> {
+    let from = src.attribute().clone();
+    let to = dst.attribute().clone();
+
+    match (from.dtype, to.dtype) {
+        (Type::DATE, Type::TIMESTAMP) =>
+            cast_rows::<Date, Timestamp, _>(src, dst, rows, |d| Ok(d.to_timestamp())),
+        (Type::TIMESTAMP, Type::DATE) =>
+            cast_rows::<Timestamp, Date, _>(src, dst, rows, |ts| ts.to_date()),
+        (Type::DECIMAL, Type::DECIMAL) => {
+            let (scale, mods) = (from.modifiers.scale, to.modifiers);
+            cast_rows::<Decimal, Decimal, _>(src, dst, rows, |v| {
+                decimal::rescale_to(*v, scale, mods.precision, mods.scale)
+            })
+        }
+        (Type::DECIMAL, dtype) =>
+            numeric_dispatch!(dtype, T => decimal_to_numeric::<T>(src, dst, rows)),
+        (dtype, Type::DECIMAL) =>
+            numeric_dispatch!(dtype, F => numeric_to_decimal::<F>(src, dst, rows)),
+        (from, to) =>
+            numeric_dispatch!(from, F => numeric_dispatch!(to, T => {
+                numeric_cast::<F, T>(src, dst, rows)
+            })),
+    }
 }
 
 impl<'a> ToStr<'a> {
@@ -60,10 +302,18 @@ impl<'b> Expr<'b> for ToStr<'b> {
         let out_schema = Schema::from_attr(out_attr);
 
         let out: Box<BoundExpr<'a> + 'a> = match input_schema.get(0)?.dtype {
+            Type::UINT8 =>
+                box ToStrBound::<UInt8>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::UINT16 =>
+                box ToStrBound::<UInt16>{alloc: alloc, schema: out_schema, pt: PhantomData},
             Type::UINT32 =>
                 box ToStrBound::<UInt32>{alloc: alloc, schema: out_schema, pt: PhantomData},
             Type::UINT64 =>
                 box ToStrBound::<UInt64>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::INT8 =>
+                box ToStrBound::<Int8>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::INT16 =>
+                box ToStrBound::<Int16>{alloc: alloc, schema: out_schema, pt: PhantomData},
             Type::INT32 =>
                 box ToStrBound::<Int32>{alloc: alloc, schema: out_schema, pt: PhantomData},
             Type::INT64 =>
@@ -74,11 +324,29 @@ impl<'b> Expr<'b> for ToStr<'b> {
                 box ToStrBound::<Float64>{alloc: alloc, schema: out_schema, pt: PhantomData},
             Type::BOOLEAN =>
                 box ToStrBound::<Float32>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::DATE =>
+                box ToStrBound::<Date>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::TIMESTAMP =>
+                box ToStrBound::<Timestamp>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::INTERVAL =>
+                box ToStrBound::<Interval>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::DECIMAL => {
+                let scale = input_schema.get(0)?.modifiers.scale;
+                box DecimalToStrBound{alloc: alloc, schema: out_schema, scale: scale}
+            }
+            Type::UUID =>
+                box ToStrBound::<Uuid>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::FIXED_BINARY =>
+                box FixedBinaryToStrBound{alloc: alloc, schema: out_schema},
             Type::TEXT =>
                 // TODO: Just copy
                 unimplemented!(),
             Type::BLOB =>
                 box ToStrBound::<Blob>{alloc: alloc, schema: out_schema, pt: PhantomData},
+            Type::LIST | Type::STRUCT => {
+                let name = input_schema.get(0)?.dtype.name();
+                return Err(DBError::ExpressionInputType(String::from(name)))
+            }
         };
 
         Ok(out)
@@ -92,6 +360,72 @@ impl<'alloc> BoundExpr<'alloc> for ToStrBound<'alloc, Blob>
     }
 }
 
+impl<'alloc> BoundExpr<'alloc> for DecimalToStrBound<'alloc> {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError> {
+        let mut out = Block::new(self.alloc, &self.schema);
+        out.add_rows(rows)?;
+
+        let src_col = view.column(0).unwrap();
+        let src_rows = column_row_data::<Decimal>(src_col)?;
+        let nullable = self.schema[0].nullable;
+
+        {
+            let col = out.column_mut(0).unwrap();
+
+            for idx in selected_rows(view.selection(), rows) {
+                if nullable && src_rows.nulls.get(idx) {
+                    NULL_VALUE.set_row(col, idx)?;
+                } else {
+                    decimal::format(src_rows.values[idx], self.scale)
+                        .set_row(col, idx)?;
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl<'alloc> BoundExpr<'alloc> for FixedBinaryToStrBound<'alloc> {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError> {
+        let mut out = Block::new(self.alloc, &self.schema);
+        out.add_rows(rows)?;
+
+        let src_col = view.column(0).unwrap();
+        let src_rows = fixed_row_data(src_col)?;
+        let nullable = self.schema[0].nullable;
+
+        {
+            let col = out.column_mut(0).unwrap();
+            let mut text = String::with_capacity(src_rows.width * 2);
+
+            for idx in selected_rows(view.selection(), rows) {
+                if nullable && src_rows.nulls.get(idx) {
+                    NULL_VALUE.set_row(col, idx)?;
+                    continue
+                }
+
+                text.clear();
+                for byte in src_rows.get(idx) {
+                    write!(text, "{:02x}", byte).unwrap();
+                }
+
+                text.set_row(col, idx)?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
 impl<'alloc, T: ValueInfo, V: ToString> BoundExpr<'alloc> for ToStrBound<'alloc, T>
     where T: ValueInfo<Store=V>
 {
@@ -111,7 +445,7 @@ impl<'alloc, T: ValueInfo, V: ToString> BoundExpr<'alloc> for ToStrBound<'alloc,
 
             let nullable = self.schema[0].nullable;
             if !nullable {
-                for idx in 0 .. rows {
+                for idx in selected_rows(view.selection(), rows) {
                     // TODO: don't allocate
                     src_rows.values[idx].to_string()
                         .set_row(col, idx);
@@ -120,8 +454,8 @@ impl<'alloc, T: ValueInfo, V: ToString> BoundExpr<'alloc> for ToStrBound<'alloc,
                 // TODO: Copy null vector 1st, copy values second
 
                 // TODO: Make sure we're not bounds checking
-                for idx in 0 .. rows {
-                    if src_rows.nulls[idx] != 0 {
+                for idx in selected_rows(view.selection(), rows) {
+                    if src_rows.nulls.get(idx) {
                         NULL_VALUE.set_row(col, idx);
                     } else {
                         src_rows.values[idx].to_string()
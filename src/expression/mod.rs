@@ -24,6 +24,8 @@ pub trait BoundExpr<'alloc> {
     /// Output schema
     fn schema(&self) -> &Schema;
 
+    /// Evaluate the expression for the first `rows` rows of the view. Only rows selected by the
+    /// view's selection are computed, the values of other output rows are unspecified.
     fn evaluate<'a>(&self, view: &'a View<'a>, rows: RowOffset) -> Result<Block<'alloc>, DBError>;
 
     /// Parent expression can can hoist out the constant value and use it directly in the
@@ -38,6 +40,28 @@ pub trait BoundExpr<'alloc> {
     }
 }
 
+/// Evaluate `$body` with `$T` being the `ValueInfo` marker type of an integer or float `Type`.
+///
+/// `numeric_dispatch!(dtype, T => kernel::<T>(col))`
+macro_rules! numeric_dispatch {
+    ($dtype:expr, $T:ident => $body:expr) => {
+        match $dtype {
+            Type::UINT8     => { type $T = UInt8; $body }
+            Type::UINT16    => { type $T = UInt16; $body }
+            Type::UINT32    => { type $T = UInt32; $body }
+            Type::UINT64    => { type $T = UInt64; $body }
+            Type::INT8      => { type $T = Int8; $body }
+            Type::INT16     => { type $T = Int16; $body }
+            Type::INT32     => { type $T = Int32; $body }
+            Type::INT64     => { type $T = Int64; $body }
+            Type::FLOAT32   => { type $T = Float32; $body }
+            Type::FLOAT64   => { type $T = Float64; $body }
+            other => Err(DBError::ExpressionInputType(String::from(other.name()))),
+        }
+    }
+}
+
+pub mod arithmetic;
 pub mod convert;
 pub mod comparison;
 // pub mod internal;
@@ -0,0 +1,309 @@
+// vim : set ts=4 sw=4 et :
+
+//! Human readable rendering of views as aligned ASCII tables, for tests and logs.
+//!
+//! ```text
+//! +----------+-----------+
+//! | id:INT32 | name:TEXT |
+//! +----------+-----------+
+//! |        1 | one       |
+//! |     NULL | two       |
+//! +----------+-----------+
+//! (2 rows)
+//! ```
+
+use std::cmp::{max, min};
+use std::fmt::{self, Write};
+
+use ::block::{Block, RefView, View, column_value};
+use ::error::DBError;
+use ::schema::Attribute;
+use ::selection;
+use ::types::Value;
+
+/// Rows rendered by default
+pub const DEFAULT_MAX_ROWS: usize = 100;
+
+/// Width (in characters) TEXT / BLOB values are truncated to by default
+pub const DEFAULT_MAX_WIDTH: usize = 32;
+
+/// Options of `format_view`
+#[derive(Clone)]
+pub struct FormatOptions {
+    /// Maximum number of rows rendered, `None` renders all rows
+    pub max_rows: Option<usize>,
+    /// TEXT / BLOB values longer than this (in characters) are truncated
+    pub max_width: usize,
+    /// Rendering of NULL values
+    pub null: String,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            max_rows: Some(DEFAULT_MAX_ROWS),
+            max_width: DEFAULT_MAX_WIDTH,
+            null: String::from("NULL"),
+        }
+    }
+}
+
+/// Render the (selected) rows of a view as an aligned ASCII table. The header has the
+/// `name:type` of each column, numeric columns are right aligned. Column widths fit the header
+/// and the rendered rows, rows past `max_rows` don't widen them.
+pub fn format_view<'v>(view: &'v View<'v>, options: &FormatOptions) -> Result<String, DBError> {
+    let schema = view.schema();
+    let count = schema.count();
+    let total = selection::selected_count(view.selection(), view.rows());
+    let limit = options.max_rows.map_or(total, |rows| min(rows, total));
+
+    let header: Vec<String> = schema.iter()
+        .map(|attr| format!("{}:{}", attr.name, attr.type_name()))
+        .collect();
+
+    let mut lines: Vec<Vec<String>> = Vec::with_capacity(limit);
+
+    for row in selection::selected_rows(view.selection(), view.rows()).take(limit) {
+        let mut line = Vec::with_capacity(count);
+
+        for pos in 0 .. count {
+            let col = view.column(pos)
+                .ok_or(DBError::make_column_unknown_pos(pos))?;
+            line.push(format_value(&column_value(col, row)?, &schema[pos], options));
+        }
+
+        lines.push(line);
+    }
+
+    let widths: Vec<usize> = (0 .. count)
+        .map(|pos| {
+            lines.iter().fold(text_width(&header[pos]), |width, line| {
+                max(width, text_width(&line[pos]))
+            })
+        })
+        .collect();
+
+    let header_align = vec![false; count];
+    let align: Vec<bool> = schema.iter().map(|attr| attr.dtype.is_numeric()).collect();
+
+    let mut out = String::new();
+    write_border(&mut out, &widths);
+    write_line(&mut out, &widths, &header, &header_align);
+    write_border(&mut out, &widths);
+
+    for line in &lines {
+        write_line(&mut out, &widths, line, &align);
+    }
+
+    write_border(&mut out, &widths);
+
+    let noun = if total == 1 { "row" } else { "rows" };
+    if limit < total {
+        out.push_str(&format!("({} of {} {})\n", limit, total, noun));
+    } else {
+        out.push_str(&format!("({} {})\n", total, noun));
+    }
+
+    Ok(out)
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn write_border(out: &mut String, widths: &[usize]) {
+    out.push('+');
+    for width in widths {
+        out.push_str(&"-".repeat(width + 2));
+        out.push('+');
+    }
+    out.push('\n');
+}
+
+fn write_line(out: &mut String, widths: &[usize], cells: &[String], right: &[bool]) {
+    out.push('|');
+    for (pos, cell) in cells.iter().enumerate() {
+        let pad = " ".repeat(widths[pos] - text_width(cell));
+
+        if right[pos] {
+            out.push_str(&format!(" {}{} |", pad, cell));
+        } else {
+            out.push_str(&format!(" {}{} |", cell, pad));
+        }
+    }
+    out.push('\n');
+}
+
+/// Cut off text longer than `width` characters, marking it with `...`
+fn truncate(text: String, width: usize) -> String {
+    if text_width(&text) <= width || width <= 3 {
+        return text
+    }
+
+    let mut out: String = text.chars().take(width - 3).collect();
+    out.push_str("...");
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + bytes.len() * 2);
+    out.push_str("0x");
+    for byte in bytes {
+        write!(out, "{:02x}", byte).unwrap();
+    }
+    out
+}
+
+/// Control characters are escaped so values stay on a single line
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_control() {
+            out.extend(c.escape_default());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Render a single value of the attribute's column as it's shown in `format_view`
+pub fn format_value(value: &Value, attr: &Attribute, options: &FormatOptions) -> String {
+    match *value {
+        Value::NULL => options.null.clone(),
+        Value::UINT8(v) => v.to_string(),
+        Value::UINT16(v) => v.to_string(),
+        Value::UINT32(v) => v.to_string(),
+        Value::UINT64(v) => v.to_string(),
+        Value::INT8(v) => v.to_string(),
+        Value::INT16(v) => v.to_string(),
+        Value::INT32(v) => v.to_string(),
+        Value::INT64(v) => v.to_string(),
+        Value::FLOAT32(v) => v.to_string(),
+        Value::FLOAT64(v) => v.to_string(),
+        Value::BOOLEAN(v) => v.to_string(),
+        Value::DATE(ref v) => v.to_string(),
+        Value::TIMESTAMP(ref v) => v.to_string(),
+        Value::INTERVAL(ref v) => v.to_string(),
+        Value::DECIMAL(ref v) => v.to_string(),
+        Value::UUID(ref v) => v.to_string(),
+        Value::FIXED_BINARY(bytes) => hex(bytes),
+        Value::TEXT(text) => truncate(escape(text), options.max_width),
+        Value::BLOB(bytes) => truncate(hex(bytes), options.max_width),
+        Value::LIST(ref items) => {
+            let element = &attr.children[0];
+            let items: Vec<String> = items.iter()
+                .map(|item| format_value(item, element, options))
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::STRUCT(ref fields) => {
+            let fields: Vec<String> = fields.iter().zip(attr.children.iter())
+                .map(|(field, fattr)| {
+                    format!("{}: {}", fattr.name, format_value(field, fattr, options))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+    }
+}
+
+fn write_view<'v>(f: &mut fmt::Formatter, view: &'v View<'v>) -> fmt::Result {
+    match format_view(view, &FormatOptions::default()) {
+        Ok(text) => f.write_str(&text),
+        Err(err) => write!(f, "<{}>", err),
+    }
+}
+
+/// Renders the first `DEFAULT_MAX_ROWS` rows as a table (see `format_view`)
+impl<'b> fmt::Display for Block<'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_view(f, self)
+    }
+}
+
+/// Renders the first `DEFAULT_MAX_ROWS` selected rows as a table (see `format_view`)
+impl<'a> fmt::Display for RefView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_view(f, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use block::window_alias;
+    use schema::Schema;
+    use selection::Selection;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn format_table() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("name", false, Type::TEXT),
+            Attribute::new("data", true, Type::BLOB),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i32).set("one").set(&b"\x01\xff"[..])
+                .add_row().set(NULL_VALUE).set("a very long name that goes on").set(NULL_VALUE)
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let options = FormatOptions { max_width: 12, .. Default::default() };
+        let text = format_view(table.block_ref(), &options).unwrap();
+
+        assert_eq!(text, "\
++----------+--------------+-----------+
+| id:INT32 | name:TEXT    | data:BLOB |
++----------+--------------+-----------+
+|        1 | one          | 0x01ff    |
+|     NULL | a very lo... | NULL      |
++----------+--------------+-----------+
+(2 rows)
+");
+
+        // Columns are only as wide as the rendered rows need
+        let options = FormatOptions { max_rows: Some(1), .. options };
+        let text = format_view(table.block_ref(), &options).unwrap();
+
+        assert_eq!(text, "\
++----------+-----------+-----------+
+| id:INT32 | name:TEXT | data:BLOB |
++----------+-----------+-----------+
+|        1 | one       | 0x01ff    |
++----------+-----------+-----------+
+(1 of 2 rows)
+");
+    }
+
+    #[test]
+    fn display_selected_rows() {
+        let schema = Schema::make_one_attr("v", false, Type::UINT8);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(10 as u8)
+                .add_row().set(20 as u8)
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let view = window_alias(table.block_ref(), None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![1]).unwrap()))
+            .unwrap();
+
+        assert_eq!(view.to_string(), "+---------+\n| v:UINT8 |\n+---------+\n\
+                                      |      20 |\n+---------+\n(1 row)\n");
+    }
+}
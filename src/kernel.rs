@@ -0,0 +1,627 @@
+// vim : set ts=4 sw=4 et :
+
+//! Gather (take) and scatter kernels. They move rows between columns by position and are the
+//! building blocks for operations that reorder or pick rows (sort, join, filter, sample).
+//!
+//! Output never borrows from the input: VARLEN values are copied into the destination column's
+//! arena (or dictionary) and LIST elements are appended to the destination element column.
+//!
+//! Sources can be in any encoding, the output is always plain (or dictionary) data. `Constant`
+//! and `RunLength` sources are copied a run at a time where possible.
+
+use std::cmp::min;
+use std::mem;
+use std::ops::Range;
+use std::slice;
+
+use ::allocator::Allocator;
+use ::block::{Block, Column, Encoding, RefColumn, View, column_nulls, list_row_data};
+use ::block::{stored_row, varlen_at};
+use ::error::DBError;
+use ::row::RowOffset;
+use ::schema::{Attribute, Schema};
+use ::types::{self, ListEntry, Type};
+
+/// Gather rows of the first `rows` rows of a column into a new single column `Block`. Row `i` of
+/// the output is row `indices[i]` of the source; indices can be in any order and repeat.
+pub fn take_column<'b, 'c>(alloc: &'b Allocator, src: &'c RefColumn<'c>, rows: RowOffset,
+                           indices: &[RowOffset]) -> Result<Block<'b>, DBError>
+{
+    let mut out = Block::new(alloc, &Schema::from_attr(src.attribute().clone()));
+    out.add_rows(indices.len())?;
+
+    gather(src, rows, out.column_mut(0).unwrap(), 0, indices)?;
+    Ok(out)
+}
+
+/// Gather rows of every column of the view into a new `Block`
+pub fn take<'b, 'v>(alloc: &'b Allocator, src: &'v View<'v>, indices: &[RowOffset])
+    -> Result<Block<'b>, DBError>
+{
+    let mut out = Block::new(alloc, src.schema());
+    out.add_rows(indices.len())?;
+
+    for pos in 0 .. src.schema().count() {
+        let col = src.column(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))?;
+        gather(col, src.rows(), out.column_mut(pos).unwrap(), 0, indices)?;
+    }
+
+    Ok(out)
+}
+
+/// Gather rows `indices` of `src` into consecutive rows of `dst` starting at row `at`. Only the
+/// first `rows` rows of `src` can be gathered, the rest (up to its capacity) aren't initialized.
+pub fn gather<'c>(src: &'c RefColumn<'c>, rows: RowOffset, dst: &mut Column, at: RowOffset,
+                  indices: &[RowOffset]) -> Result<(), DBError>
+{
+    let rows = min(rows, src.capacity());
+
+    if indices.iter().any(|row| *row >= rows) || at + indices.len() > dst.capacity() {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    match src.encoding() {
+        Encoding::Constant => fill(src, dst, 0, at .. at + indices.len()),
+        Encoding::RunLength => {
+            let stored = stored_indices(src, indices.iter().cloned());
+            copy_rows(src, dst, stored.iter().cloned().zip(at ..))
+        }
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets =>
+            copy_rows(src, dst, indices.iter().cloned().zip(at ..)),
+    }
+}
+
+/// Scatter the first `rows` rows of `src` into `dst`. Row `i` of the source is written into row
+/// `positions[i]` of the destination, the other destination rows are left alone.
+pub fn scatter<'c>(src: &'c RefColumn<'c>, rows: RowOffset, dst: &mut Column,
+                   positions: &[RowOffset]) -> Result<(), DBError>
+{
+    let dst_cap = dst.capacity();
+
+    if positions.len() > min(rows, src.capacity())
+        || positions.iter().any(|row| *row >= dst_cap)
+    {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    match src.encoding() {
+        Encoding::Constant | Encoding::RunLength => {
+            let stored = stored_indices(src, 0 .. positions.len());
+            copy_rows(src, dst, stored.iter().cloned().zip(positions.iter().cloned()))
+        }
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets =>
+            copy_rows(src, dst, (0 .. positions.len()).zip(positions.iter().cloned())),
+    }
+}
+
+/// Stored rows (see `block::stored_row`) of `Constant` / `RunLength` encoded column rows
+fn stored_indices<I: Iterator<Item=RowOffset>>(src: &RefColumn, rows: I) -> Vec<RowOffset> {
+    rows.map(|row| stored_row(src, row)).collect()
+}
+
+/// Scatter rows of every column of the view into the existing rows `positions` of `dst`
+pub fn scatter_block<'v>(src: &'v View<'v>, dst: &mut Block, positions: &[RowOffset])
+    -> Result<(), DBError>
+{
+    if positions.len() > src.rows() || positions.iter().any(|row| *row >= dst.rows()) {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    let count = src.schema().count();
+    if count != dst.schema().count() {
+        return Err(DBError::ExpressionInputCount(
+            format!("{} != {}", count, dst.schema().count())))
+    }
+
+    for pos in 0 .. count {
+        let col = src.column(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))?;
+        scatter(col, src.rows(), dst.column_mut(pos).unwrap(), positions)?;
+    }
+
+    Ok(())
+}
+
+/// Copy a contiguous range of the first `rows` rows of `src` into `dst` starting at row `at`.
+/// Fixed width row data and null vectors are copied in bulk.
+pub fn copy_range<'c>(src: &'c RefColumn<'c>, rows: RowOffset, dst: &mut Column, at: RowOffset,
+                      range: Range<RowOffset>) -> Result<(), DBError>
+{
+    if range.start > range.end || range.end > min(rows, src.capacity())
+        || at + range.len() > dst.capacity()
+    {
+        return Err(DBError::RowOutOfBounds)
+    }
+
+    copy_contiguous(src, dst, at, range)
+}
+
+fn copy_contiguous<'c>(src: &'c RefColumn<'c>, dst: &mut Column, at: RowOffset,
+                       range: Range<RowOffset>) -> Result<(), DBError>
+{
+    match src.encoding() {
+        Encoding::Constant => return fill(src, dst, 0, at .. at + range.len()),
+        Encoding::RunLength => return fill_runs(src, dst, at, range),
+        Encoding::Plain | Encoding::Dictionary | Encoding::Offsets => {},
+    }
+
+    let attr = src.attribute().clone();
+    let len = range.len();
+    let nulls = column_nulls(src);
+
+    // VARLEN values and LIST elements have to be copied value by value. So do the fields of
+    // STRUCTs with NULL rows, the fields of NULL rows aren't initialized.
+    let struct_nulls = attr.dtype == Type::STRUCT && !nulls.is_empty()
+        && nulls.slice(range.start, len).any();
+
+    if attr.dtype == Type::TEXT || attr.dtype == Type::BLOB || attr.dtype == Type::LIST
+        || struct_nulls
+    {
+        return copy_rows(src, dst, range.clone().zip(at ..))
+    }
+
+    check_column(&attr, dst)?;
+
+    if dst.attribute().nullable {
+        let mut dst_nulls = dst.nulls_mut()?;
+
+        if nulls.is_empty() {
+            dst_nulls.set_range(at, at + len, false);
+        } else {
+            dst_nulls.copy_from(at, &nulls.slice(range.start, len));
+        }
+    }
+
+    if attr.dtype == Type::STRUCT {
+        for pos in 0 .. attr.children.len() {
+            let field = src.child(pos)
+                .ok_or(DBError::make_column_unknown_pos(pos))?;
+            let dst_field = dst.child_mut(pos)
+                .ok_or(DBError::make_column_unknown_pos(pos))?;
+
+            copy_contiguous(field, dst_field, at, range.clone())?;
+        }
+
+        return Ok(())
+    }
+
+    let width = attr.size_of();
+    let data = src.rows_raw_slice();
+
+    dst.rows_raw_mut()[at * width .. (at + len) * width]
+        .copy_from_slice(&data[range.start * width .. range.end * width]);
+    Ok(())
+}
+
+/// Copy the runs of a `RunLength` encoded column overlapping the range
+fn fill_runs<'c>(src: &'c RefColumn<'c>, dst: &mut Column, at: RowOffset,
+                 range: Range<RowOffset>) -> Result<(), DBError>
+{
+    let ends = src.run_ends().unwrap_or(&[]);
+    let mut row = range.start;
+    let mut run = stored_row(src, row);
+
+    while row < range.end {
+        let end = min(ends[run], range.end);
+        fill(src, dst, run, at + row - range.start .. at + end - range.start)?;
+
+        row = end;
+        run += 1;
+    }
+
+    Ok(())
+}
+
+/// Copy the value at a stored row into every row of the `to` range. Only used for `Constant`
+/// and `RunLength` encoded columns, which can't be of a nested type.
+fn fill<'c>(src: &'c RefColumn<'c>, dst: &mut Column, from: RowOffset, to: Range<RowOffset>)
+    -> Result<(), DBError>
+{
+    let attr = src.attribute().clone();
+    check_column(&attr, dst)?;
+
+    let null = column_nulls(src).is_set(from);
+
+    if dst.attribute().nullable {
+        dst.nulls_mut()?.set_range(to.start, to.end, null);
+    }
+
+    if null {
+        return Ok(())
+    }
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB => {
+            let value = varlen_at(src, from);
+            for row in to {
+                dst.set_varlen(row, value)?;
+            }
+        }
+        _ => {
+            let width = attr.size_of();
+            let value = &src.rows_raw_slice()[from * width .. (from + 1) * width];
+            let out = dst.rows_raw_mut();
+
+            for row in to {
+                out[row * width .. (row + 1) * width].copy_from_slice(value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Source and destination columns have to be of the same type. NULLs can't be copied into a
+/// non-nullable column.
+fn check_column(attr: &Attribute, dst: &Column) -> Result<(), DBError> {
+    let dattr = dst.attribute();
+
+    if attr.dtype != dattr.dtype || attr.size_of() != dattr.size_of() {
+        return Err(DBError::AttributeType(dattr.name.clone()))
+    }
+
+    if attr.nullable && !dattr.nullable {
+        return Err(DBError::make_column_not_nullable(dattr.name.clone()))
+    }
+
+    Ok(())
+}
+
+/// Copy (source row, destination row) pairs. Bounds are checked by the callers.
+fn copy_rows<'c, I>(src: &'c RefColumn<'c>, dst: &mut Column, rows: I) -> Result<(), DBError>
+    where I: Iterator<Item=(RowOffset, RowOffset)> + Clone
+{
+    let attr = src.attribute().clone();
+    check_column(&attr, dst)?;
+
+    let nulls = column_nulls(src);
+
+    if dst.attribute().nullable {
+        let mut dst_nulls = dst.nulls_mut()?;
+        for (from, to) in rows.clone() {
+            dst_nulls.set(to, nulls.is_set(from));
+        }
+    }
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB => {
+            for (from, to) in rows {
+                if !nulls.is_set(from) {
+                    dst.set_varlen(to, varlen_at(src, from))?;
+                }
+            }
+        }
+        Type::LIST => {
+            let lists = list_row_data(src)?;
+
+            for (from, to) in rows {
+                let entry = if nulls.is_set(from) {
+                    ListEntry::default()
+                } else {
+                    let range = lists.range(from);
+                    let len = range.len();
+                    let start = dst.list_reserve(len)?;
+
+                    copy_rows(lists.elements, dst.child_mut(0).unwrap(), range.zip(start ..))?;
+                    ListEntry { offset: start as u32, len: len as u32 }
+                };
+
+                dst.rows_mut::<types::List>()?[to] = entry;
+            }
+        }
+        Type::STRUCT => {
+            // Fields of NULL rows aren't initialized
+            let valid: Vec<(RowOffset, RowOffset)> = rows
+                .filter(|&(from, _)| !nulls.is_set(from))
+                .collect();
+
+            for pos in 0 .. attr.children.len() {
+                let field = src.child(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+                let dst_field = dst.child_mut(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+
+                copy_rows(field, dst_field, valid.iter().cloned())?;
+            }
+        }
+        _ => copy_fixed(src.rows_raw_slice(), dst.rows_raw_mut(), attr.size_of(), rows),
+    }
+
+    Ok(())
+}
+
+/// Copy fixed width rows, using a native integer type of the same width where there's one
+fn copy_fixed<I>(src: &[u8], dst: &mut [u8], width: usize, rows: I)
+    where I: Iterator<Item=(RowOffset, RowOffset)>
+{
+    match width {
+        1 => copy_typed::<u8, I>(src, dst, rows),
+        2 => copy_typed::<u16, I>(src, dst, rows),
+        4 => copy_typed::<u32, I>(src, dst, rows),
+        8 => copy_typed::<u64, I>(src, dst, rows),
+        16 => copy_typed::<u128, I>(src, dst, rows),
+        _ => {
+            for (from, to) in rows {
+                dst[to * width .. (to + 1) * width]
+                    .copy_from_slice(&src[from * width .. (from + 1) * width]);
+            }
+        }
+    }
+}
+
+fn copy_typed<T: Copy, I>(src: &[u8], dst: &mut [u8], rows: I)
+    where I: Iterator<Item=(RowOffset, RowOffset)>
+{
+    let src = unsafe { typed_rows::<T>(src) };
+    let dst = unsafe {
+        slice::from_raw_parts_mut(dst.as_mut_ptr() as *mut T, dst.len() / mem::size_of::<T>())
+    };
+
+    for (from, to) in rows {
+        dst[to] = src[from];
+    }
+}
+
+/// Row data is allocated with (at least) `MIN_ALIGN` alignment
+unsafe fn typed_rows<T>(raw: &[u8]) -> &[T] {
+    slice::from_raw_parts(raw.as_ptr() as *const T, raw.len() / mem::size_of::<T>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use block::column_row_data;
+    use rle::{ConstantColumn, RleColumn};
+    use table::{Table, TableAppender};
+    use types::*;
+    use util::copy_value::ValueSetter;
+
+    #[test]
+    fn take_rows() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT64),
+            Attribute::new("name", false, Type::TEXT),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(10 as i64).set("ten")
+                .add_row().set(NULL_VALUE).set("null")
+                .add_row().set(30 as i64).set("thirty")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let out = take(&allocator::GLOBAL, table.block_ref(), &[2, 1, 2, 0]).unwrap();
+        assert_eq!(out.rows(), 4);
+
+        let ids = column_row_data::<Int64>(out.column(0).unwrap()).unwrap();
+        assert_eq!(ids.nulls.iter_ones().collect::<Vec<_>>(), vec![1]);
+        assert_eq!((ids.values[0], ids.values[2], ids.values[3]), (30, 30, 10));
+
+        let names = column_row_data::<Text>(out.column(1).unwrap()).unwrap();
+        assert_eq!(names.values[2].as_ref() as &str, "thirty");
+
+        let err = take(&allocator::GLOBAL, table.block_ref(), &[3]);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn scatter_rows() {
+        let schema = Schema::make_one_attr("v", true, Type::INT32);
+        let mut src = Table::new(&allocator::GLOBAL, &schema, None);
+        let mut dst = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut src)
+                .add_row().set(1 as i32)
+                .add_row().set(NULL_VALUE)
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+
+            let status = TableAppender::new(&mut dst)
+                .add_row().set(7 as i32)
+                .add_row().set(NULL_VALUE)
+                .add_row().set(9 as i32)
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut block = dst.take().unwrap();
+        scatter_block(src.block_ref(), &mut block, &[1, 2]).unwrap();
+
+        let rows = column_row_data::<Int32>(block.column(0).unwrap()).unwrap();
+        assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![2]);
+        assert_eq!((rows.values[0], rows.values[1]), (7, 1));
+    }
+
+    #[test]
+    fn take_list_rows() {
+        let element = Attribute::new("element", false, Type::INT32);
+        let schema = Schema::from_attr(Attribute::list("ids", true, element));
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(vec![1 as i32, 2])
+                .add_row().set(NULL_VALUE)
+                .add_row().set(vec![3 as i32])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let out = take(&allocator::GLOBAL, table.block_ref(), &[2, 0, 1, 0]).unwrap();
+        let lists = list_row_data(out.column(0).unwrap()).unwrap();
+        assert_eq!(lists.nulls.iter_ones().collect::<Vec<_>>(), vec![2]);
+
+        // Elements of the taken lists are appended to the output element column
+        let elements = column_row_data::<Int32>(lists.elements).unwrap();
+        let values: Vec<Vec<i32>> = [0, 1, 3].iter()
+            .map(|row| elements.values[lists.range(*row)].to_vec())
+            .collect();
+        assert_eq!(values, vec![vec![3], vec![1, 2], vec![1, 2]]);
+    }
+
+    #[test]
+    fn take_struct_rows() {
+        let address = Attribute::structure("address", true, vec![
+            Attribute::new("city", false, Type::TEXT),
+            Attribute::new("zip", false, Type::UINT32),
+        ]).unwrap();
+
+        let schema = Schema::from_attr(address);
+        let mut block = Block::new(&allocator::GLOBAL, &schema);
+        block.add_rows(2).unwrap();
+
+        {
+            let address = block.column_mut(0).unwrap();
+            address.nulls_mut().unwrap().set(0, true);
+            address.child_mut(0).unwrap().set_varlen(1, b"Krakow").unwrap();
+            (30001 as u32).set_row(address.child_mut(1).unwrap(), 1).unwrap();
+        }
+
+        let out = take(&allocator::GLOBAL, &block, &[1, 0, 1]).unwrap();
+        let col = out.column(0).unwrap();
+        assert_eq!(column_nulls(col).iter_ones().collect::<Vec<_>>(), vec![1]);
+
+        // Fields are copied row by row along with the STRUCT
+        let cities = column_row_data::<Text>(col.child(0).unwrap()).unwrap();
+        assert_eq!(cities.values[2].as_ref() as &str, "Krakow");
+
+        let zips = column_row_data::<UInt32>(col.child(1).unwrap()).unwrap();
+        assert_eq!((zips.values[0], zips.values[2]), (30001, 30001));
+
+        let mut copy = Block::new(&allocator::GLOBAL, &schema);
+        copy.add_rows(2).unwrap();
+        copy_range(block.column(0).unwrap(), 2, &mut copy[0], 0, 0 .. 2).unwrap();
+
+        let cities = column_row_data::<Text>(copy.column(0).unwrap().child(0).unwrap()).unwrap();
+        assert_eq!(cities.values[1].as_ref() as &str, "Krakow");
+    }
+
+    #[test]
+    fn take_dictionary_rows() {
+        let schema = Schema::make_one_attr("country", true, Type::TEXT);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("us")
+                .add_row().set(NULL_VALUE)
+                .add_row().set("pl")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut block = table.take().unwrap();
+        block.encode_dictionary(0).unwrap();
+
+        let out = take_column(&allocator::GLOBAL, block.column(0).unwrap(), 3, &[2, 1, 0])
+            .unwrap();
+        let col = out.column(0).unwrap();
+        assert_eq!(col.encoding(), Encoding::Plain);
+
+        let values = column_row_data::<Text>(col).unwrap();
+        assert_eq!(values.nulls.iter_ones().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(values.values[0].as_ref() as &str, "pl");
+        assert_eq!(values.values[2].as_ref() as &str, "us");
+    }
+
+    #[test]
+    fn constant_source() {
+        let attr = Attribute::new("c", true, Type::INT32);
+        let seven = ConstantColumn::new(&allocator::GLOBAL, attr.clone(), 7 as i32, 5).unwrap();
+        let nulls = ConstantColumn::new(&allocator::GLOBAL, attr, NULL_VALUE, 5).unwrap();
+
+        let schema = Schema::make_one_attr("c", true, Type::INT32);
+        let mut out = Block::new(&allocator::GLOBAL, &schema);
+        out.add_rows(6).unwrap();
+
+        gather(&seven, 5, &mut out[0], 0, &[4, 0]).unwrap();
+        copy_range(&nulls, 5, &mut out[0], 2, 1 .. 3).unwrap();
+        scatter(&seven, 5, &mut out[0], &[5, 4]).unwrap();
+        scatter(&nulls, 5, &mut out[0], &[1]).unwrap();
+
+        let rows = column_row_data::<Int32>(out.column(0).unwrap()).unwrap();
+        assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!((rows.values[0], rows.values[4], rows.values[5]), (7, 7, 7));
+
+        assert!(gather(&seven, 5, &mut out[0], 0, &[5]).is_err());
+    }
+
+    #[test]
+    fn run_length_source() {
+        let schema = Schema::make_one_attr("v", true, Type::TEXT);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("a")
+                .add_row().set("a")
+                .add_row().set(NULL_VALUE)
+                .add_row().set("b")
+                .add_row().set("b")
+                .add_row().set("b")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let rle = RleColumn::encode(&allocator::GLOBAL, table.block_ref().column(0).unwrap(), 6)
+            .unwrap();
+        assert_eq!(rle.runs(), 3);
+
+        let mut out = Block::new(&allocator::GLOBAL, &schema);
+        out.add_rows(5).unwrap();
+
+        // Starts and ends in the middle of a run
+        copy_range(&rle, 6, &mut out[0], 0, 1 .. 5).unwrap();
+        scatter(&rle, 6, &mut out[0], &[4]).unwrap();
+
+        {
+            let rows = column_row_data::<Text>(out.column(0).unwrap()).unwrap();
+            assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![1]);
+
+            let values: Vec<&str> = [0, 2, 3, 4].iter()
+                .map(|row| rows.values[*row].as_ref() as &str)
+                .collect();
+            assert_eq!(values, vec!["a", "b", "b", "a"]);
+        }
+
+        gather(&rle, 6, &mut out[0], 0, &[5, 2]).unwrap();
+
+        let rows = column_row_data::<Text>(out.column(0).unwrap()).unwrap();
+        assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(rows.values[0].as_ref() as &str, "b");
+    }
+
+    #[test]
+    fn take_wide_rows() {
+        let schema = Schema::make_one_attr("id", false, Type::UUID);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("123e4567-e89b-12d3-a456-426614174000")
+                .add_row().set("00000000-0000-0000-0000-000000000001")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        // 16 byte rows are copied as u128
+        let out = take(&allocator::GLOBAL, table.block_ref(), &[1, 0, 1]).unwrap();
+        let ids = column_row_data::<Uuid>(out.column(0).unwrap()).unwrap();
+
+        assert_eq!(ids.values[0].to_string(), "00000000-0000-0000-0000-000000000001");
+        assert_eq!(ids.values[1].to_string(), "123e4567-e89b-12d3-a456-426614174000");
+        assert_eq!(ids.values[2].to_string(), "00000000-0000-0000-0000-000000000001");
+    }
+}
@@ -5,6 +5,7 @@
 #![feature(box_patterns)]
 #![feature(box_syntax)]
 #![feature(heap_api)]
+#![feature(i128_type)]
 #![feature(inclusive_range_syntax)]
 #![feature(specialization)]
 // #![feature(nll)]
@@ -24,6 +25,8 @@ extern crate itertools;
 
 extern crate num;
 
+extern crate memmap;
+
 /// Database error type and error utilities
 pub mod error;
 
@@ -36,10 +39,37 @@ pub mod schema;
 pub mod row;
 pub mod util;
 
+/// Bit-packed bitmaps (column null vectors).
+pub mod bitmaps;
+/// Selection vectors for zero-copy filtering of views.
+pub mod selection;
 /// Containers for columnar data.
 pub mod block;
+/// Views over reference counted column buffers, not tied to a block's lifetime.
+pub mod shared;
+/// Dictionary encoding of VARLEN columns.
+pub mod dictionary;
+/// Gather / scatter kernels for moving rows between columns.
+pub mod kernel;
+/// Constant and run-length encoded columns.
+pub mod rle;
 /// Tools for creating, writing & accessing columnar by row or element.
 pub mod table;
+/// Human readable (ASCII table) rendering of views.
+pub mod format;
+/// View equality and difference reports for tests (`assert_views_eq!`).
+#[macro_use]
+pub mod compare;
+/// Arrow C Data Interface export / import of views.
+pub mod arrow;
+/// Versioned binary serialization of blocks.
+pub mod serialize;
+/// Read only views over memory mapped block files.
+pub mod mmap;
+/// Integrity checks of column data from outside sources.
+pub mod validate;
+/// Mergeable per-column statistics (min / max, NULL and distinct counts).
+pub mod stats;
 
 /// Database operations
 pub mod operation;
@@ -0,0 +1,700 @@
+// vim : set ts=4 sw=4 et :
+
+//! Read only views over memory mapped block files, for large (reference) tables that shouldn't be
+//! copied into memory.
+//!
+//! The columns of a `MappedView` point directly into the mapping; nothing is copied or decoded.
+//! The file layout, integers in the prefix and header are little endian:
+//!
+//! ```text
+//! "DBKM" | version: u16 | layout: u16 | header length: u32 | header CRC-32: u32 | header | data
+//! ```
+//!
+//! The header holds the number of rows, the schema and a buffer table. The table has an entry for
+//! every column, in schema order with nested columns following their parent: the number of rows
+//! in the column and the offset (relative to the data) and length of each of its buffers:
+//!
+//! - Nullable columns: the null vector, in 64 bit words.
+//! - Fixed width types: the row data (`ValueInfo::Store`).
+//! - TEXT / BLOB: `u64` offsets, one more than there are rows, and the bytes of all the values.
+//!   The column is `Encoding::Offsets` encoded.
+//! - LIST: the `ListEntry` rows. The element column follows, with a row per element.
+//! - STRUCT: no buffers of its own, the field columns follow.
+//!
+//! The data starts at the first `MIN_ALIGN` aligned offset after the header and every buffer
+//! starts at a `MIN_ALIGN` aligned offset, so the mapped buffers are aligned like column memory.
+//!
+//! Row data is in the native in-memory layout. Files are meant to be read on the platform and by
+//! the build that wrote them, `layout` records the byte order and the size of `TimestampValue`
+//! and files with a different one are refused.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+use std::slice;
+use std::str;
+
+use memmap::Mmap;
+
+use ::allocator::{Allocator, MIN_ALIGN};
+use ::bitmaps::{self, Bitmap, Word};
+use ::block::{Encoding, RefColumn, View, column_nulls, list_row_data, varlen_at};
+use ::error::DBError;
+use ::kernel;
+use ::row::RowOffset;
+use ::schema::{Attribute, Schema};
+use ::selection;
+use ::serialize::{read_attribute, write_attribute};
+use ::types::{ListEntry, TimestampValue, Type};
+use ::util::crc32::crc32;
+use ::util::endian::{ByteReader, put_u8, put_u16, put_u32, put_u64};
+use ::util::math::round_up;
+
+const MAGIC: &'static [u8] = b"DBKM";
+
+/// Version of the layout written by `write_mapped`
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Magic, version, data layout, header length & checksum
+const PREFIX_SIZE: usize = 16;
+
+/// Guards against huge header lengths in corrupt files
+const MAX_HEADER_SIZE: usize = 16 * 1024 * 1024;
+
+fn corrupt<S: Into<String>>(what: S) -> DBError {
+    DBError::CorruptData(what.into())
+}
+
+/// The parts of the native row layout that differ between platforms and builds: byte order (high
+/// bit set on big endian platforms) and the size of `TimestampValue`.
+fn native_layout() -> u16 {
+    let order = if cfg!(target_endian = "big") { 0x8000 } else { 0 };
+    order | mem::size_of::<TimestampValue>() as u16
+}
+
+/// Bytes of a slice of native values
+fn native_bytes<T>(values: &[T]) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(values.as_ptr() as *const u8, values.len() * mem::size_of::<T>())
+    }
+}
+
+/// Buffers are at `MIN_ALIGN` aligned offsets of the (page aligned) mapping
+unsafe fn typed<T>(raw: &[u8]) -> &[T] {
+    slice::from_raw_parts(raw.as_ptr() as *const T, raw.len() / mem::size_of::<T>())
+}
+
+/// Buffer table entry of a column being written
+struct ColumnBuffers<'a> {
+    rows: RowOffset,
+    buffers: Vec<Cow<'a, [u8]>>,
+}
+
+/// Write the (selected) rows of a view as a file that can be mapped by `MappedFile::open`. The
+/// view is compacted into a plain encoded `Block` first (see `kernel::take`).
+pub fn write_mapped<'v, W: Write>(alloc: &Allocator, view: &'v View<'v>, out: &mut W)
+    -> Result<(), DBError>
+{
+    let rows: Vec<RowOffset> = selection::selected_rows(view.selection(), view.rows()).collect();
+    let block = kernel::take(alloc, view, &rows)?;
+    let compact: &View = &block;
+    let schema = compact.schema();
+
+    let mut columns = Vec::new();
+    for pos in 0 .. schema.count() {
+        let col = compact.column(pos)
+            .ok_or(DBError::make_column_unknown_pos(pos))?;
+        collect_buffers(col, rows.len(), &mut columns)?;
+    }
+
+    let mut header = Vec::new();
+    put_u64(&mut header, rows.len() as u64);
+    put_u32(&mut header, schema.count() as u32);
+
+    for attr in schema.iter() {
+        write_attribute(&mut header, attr);
+    }
+
+    let mut offset = 0;
+    for col in &columns {
+        put_u64(&mut header, col.rows as u64);
+        put_u8(&mut header, col.buffers.len() as u8);
+
+        for buf in &col.buffers {
+            put_u64(&mut header, offset as u64);
+            put_u64(&mut header, buf.len() as u64);
+            offset = round_up(offset + buf.len(), MIN_ALIGN);
+        }
+    }
+
+    let mut prefix = Vec::with_capacity(PREFIX_SIZE);
+    prefix.extend_from_slice(MAGIC);
+    put_u16(&mut prefix, FORMAT_VERSION);
+    put_u16(&mut prefix, native_layout());
+    put_u32(&mut prefix, header.len() as u32);
+    put_u32(&mut prefix, crc32(&header));
+
+    let end = PREFIX_SIZE + header.len();
+
+    out.write_all(&prefix)
+        .and_then(|_| out.write_all(&header))
+        .and_then(|_| write_padding(out, end))
+        .and_then(|_| write_buffers(out, &columns))
+        .map_err(DBError::IO)
+}
+
+/// Pad with zeros to the next `MIN_ALIGN` offset
+fn write_padding<W: Write>(out: &mut W, len: usize) -> io::Result<()> {
+    let padding = [0u8; MIN_ALIGN];
+    out.write_all(&padding[.. round_up(len, MIN_ALIGN) - len])
+}
+
+fn write_buffers<W: Write>(out: &mut W, columns: &[ColumnBuffers]) -> io::Result<()> {
+    for buf in columns.iter().flat_map(|col| col.buffers.iter()) {
+        out.write_all(buf)?;
+        write_padding(out, buf.len())?;
+    }
+
+    Ok(())
+}
+
+/// Buffers of the first `rows` rows of a (compacted) column, followed by the buffers of the
+/// nested columns
+fn collect_buffers<'c>(col: &'c RefColumn<'c>, rows: RowOffset, out: &mut Vec<ColumnBuffers<'c>>)
+    -> Result<(), DBError>
+{
+    let attr = col.attribute();
+    let nulls = column_nulls(col);
+    let mut buffers = Vec::new();
+
+    if attr.nullable {
+        buffers.push(Cow::Borrowed(native_bytes(&nulls.words()[.. bitmaps::words_for(rows)])));
+    }
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB => {
+            let mut offsets = Vec::with_capacity(rows + 1);
+            let mut data = Vec::new();
+            offsets.push(0 as u64);
+
+            for row in 0 .. rows {
+                if !nulls.is_set(row) {
+                    data.extend_from_slice(varlen_at(col, row));
+                }
+                offsets.push(data.len() as u64);
+            }
+
+            buffers.push(Cow::Owned(native_bytes(&offsets).to_vec()));
+            buffers.push(Cow::Owned(data));
+        }
+        Type::STRUCT => {},
+        _ => buffers.push(Cow::Borrowed(&col.rows_raw_slice()[.. rows * attr.size_of()])),
+    }
+
+    out.push(ColumnBuffers { rows: rows, buffers: buffers });
+
+    match attr.dtype {
+        Type::LIST => {
+            // Elements of a compacted column are in row order, NULL rows have empty entries
+            let lists = list_row_data(col)?;
+            let elements = (0 .. rows).map(|row| lists.range(row).end).max().unwrap_or(0);
+            collect_buffers(lists.elements, elements, out)
+        }
+        Type::STRUCT => {
+            for pos in 0 .. attr.children.len() {
+                let field = col.child(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+                collect_buffers(field, rows, out)?;
+            }
+
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A memory mapped file written by `write_mapped`. The mapping lives as long as the file, views
+/// of it borrow the file.
+pub struct MappedFile {
+    map: Mmap,
+    schema: Schema,
+    rows: RowOffset,
+    columns: Vec<ColumnLayout>,
+}
+
+/// Position of a column's buffers in the mapping
+struct ColumnLayout {
+    rows: RowOffset,
+    nulls: Option<Range<usize>>,
+    buffers: Vec<Range<usize>>,
+    children: Vec<ColumnLayout>,
+}
+
+impl MappedFile {
+    /// Map a file written by `write_mapped`. The header, the buffer table, VARLEN offsets and
+    /// LIST entries are validated, other row data is used as is. A malformed file, or one written
+    /// on a platform with a different data layout, is a `DBError::CorruptData` error.
+    ///
+    /// Unsafe because the file must not be modified (or truncated) while it's mapped.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<MappedFile, DBError> {
+        let file = File::open(path).map_err(DBError::IO)?;
+        let map = Mmap::map(&file).map_err(DBError::IO)?;
+        let (schema, rows, columns) = parse(&map)?;
+
+        Ok(MappedFile {
+            map: map,
+            schema: schema,
+            rows: rows,
+            columns: columns,
+        })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn rows(&self) -> RowOffset {
+        self.rows
+    }
+
+    /// View of all the rows, its columns point into the mapping
+    pub fn view<'m>(&'m self) -> MappedView<'m> {
+        let columns = self.schema.iter()
+            .zip(self.columns.iter())
+            .map(|(attr, layout)| mapped_column(&self.map, attr, layout))
+            .collect();
+
+        MappedView {
+            schema: &self.schema,
+            columns: columns,
+            rows: self.rows,
+        }
+    }
+}
+
+/// Validate the prefix, header and buffer table of a mapped file
+fn parse(data: &[u8]) -> Result<(Schema, RowOffset, Vec<ColumnLayout>), DBError> {
+    if data.len() < PREFIX_SIZE {
+        return Err(corrupt("not a mapped block file (too short)"))
+    }
+
+    let mut reader = ByteReader::new(&data[.. PREFIX_SIZE]);
+
+    if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(corrupt("not a mapped block file (bad magic)"))
+    }
+
+    let version = reader.read_u16()?;
+    if version != FORMAT_VERSION {
+        return Err(corrupt(format!("unsupported format version {}", version)))
+    }
+
+    let layout = reader.read_u16()?;
+    if layout != native_layout() {
+        return Err(corrupt(format!("written with a different data layout ({:#x})", layout)))
+    }
+
+    let header_len = reader.read_u32()? as usize;
+    let header_crc = reader.read_u32()?;
+
+    if header_len > MAX_HEADER_SIZE || PREFIX_SIZE + header_len > data.len() {
+        return Err(corrupt(format!("header length {} out of bounds", header_len)))
+    }
+
+    let header = &data[PREFIX_SIZE .. PREFIX_SIZE + header_len];
+    if crc32(header) != header_crc {
+        return Err(corrupt("header checksum mismatch"))
+    }
+
+    let mut reader = ByteReader::new(header);
+    let rows = reader.read_u64()? as usize;
+    let count = reader.read_u32()?;
+
+    let mut attrs = Vec::new();
+    for _ in 0 .. count {
+        attrs.push(read_attribute(&mut reader, 0)?);
+    }
+
+    let start = round_up(PREFIX_SIZE + header_len, MIN_ALIGN);
+    let mut columns = Vec::with_capacity(attrs.len());
+
+    for attr in &attrs {
+        columns.push(read_layout(&mut reader, data, start, attr, Some(rows))?);
+    }
+
+    if !reader.is_empty() {
+        return Err(corrupt("trailing bytes after the buffer table"))
+    }
+
+    let schema = Schema::from_vec(attrs)
+        .map_err(|err| corrupt(format!("invalid schema ({})", err)))?;
+    Ok((schema, rows, columns))
+}
+
+/// Read and validate the buffer table entry of a column (and its nested columns). `expected` is
+/// the number of rows the column has to have, LIST element columns can have any.
+fn read_layout(reader: &mut ByteReader, data: &[u8], start: usize, attr: &Attribute,
+               expected: Option<RowOffset>) -> Result<ColumnLayout, DBError>
+{
+    let rows = reader.read_u64()? as usize;
+
+    if expected.map_or(false, |n| n != rows) || rows > isize::max_value() as usize {
+        return Err(corrupt(format!("{} (unexpected row count {})", attr.name, rows)))
+    }
+
+    let size = |count: usize, width: usize| {
+        count.checked_mul(width)
+            .ok_or(corrupt(format!("{} (too many rows {})", attr.name, rows)))
+    };
+
+    // Expected buffer lengths, `None` for buffers of any length
+    let mut lengths = Vec::new();
+
+    if attr.nullable {
+        lengths.push(Some(bitmaps::bytes_for(rows)));
+    }
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB => {
+            lengths.push(Some(size(rows + 1, mem::size_of::<u64>())?));
+            lengths.push(None);
+        }
+        Type::STRUCT => {},
+        _ => lengths.push(Some(size(rows, attr.size_of())?)),
+    }
+
+    let count = reader.read_u8()? as usize;
+    if count != lengths.len() {
+        return Err(corrupt(format!("{} ({} buffers, expected {})", attr.name, count,
+                                   lengths.len())))
+    }
+
+    let mut buffers = Vec::with_capacity(count);
+
+    for length in lengths {
+        let offset = reader.read_u64()? as usize;
+        let len = reader.read_u64()? as usize;
+
+        let begin = start.checked_add(offset);
+        let end = begin.and_then(|begin| begin.checked_add(len));
+
+        if offset % MIN_ALIGN != 0 || length.map_or(false, |l| l != len)
+            || end.map_or(true, |end| end > data.len())
+        {
+            return Err(corrupt(format!("{} (buffer misplaced or out of bounds)", attr.name)))
+        }
+
+        buffers.push(begin.unwrap() .. end.unwrap());
+    }
+
+    let nulls = if attr.nullable { Some(buffers.remove(0)) } else { None };
+    let mut children = Vec::new();
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB => {
+            let offsets = unsafe { typed::<u64>(&data[buffers[0].clone()]) };
+            let len = buffers[1].len() as u64;
+
+            if offsets.windows(2).any(|w| w[0] > w[1]) || offsets[rows] > len {
+                return Err(corrupt(format!("{} (invalid value offsets)", attr.name)))
+            }
+
+            // TEXT values are read as `str` without checking
+            if attr.dtype == Type::TEXT {
+                let null_rows = layout_nulls(data, &nulls, rows);
+                let values = &data[buffers[1].clone()];
+
+                let invalid = (0 .. rows)
+                    .filter(|row| !null_rows.is_set(*row))
+                    .any(|row| {
+                        let value = &values[offsets[row] as usize .. offsets[row + 1] as usize];
+                        str::from_utf8(value).is_err()
+                    });
+
+                if invalid {
+                    return Err(corrupt(format!("{} (TEXT value is not UTF-8)", attr.name)))
+                }
+            }
+        }
+        Type::BOOLEAN => {
+            // Values of NULL rows are undefined
+            let null_rows = layout_nulls(data, &nulls, rows);
+            let values = &data[buffers[0].clone()];
+
+            if values.iter().enumerate().any(|(row, b)| *b > 1 && !null_rows.is_set(row)) {
+                return Err(corrupt(format!("{} (invalid BOOLEAN value)", attr.name)))
+            }
+        }
+        Type::LIST => {
+            let elements = read_layout(reader, data, start, &attr.children[0], None)?;
+            let entries = unsafe { typed::<ListEntry>(&data[buffers[0].clone()]) };
+
+            if entries.iter().any(|e| e.offset as u64 + e.len as u64 > elements.rows as u64) {
+                return Err(corrupt(format!("{} (list entry out of bounds)", attr.name)))
+            }
+
+            children.push(elements);
+        }
+        Type::STRUCT => {
+            for field in &attr.children {
+                children.push(read_layout(reader, data, start, field, Some(rows))?);
+            }
+        }
+        _ => {},
+    }
+
+    Ok(ColumnLayout {
+        rows: rows,
+        nulls: nulls,
+        buffers: buffers,
+        children: children,
+    })
+}
+
+/// Null vector of `rows` rows in the mapping, empty for non-nullable columns
+fn layout_nulls<'m>(data: &'m [u8], nulls: &Option<Range<usize>>, rows: RowOffset) -> Bitmap<'m> {
+    match *nulls {
+        Some(ref range) => Bitmap::new(unsafe { typed::<Word>(&data[range.clone()]) }, rows),
+        None => Bitmap::empty(),
+    }
+}
+
+fn mapped_column<'m>(data: &'m [u8], attr: &Attribute, layout: &ColumnLayout)
+    -> MappedColumn<'m>
+{
+    let nulls = layout_nulls(data, &layout.nulls, layout.rows);
+
+    let (raw, varlen_data) = match attr.dtype {
+        Type::TEXT | Type::BLOB =>
+            (&data[layout.buffers[0].clone()], Some(&data[layout.buffers[1].clone()])),
+        Type::STRUCT => (&[][..], None),
+        _ => (&data[layout.buffers[0].clone()], None),
+    };
+
+    let children = attr.children.iter()
+        .zip(layout.children.iter())
+        .map(|(child, child_layout)| mapped_column(data, child, child_layout))
+        .collect();
+
+    MappedColumn {
+        attr: attr.clone(),
+        nulls: nulls,
+        raw: raw,
+        varlen_data: varlen_data,
+        children: children,
+        rows: layout.rows,
+    }
+}
+
+/// Column of a `MappedView`, row data and null vector point into the mapping
+pub struct MappedColumn<'m> {
+    attr: Attribute,
+    nulls: Bitmap<'m>,
+    raw: &'m [u8],
+    varlen_data: Option<&'m [u8]>,
+    children: Vec<MappedColumn<'m>>,
+    rows: RowOffset,
+}
+
+impl<'m> RefColumn<'m> for MappedColumn<'m> {
+    fn attribute(&self) -> &Attribute {
+        &self.attr
+    }
+
+    /// Number of rows
+    fn capacity(&self) -> usize {
+        self.rows
+    }
+
+    fn rows_raw_slice(&'m self) -> &'m [u8] {
+        self.raw
+    }
+
+    unsafe fn rows_ptr(&self) -> *const u8 {
+        self.raw.as_ptr()
+    }
+
+    unsafe fn nulls_ptr(&self) -> *const u8 {
+        self.nulls.words_ptr()
+    }
+
+    fn encoding(&self) -> Encoding {
+        if self.varlen_data.is_some() { Encoding::Offsets } else { Encoding::Plain }
+    }
+
+    fn varlen_data(&self) -> Option<&[u8]> {
+        self.varlen_data
+    }
+
+    fn child(&'m self, pos: usize) -> Option<&'m RefColumn<'m>> {
+        self.children.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+}
+
+/// Read only view of a `MappedFile`
+pub struct MappedView<'m> {
+    schema: &'m Schema,
+    columns: Vec<MappedColumn<'m>>,
+    rows: RowOffset,
+}
+
+impl<'m> View<'m> for MappedView<'m> {
+    fn schema(&'m self) -> &'m Schema {
+        self.schema
+    }
+
+    fn column(&'m self, pos: usize) -> Option<&'m RefColumn<'m>> {
+        self.columns.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+
+    fn rows(&self) -> RowOffset {
+        self.rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use allocator;
+    use block::window_alias;
+    use row::RowRange;
+    use selection::Selection;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    /// Write `data` to a temporary file and map it. The file is removed once it's mapped.
+    fn map_bytes(name: &str, data: &[u8]) -> Result<MappedFile, DBError> {
+        let path = env::temp_dir()
+            .join(format!("dbkit-mmap-{}-{}", name, ::std::process::id()));
+
+        File::create(&path).and_then(|mut file| file.write_all(data)).unwrap();
+        let mapped = unsafe { MappedFile::open(&path) };
+        fs::remove_file(&path).unwrap();
+        mapped
+    }
+
+    fn write_view<'v>(view: &'v View<'v>) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_mapped(&allocator::GLOBAL, view, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn write_map_view() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT64),
+            Attribute::new("name", false, Type::TEXT),
+            Attribute::new("flag", true, Type::BOOLEAN),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::TEXT)),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i64).set("one").set(true).set(vec!["a", "b"])
+                .add_row().set(NULL_VALUE).set("").set(NULL_VALUE).set(NULL_VALUE)
+                .add_row().set(3 as i64).set("three").set(false).set(vec!["c"])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        // Dictionary encoded columns are written as plain values
+        let mut block = table.take().unwrap();
+        block.encode_dictionary(1).unwrap();
+
+        let mapped = map_bytes("view", &write_view(&block)).unwrap();
+        let view = mapped.view();
+
+        assert_eq!(view.column(1).unwrap().encoding(), Encoding::Offsets);
+        assert_views_eq!(&block, &view);
+
+        let range = Some(RowRange { offset: 1, rows: 2 });
+        let window = window_alias(&view, range).unwrap();
+        assert_views_eq!(&window_alias(&block, range).unwrap(), &window);
+
+        let selected = window_alias(&block, None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![0, 2]).unwrap()))
+            .unwrap();
+
+        let mapped = map_bytes("selected", &write_view(&selected)).unwrap();
+        assert_eq!(mapped.rows(), 2);
+        assert_views_eq!(&selected, &mapped.view());
+    }
+
+    #[test]
+    fn corrupt_file() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT64),
+            Attribute::new("flag", false, Type::BOOLEAN),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i64).set(true)
+                .add_row().set(NULL_VALUE).set(false)
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let data = write_view(table.block_ref());
+
+        let is_corrupt = |name: &str, data: &[u8]| {
+            match map_bytes(name, data) {
+                Err(DBError::CorruptData(_)) => true,
+                _ => false,
+            }
+        };
+
+        let mut flipped = data.clone();
+        flipped[PREFIX_SIZE] ^= 0xFF;
+        assert!(is_corrupt("flipped", &flipped));
+
+        assert!(is_corrupt("truncated", &data[.. data.len() - 2 * MIN_ALIGN]));
+
+        // The BOOLEAN values are the last (padded) buffer
+        let mut invalid = data.clone();
+        invalid[data.len() - MIN_ALIGN + 1] = 2;
+        assert!(is_corrupt("boolean", &invalid));
+        assert!(is_corrupt("magic", b"DBKX\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"));
+    }
+
+    #[test]
+    fn corrupt_text() {
+        let schema = Schema::make_one_attr("name", true, Type::TEXT);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(NULL_VALUE)
+                .add_row().set("marker")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let data = write_view(table.block_ref());
+        assert!(map_bytes("valid", &data).is_ok());
+
+        // The data isn't checksummed, a malformed value has to be caught when the file is opened
+        let pos = data.windows(6).position(|w| w == b"marker").unwrap();
+        let mut invalid = data.clone();
+        invalid[pos] = 0xFF;
+
+        match map_bytes("invalid", &invalid) {
+            Err(DBError::CorruptData(_)) => {},
+            _ => panic!("TEXT that isn't UTF-8 accepted"),
+        }
+    }
+}
@@ -20,6 +20,8 @@ pub struct Project<'a> {
 struct ProjectCursor<'a> {
     input: Box<Cursor<'a> + 'a>,
     proj: BoundProjector,
+    /// Input chunk the output chunk aliases. Chunks borrow the cursor (see `Cursor::next`), so the
+    /// input chunk is kept here until the next call replaces it.
     _next: RefView<'a>,
 }
 
@@ -78,9 +80,9 @@ mod tests {
     fn reorder_columns() {
         let block = {
             let attrs = vec![
-                Attribute{name: "one".to_string(), nullable: false, dtype: Type::UINT32},
-                Attribute{name: "two".to_string(), nullable: false, dtype: Type::UINT32},
-                Attribute{name: "three".to_string(), nullable: false, dtype: Type::UINT32},
+                Attribute::new("one", false, Type::UINT32),
+                Attribute::new("two", false, Type::UINT32),
+                Attribute::new("three", false, Type::UINT32),
             ];
 
             let schema = Schema::from_vec(attrs).unwrap();
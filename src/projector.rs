@@ -3,6 +3,8 @@ use itertools::Itertools;
 use super::error::DBError;
 use super::schema::{Attribute, Schema};
 use super::block::{self, RefView, View};
+use super::row::RowRange;
+use super::shared::SharedView;
 
 /// Typed checked and evaluated projector
 pub struct BoundProjector {
@@ -21,7 +23,7 @@ pub struct MultiSourceProjector(Vec<MultiProjector>);
 enum Source {
     /// From source by position
     POS(usize),
-    /// From source by name
+    /// From source by name, or dotted path to a STRUCT field
     NAME(String),
     /// All source attributes
     ALL,
@@ -41,8 +43,8 @@ struct Projector(Source, As);
 struct MultiProjector(Projector, usize);
 
 /// Bound attribute
-/// input index, input column index & output attribute.
-struct BoundAttribute(usize, usize, Attribute);
+/// input index, input column path (column index, then STRUCT field indexes) & output attribute.
+struct BoundAttribute(usize, Vec<usize>, Attribute);
 
 /// Project all attributes without renaming them
 pub fn project_all_attributes() -> SingleSourceProjector {
@@ -54,19 +56,33 @@ pub fn project_by_position(pos: usize) ->  SingleSourceProjector {
     SingleSourceProjector(vec![Projector(Source::POS(pos), As::ORIG)])
 }
 
-/// Project single argument from source by column name
+/// Project single argument from source by column name.
+///
+/// Fields of STRUCT columns can be projected using a dotted path (`address.city`), the output
+/// attribute is named after the field.
 pub fn project_by_name<S: ToString>(name: S) ->  SingleSourceProjector {
     SingleSourceProjector(vec![Projector(Source::NAME(name.to_string()), As::ORIG)])
 }
 
-fn mk_bound_attr(input: &Schema, pos: usize, out: &As) -> Result<BoundAttribute, DBError> {
-    input.get(pos)
-        .map(|attr| match *out {
-            As::ORIG                => attr.clone(),
-            As::PREFIX(ref prefix)  => attr.rename(format!("{}{}", prefix, attr.name)),
-            As::NEW(ref name)       => attr.rename(name.clone()),
-        })
-        .map(|attr| BoundAttribute(0, pos, attr))
+fn mk_bound_attr(input: &Schema, path: Vec<usize>, out: &As) -> Result<BoundAttribute, DBError> {
+    let mut attr = input.get(path[0])?;
+    let mut parent_nullable = false;
+
+    for pos in &path[1..] {
+        parent_nullable |= attr.nullable;
+        attr = &attr.children[*pos];
+    }
+
+    let mut attr = match *out {
+        As::ORIG                => attr.clone(),
+        As::PREFIX(ref prefix)  => attr.rename(format!("{}{}", prefix, attr.name)),
+        As::NEW(ref name)       => attr.rename(name.clone()),
+    };
+
+    // Fields of NULL STRUCT rows are NULL
+    attr.nullable |= parent_nullable;
+
+    Ok(BoundAttribute(0, path, attr))
 }
 
 impl SingleSourceProjector {
@@ -76,12 +92,12 @@ impl SingleSourceProjector {
         for proj in &self.0 {
             match proj.0 {
                 Source::POS(pos) =>
-                    bound.push(mk_bound_attr(input, pos, &proj.1)?),
+                    bound.push(mk_bound_attr(input, vec![pos], &proj.1)?),
                 Source::NAME(ref name) =>
-                    bound.push(mk_bound_attr(input, input.exists_ok(name.as_str())?, &proj.1)?),
+                    bound.push(mk_bound_attr(input, input.resolve_path(name.as_str())?, &proj.1)?),
                 Source::ALL =>
                     for pos in 0..input.count() {
-                        bound.push(mk_bound_attr(input, pos, &proj.1)?)
+                        bound.push(mk_bound_attr(input, vec![pos], &proj.1)?)
                     }
             }
         }
@@ -134,18 +150,29 @@ impl MultiSourceProjector {
 impl BoundProjector {
     pub fn project_view<'a>(&self, src: &'a View<'a>) -> Result<RefView<'a>, DBError> {
         let mut columns = Vec::new();
-        let schema = src.schema().clone();
+        let schema = self.schema.clone();
         let rows = src.rows();
 
         for bound_attr in &self.bound_attrs {
-            let c = src.column(bound_attr.1).unwrap();
-            let nc = block::alias_column(c, None)?;
+            let range = Some(RowRange { offset: 0, rows: rows });
+            columns.push(block::alias_nested_column(src, bound_attr.1.as_slice(), range)?);
+        }
 
-            columns.push(nc);
+        // Projection doesn't change which rows are visible
+        RefView::new(schema, columns, rows)
+            .with_selection(src.selection().cloned())
+    }
+
+    /// Project a shared view, the output shares the projected columns
+    pub fn project_shared(&self, src: &SharedView) -> Result<SharedView, DBError> {
+        let mut columns = Vec::new();
+
+        for bound_attr in &self.bound_attrs {
+            columns.push(src.alias_nested_column(bound_attr.1.as_slice())?);
         }
 
-        let out = RefView::new(schema, columns, rows);
-        Ok(out)
+        SharedView::new(self.schema.clone(), columns, src.rows())
+            .with_selection(src.selection().cloned())
     }
 }
 
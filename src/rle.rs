@@ -0,0 +1,338 @@
+// vim : set ts=4 sw=4 et :
+
+//! Constant and run-length encoded columns. Both store a value per run of equal values instead of
+//! a value per row: a constant column is a single run, so literals and sorted data don't cost
+//! memory proportional to the number of rows.
+//!
+//! The kernels (see `kernel`) understand both encodings. Consumers that only understand plain data
+//! should expand them first with `expand_column`.
+
+use ::allocator::Allocator;
+use ::block::{Block, Encoding, RefColumn, View, column_nulls, varlen_at};
+use ::error::DBError;
+use ::kernel;
+use ::row::RowOffset;
+use ::schema::{Attribute, Schema};
+use ::types::Type;
+use ::util::copy_value::ValueSetter;
+
+/// Column with the same value in every row. Only the value is stored.
+pub struct ConstantColumn<'alloc> {
+    /// Single column, single row block
+    value: Block<'alloc>,
+    rows: RowOffset,
+}
+
+/// Column stored as runs of equal values. Stores a value and the (exclusive) end row for each run.
+pub struct RleColumn<'alloc> {
+    /// Single column block with a row per run
+    values: Block<'alloc>,
+    run_ends: Vec<RowOffset>,
+}
+
+/// Nested columns can't be stored as runs
+fn check_flat(attr: &Attribute) -> Result<(), DBError> {
+    if attr.dtype.is_nested() {
+        Err(DBError::AttributeType(attr.name.clone()))
+    } else {
+        Ok(())
+    }
+}
+
+impl<'alloc> ConstantColumn<'alloc> {
+    /// Column of `rows` rows all set to `value` (which can be `NULL_VALUE`)
+    pub fn new<T: ValueSetter>(alloc: &'alloc Allocator, attr: Attribute, value: T,
+                               rows: RowOffset) -> Result<ConstantColumn<'alloc>, DBError>
+    {
+        check_flat(&attr)?;
+
+        let mut block = Block::new(alloc, &Schema::from_attr(attr));
+        block.add_row()?;
+        value.set_row(&mut block[0], 0)?;
+
+        Ok(ConstantColumn { value: block, rows: rows })
+    }
+
+    /// Column of `rows` rows all set to the value of a row of another column (eg. the result of
+    /// evaluating a constant expression)
+    pub fn from_row<'c>(alloc: &'alloc Allocator, src: &'c RefColumn<'c>, row: RowOffset,
+                        rows: RowOffset) -> Result<ConstantColumn<'alloc>, DBError>
+    {
+        check_flat(src.attribute())?;
+
+        let value = kernel::take_column(alloc, src, src.capacity(), &[row])?;
+        Ok(ConstantColumn { value: value, rows: rows })
+    }
+
+    /// The same value with a different number of rows
+    pub fn resize(mut self, rows: RowOffset) -> ConstantColumn<'alloc> {
+        self.rows = rows;
+        self
+    }
+}
+
+impl<'alloc> RleColumn<'alloc> {
+    /// Run-length encode the first `rows` rows of a column. Consecutive rows are part of the same
+    /// run if they're both NULL, or their values have the same binary representation.
+    pub fn encode<'c>(alloc: &'alloc Allocator, src: &'c RefColumn<'c>, rows: RowOffset)
+        -> Result<RleColumn<'alloc>, DBError>
+    {
+        check_flat(src.attribute())?;
+
+        match src.encoding() {
+            Encoding::Plain | Encoding::Dictionary => {},
+            _ => return Err(DBError::ColumnEncoding(src.attribute().name.clone())),
+        }
+
+        if rows > src.capacity() {
+            return Err(DBError::RowOutOfBounds)
+        }
+
+        let mut starts = Vec::new();
+        let mut run_ends = Vec::new();
+
+        for row in 0 .. rows {
+            if row > 0 && same_value(src, row - 1, row) {
+                continue
+            }
+
+            if row > 0 {
+                run_ends.push(row);
+            }
+            starts.push(row);
+        }
+
+        if rows > 0 {
+            run_ends.push(rows);
+        }
+
+        let values = kernel::take_column(alloc, src, rows, &starts)?;
+        Ok(RleColumn { values: values, run_ends: run_ends })
+    }
+
+    /// Column from a block with a single column holding the plain encoded value of each run, and
+    /// the end rows of the runs.
+    pub fn from_runs(values: Block<'alloc>, run_ends: Vec<RowOffset>)
+        -> Result<RleColumn<'alloc>, DBError>
+    {
+        if values.schema().count() != 1 {
+            return Err(DBError::ExpressionInputCount(format!("{} != 1", values.schema().count())))
+        }
+
+        let name = values.schema()[0].name.clone();
+        check_flat(&values.schema()[0])?;
+
+        let increasing = run_ends.first().map_or(true, |first| *first > 0)
+            && run_ends.windows(2).all(|pair| pair[0] < pair[1]);
+
+        // Run values are read as plain data (see `block::stored_row`)
+        let plain = values[0].encoding() == Encoding::Plain;
+
+        if !increasing || !plain || values.rows() != run_ends.len() {
+            return Err(DBError::ColumnEncoding(name))
+        }
+
+        Ok(RleColumn { values: values, run_ends: run_ends })
+    }
+
+    /// Number of runs
+    pub fn runs(&self) -> usize {
+        self.run_ends.len()
+    }
+}
+
+/// Rows of a plain or dictionary encoded column hold the same value
+fn same_value<'c>(src: &'c RefColumn<'c>, a: RowOffset, b: RowOffset) -> bool {
+    let nulls = column_nulls(src);
+
+    if nulls.is_set(a) || nulls.is_set(b) {
+        return nulls.is_set(a) && nulls.is_set(b)
+    }
+
+    let attr = src.attribute();
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB => varlen_at(src, a) == varlen_at(src, b),
+        _ => {
+            let width = attr.size_of();
+            let raw = src.rows_raw_slice();
+            raw[a * width .. (a + 1) * width] == raw[b * width .. (b + 1) * width]
+        }
+    }
+}
+
+/// Expand a column in any encoding into a single column `Block` with plain data (dictionary
+/// encoded columns are decoded as well).
+pub fn expand_column<'alloc, 'c>(alloc: &'alloc Allocator, col: &'c RefColumn<'c>)
+    -> Result<Block<'alloc>, DBError>
+{
+    let rows = col.capacity();
+
+    let mut out = Block::new(alloc, &Schema::from_attr(col.attribute().clone()));
+    out.add_rows(rows)?;
+
+    kernel::copy_range(col, rows, &mut out[0], 0, 0 .. rows)?;
+    Ok(out)
+}
+
+impl<'alloc> RefColumn<'alloc> for ConstantColumn<'alloc> {
+    fn attribute(&self) -> &Attribute {
+        self.value[0].attribute()
+    }
+
+    /// Number of rows
+    fn capacity(&self) -> usize {
+        self.rows
+    }
+
+    fn rows_raw_slice(&'alloc self) -> &'alloc [u8] {
+        self.value[0].rows_raw_slice()
+    }
+
+    unsafe fn rows_ptr(&self) -> *const u8 {
+        self.value[0].rows_ptr()
+    }
+
+    unsafe fn nulls_ptr(&self) -> *const u8 {
+        self.value[0].nulls_ptr()
+    }
+
+    fn encoding(&self) -> Encoding {
+        Encoding::Constant
+    }
+}
+
+impl<'alloc> RefColumn<'alloc> for RleColumn<'alloc> {
+    fn attribute(&self) -> &Attribute {
+        self.values[0].attribute()
+    }
+
+    /// Number of rows (not runs)
+    fn capacity(&self) -> usize {
+        self.run_ends.last().cloned().unwrap_or(0)
+    }
+
+    fn rows_raw_slice(&'alloc self) -> &'alloc [u8] {
+        self.values[0].rows_raw_slice()
+    }
+
+    unsafe fn rows_ptr(&self) -> *const u8 {
+        self.values[0].rows_ptr()
+    }
+
+    unsafe fn nulls_ptr(&self) -> *const u8 {
+        self.values[0].nulls_ptr()
+    }
+
+    fn encoding(&self) -> Encoding {
+        Encoding::RunLength
+    }
+
+    fn run_ends(&self) -> Option<&[RowOffset]> {
+        Some(&self.run_ends)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use block::{alias_column, column_row_data, stored_rows};
+    use kernel::take_column;
+    use row::RowRange;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn constant_column() {
+        let attr = Attribute::new("c", false, Type::TEXT);
+        let col = ConstantColumn::new(&allocator::GLOBAL, attr, "same", 2000).unwrap();
+        assert_eq!(stored_rows(&col), 1);
+
+        let window = alias_column(&col, Some(RowRange { offset: 1500, rows: 500 })).unwrap();
+        assert_eq!(window.encoding(), Encoding::Constant);
+
+        let plain = expand_column(&allocator::GLOBAL, &window).unwrap();
+        let values = column_row_data::<Text>(plain.column(0).unwrap()).unwrap();
+        assert_eq!(values.values[499].as_ref() as &str, "same");
+
+        let attr = Attribute::list("l", false, Attribute::new("e", false, Type::INT32));
+        assert!(ConstantColumn::new(&allocator::GLOBAL, attr, NULL_VALUE, 10).is_err());
+    }
+
+    #[test]
+    fn run_length_column() {
+        let schema = Schema::make_one_attr("v", true, Type::INT32);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let mut appender = TableAppender::new(&mut table);
+            for row in 0 .. 10 {
+                appender = if row < 4 {
+                    appender.add_row().set(7 as i32)
+                } else if row < 6 {
+                    appender.add_row().set(NULL_VALUE)
+                } else {
+                    appender.add_row().set(9 as i32)
+                };
+            }
+
+            let status = appender.done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let rle = RleColumn::encode(&allocator::GLOBAL, table.block_ref().column(0).unwrap(), 10)
+            .unwrap();
+        assert_eq!(rle.runs(), 3);
+        assert_eq!(rle.run_ends(), Some(&[4, 6, 10][..]));
+
+        // Window starting in the middle of the first run
+        let window = alias_column(&rle, Some(RowRange { offset: 2, rows: 5 })).unwrap();
+        assert_eq!(window.run_ends(), Some(&[2, 4, 5][..]));
+
+        let plain = expand_column(&allocator::GLOBAL, &window).unwrap();
+        let rows = column_row_data::<Int32>(plain.column(0).unwrap()).unwrap();
+        assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!((rows.values[1], rows.values[4]), (7, 9));
+
+        let taken = take_column(&allocator::GLOBAL, &rle, rle.capacity(), &[9, 0, 5]).unwrap();
+        let rows = column_row_data::<Int32>(taken.column(0).unwrap()).unwrap();
+        assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![2]);
+        assert_eq!((rows.values[0], rows.values[1]), (9, 7));
+    }
+
+    #[test]
+    fn runs_need_plain_values() {
+        let schema = Schema::make_one_attr("v", false, Type::TEXT);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("a")
+                .add_row().set("b")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut values = table.take().unwrap();
+        values.encode_dictionary(0).unwrap();
+        assert!(RleColumn::from_runs(values, vec![2, 5]).is_err(), "Dictionary encoded runs");
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("a")
+                .add_row().set("b")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let rle = RleColumn::from_runs(table.take().unwrap(), vec![2, 5]).unwrap();
+        let plain = expand_column(&allocator::GLOBAL, &rle).unwrap();
+        let rows = column_row_data::<Text>(plain.column(0).unwrap()).unwrap();
+        assert_eq!(rows.values[4].as_ref() as &str, "b");
+    }
+}
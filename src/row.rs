@@ -1,3 +1,9 @@
+use ::block::View;
+use ::error::DBError;
+use ::selection::{self, SelectedRows};
+use ::types::Value;
+use ::util::get_value::{ValueGetter, get_value};
+
 
 /// Index into table/column row
 pub type RowOffset = usize;
@@ -10,3 +16,197 @@ pub struct RowRange {
     /// Count of rows
     pub rows: usize,
 }
+
+/// Handle to a single row of a view, for reading its values without knowing the column types up
+/// front (or with `ValueGetter` when they are).
+#[derive(Copy, Clone)]
+pub struct Row<'v> {
+    view: &'v View<'v>,
+    row: RowOffset,
+}
+
+impl<'v> Row<'v> {
+    /// Handle to row `row` of the view, which has to be one of the selected rows
+    pub fn new(view: &'v View<'v>, row: RowOffset) -> Result<Row<'v>, DBError> {
+        if row >= view.rows() || view.selection().map_or(false, |sel| !sel.is_selected(row)) {
+            return Err(DBError::RowOutOfBounds)
+        }
+
+        Ok(Row { view: view, row: row })
+    }
+
+    /// Position of the row in the view
+    pub fn offset(&self) -> RowOffset {
+        self.row
+    }
+
+    /// Number of columns
+    pub fn len(&self) -> usize {
+        self.view.schema().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value of the column at position `col`, `Value::NULL` for NULL values
+    pub fn value(&self, col: usize) -> Result<Value<'v>, DBError> {
+        self.view.value(col, self.row)
+    }
+
+    /// Typed value of the column at position `col`
+    pub fn get<T: ValueGetter<'v>>(&self, col: usize) -> Result<T, DBError> {
+        let column = self.view.column(col)
+            .ok_or(DBError::make_column_unknown_pos(col))?;
+        get_value(column, self.row)
+    }
+
+    /// Typed value of the column named `name`
+    pub fn get_by_name<T: ValueGetter<'v>>(&self, name: &str) -> Result<T, DBError> {
+        let col = self.view.schema().exists_ok(name)?;
+        self.get(col)
+    }
+
+    /// Values of all the columns, in schema order
+    pub fn values(&self) -> RowValues<'v> {
+        RowValues { row: *self, col: 0 }
+    }
+}
+
+/// Iterator over the values of a `Row`
+pub struct RowValues<'v> {
+    row: Row<'v>,
+    col: usize,
+}
+
+impl<'v> Iterator for RowValues<'v> {
+    type Item = Result<Value<'v>, DBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.col >= self.row.len() {
+            return None
+        }
+
+        self.col += 1;
+        Some(self.row.value(self.col - 1))
+    }
+}
+
+/// Handles to the (selected) rows of a view, in order
+pub fn rows<'v>(view: &'v View<'v>) -> Rows<'v> {
+    Rows { view: view, rows: selection::selected_rows(view.selection(), view.rows()) }
+}
+
+/// Iterator over the rows of a view, see `rows`
+pub struct Rows<'v> {
+    view: &'v View<'v>,
+    rows: SelectedRows<'v>,
+}
+
+impl<'v> Iterator for Rows<'v> {
+    type Item = Row<'v>;
+
+    fn next(&mut self) -> Option<Row<'v>> {
+        let view = self.view;
+        self.rows.next().map(|row| Row { view: view, row: row })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use block::window_alias;
+    use schema::{Attribute, Schema};
+    use selection::Selection;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn typed_getters() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", false, Type::UINT32),
+            Attribute::new("score", true, Type::INT64),
+            Attribute::new("name", false, Type::TEXT),
+            Attribute::list("tags", false, Attribute::new("tag", false, Type::TEXT)),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as u32).set(10 as i64).set("one").set(vec!["a", "b"])
+                .add_row().set(2 as u32).set(NULL_VALUE).set("two").set(vec!["c"])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let row = Row::new(table.block_ref(), 1).unwrap();
+
+        assert_eq!(row.get::<u32>(0).unwrap(), 2);
+        assert_eq!(row.get::<Option<i64>>(1).unwrap(), None);
+        assert_eq!(row.get_by_name::<&str>("name").unwrap(), "two");
+        assert_eq!(row.get::<Vec<String>>(3).unwrap(), vec![String::from("c")]);
+
+        match row.get::<i64>(1) {
+            Err(DBError::AttributeNullability(_)) => {},
+            _ => panic!("NULL read into i64"),
+        }
+
+        match row.get::<i32>(0) {
+            Err(DBError::AttributeType(_)) => {},
+            _ => panic!("UINT32 read into i32"),
+        }
+
+        assert!(Row::new(table.block_ref(), 2).is_err());
+    }
+
+    #[test]
+    fn iterate_rows() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", false, Type::UINT32),
+            Attribute::new("score", true, Type::INT64),
+            Attribute::new("name", false, Type::TEXT),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as u32).set(10 as i64).set("one")
+                .add_row().set(2 as u32).set(NULL_VALUE).set("two")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let first = Row::new(table.block_ref(), 0).unwrap();
+
+        let values: Vec<_> = first.values().map(|v| v.unwrap()).collect();
+        assert_eq!(values.len(), 3);
+
+        match (&values[1], &values[2]) {
+            (&Value::INT64(10), &Value::TEXT("one")) => {},
+            _ => panic!("unexpected values"),
+        }
+
+        // Within the block capacity, but past its rows
+        assert!(table.block_ref().value(0, 2).is_err());
+
+        let view = window_alias(table.block_ref(), None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![1]).unwrap()))
+            .unwrap();
+
+        let ids: Vec<u32> = rows(&view).map(|row| row.get(0).unwrap()).collect();
+        assert_eq!(ids, vec![2]);
+
+        match view.value(1, 1).unwrap() {
+            Value::NULL => {},
+            _ => panic!("expected NULL"),
+        }
+
+        assert!(view.value(1, 0).is_err(), "Row not selected");
+        assert!(Row::new(&view, 0).is_err());
+    }
+}
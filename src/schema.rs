@@ -7,7 +7,21 @@ use std::ops::Index;
 
 // DBKit
 use super::error::DBError;
-use super::types::Type;
+use super::types::{self, Type};
+use super::util::decimal;
+
+/// Parameters of parameterized types, such as the precision and scale of a DECIMAL.
+///
+/// Types that are not parameterized leave the modifiers at their default value.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct TypeModifiers {
+    /// DECIMAL: total number of digits
+    pub precision: u8,
+    /// DECIMAL: number of digits after the decimal point
+    pub scale: u8,
+    /// FIXED_BINARY: number of bytes per row
+    pub width: u32,
+}
 
 /// Attribute represents high level column metadata such as name, nullability and type
 #[derive(Clone)]
@@ -15,6 +29,9 @@ pub struct Attribute {
     pub name: String,
     pub nullable: bool,
     pub dtype: Type,
+    pub modifiers: TypeModifiers,
+    /// Nested attributes. The element attribute of a LIST, fields of a STRUCT.
+    pub children: Vec<Attribute>,
 }
 
 /// Describes the attributes and organization of data
@@ -28,14 +45,261 @@ pub struct AttributeIter<'a> {
     cur: usize
 }
 
+impl TypeModifiers {
+    pub fn decimal(precision: u8, scale: u8) -> TypeModifiers {
+        TypeModifiers { precision: precision, scale: scale, width: 0 }
+    }
+
+    /// DECIMAL modifiers, checking the precision is within 1 and `decimal::MAX_PRECISION` and the
+    /// scale isn't larger than the precision
+    pub fn checked_decimal(precision: u32, scale: u32) -> Result<TypeModifiers, DBError> {
+        if precision == 0 || precision > decimal::MAX_PRECISION as u32 || scale > precision {
+            return Err(DBError::UnknownType(format!("DECIMAL({},{})", precision, scale)))
+        }
+
+        Ok(TypeModifiers::decimal(precision as u8, scale as u8))
+    }
+
+    pub fn fixed(width: u32) -> TypeModifiers {
+        TypeModifiers { width: width, .. Default::default() }
+    }
+
+    /// FIXED_BINARY modifiers, checking the width isn't 0
+    pub fn checked_fixed(width: u32) -> Result<TypeModifiers, DBError> {
+        if width == 0 {
+            return Err(DBError::UnknownType(format!("FIXED_BINARY({})", width)))
+        }
+
+        Ok(TypeModifiers::fixed(width))
+    }
+
+    /// Modifiers of a type declared without any. DECIMAL gets the widest precision and a scale of
+    /// 0 (`DECIMAL(38,0)`).
+    pub fn default_for(dtype: Type) -> TypeModifiers {
+        if dtype == Type::DECIMAL {
+            TypeModifiers::decimal(decimal::MAX_PRECISION, 0)
+        } else {
+            Default::default()
+        }
+    }
+}
+
 impl Attribute {
+    /// Create an attribute of a non-parameterized type. DECIMAL attributes get the default
+    /// modifiers (see `TypeModifiers::default_for`), use `Attribute::decimal` for others.
+    pub fn new<S: Into<String>>(name: S, nullable: bool, dtype: Type) -> Attribute {
+        Attribute {
+            name: name.into(),
+            nullable: nullable,
+            dtype: dtype,
+            modifiers: TypeModifiers::default_for(dtype),
+            children: Vec::new(),
+        }
+    }
+
+    /// Create a DECIMAL(precision, scale) attribute (see `TypeModifiers::checked_decimal`)
+    pub fn decimal<S: Into<String>>(name: S, nullable: bool, precision: u8, scale: u8)
+        -> Result<Attribute, DBError>
+    {
+        Ok(Attribute {
+            name: name.into(),
+            nullable: nullable,
+            dtype: Type::DECIMAL,
+            modifiers: TypeModifiers::checked_decimal(precision as u32, scale as u32)?,
+            children: Vec::new(),
+        })
+    }
+
+    /// Create a FIXED_BINARY(width) attribute (see `TypeModifiers::checked_fixed`)
+    pub fn fixed_binary<S: Into<String>>(name: S, nullable: bool, width: u32)
+        -> Result<Attribute, DBError>
+    {
+        Ok(Attribute {
+            name: name.into(),
+            nullable: nullable,
+            dtype: Type::FIXED_BINARY,
+            modifiers: TypeModifiers::checked_fixed(width)?,
+            children: Vec::new(),
+        })
+    }
+
+    /// Create a LIST attribute, where each row is a list of `element` values
+    pub fn list<S: Into<String>>(name: S, nullable: bool, element: Attribute) -> Attribute {
+        Attribute {
+            name: name.into(),
+            nullable: nullable,
+            dtype: Type::LIST,
+            modifiers: Default::default(),
+            children: vec![element],
+        }
+    }
+
+    /// Create a STRUCT attribute made of the named `fields`
+    pub fn structure<S: Into<String>>(name: S, nullable: bool, fields: Vec<Attribute>)
+        -> Result<Attribute, DBError>
+    {
+        let mut names = HashSet::with_capacity(fields.len());
+
+        for f in &fields {
+            if names.replace(f.name.clone()).is_some() {
+                return Err(DBError::AttributeDuplicate(f.name.clone()))
+            }
+        }
+
+        Ok(Attribute {
+            name: name.into(),
+            nullable: nullable,
+            dtype: Type::STRUCT,
+            modifiers: Default::default(),
+            children: fields,
+        })
+    }
+
+    /// Position of a nested attribute (STRUCT field) by name
+    pub fn child_pos(&self, name: &str) -> Option<usize> {
+        self.children.iter().position(|c| c.name == name)
+    }
+
+    /// Size of a single value in the column row vector. Unlike `Type::size_of()` this accounts
+    /// for type modifiers (FIXED_BINARY width).
+    pub fn size_of(&self) -> usize {
+        match self.dtype {
+            Type::FIXED_BINARY => self.modifiers.width as usize,
+            dtype => dtype.size_of(),
+        }
+    }
+
+    /// Type name including type modifiers and nested types, eg. `DECIMAL(10,2)` or
+    /// `LIST<INT32>`
+    pub fn type_name(&self) -> String {
+        match self.dtype {
+            Type::DECIMAL =>
+                format!("DECIMAL({},{})", self.modifiers.precision, self.modifiers.scale),
+            Type::FIXED_BINARY => format!("FIXED_BINARY({})", self.modifiers.width),
+            Type::LIST => {
+                let element = self.children.first().map_or(String::new(), |e| e.type_name());
+                format!("LIST<{}>", element)
+            }
+            Type::STRUCT => {
+                let fields: Vec<String> = self.children.iter()
+                    .map(|f| format!("{}:{}", f.name, f.type_name()))
+                    .collect();
+                format!("STRUCT<{}>", fields.join(","))
+            }
+            dtype => String::from(dtype.name()),
+        }
+    }
+
     pub fn rename<S: Into<String>>(&self, name: S) -> Attribute {
-        Attribute { name: name.into(), nullable: self.nullable, dtype: self.dtype }
+        Attribute { name: name.into(), .. self.clone() }
     }
 
     /// Helper methods to create a the same named attribute but of different type
+    ///
+    /// Type modifiers and nested attributes are only kept when casting to the same type.
     pub fn cast(&self, cast: Type) -> Attribute {
-        Attribute { name: self.name.clone(), nullable: self.nullable, dtype: cast }
+        if cast == self.dtype {
+            self.clone()
+        } else {
+            self.cast_with(cast, TypeModifiers::default_for(cast))
+        }
+    }
+
+    /// Helper methods to create a the same named attribute but of different type & modifiers
+    pub fn cast_with(&self, cast: Type, modifiers: TypeModifiers) -> Attribute {
+        Attribute {
+            name: self.name.clone(),
+            nullable: self.nullable,
+            dtype: cast,
+            modifiers: modifiers,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Parse a type name as rendered by `Attribute::type_name`, including the modifiers of DECIMAL
+/// (`DECIMAL(10,2)`) and FIXED_BINARY (`FIXED_BINARY(16)`) types. Types without modifiers get the
+/// default ones (`TypeModifiers::default_for`). Nested types (LIST, STRUCT) are parsed without
+/// their element / field types.
+pub fn parse_type(s: &str) -> Result<(Type, TypeModifiers), DBError> {
+    let unknown = || DBError::UnknownType(String::from(s));
+
+    let (name, params) = match s.find('(') {
+        Some(pos) if s.ends_with(')') => (&s[..pos], Some(&s[pos + 1 .. s.len() - 1])),
+        Some(_) => return Err(unknown()),
+        None => (s, None),
+    };
+
+    let dtype: Type = name.trim().parse()?;
+    let params: Vec<&str> = params.map_or(Vec::new(), |p| p.split(',').map(str::trim).collect());
+    let number = |p: &str| p.parse::<u32>().map_err(|_| unknown());
+
+    match (dtype, params.len()) {
+        (Type::DECIMAL, 2) => {
+            let modifiers = TypeModifiers::checked_decimal(number(params[0])?, number(params[1])?)
+                .map_err(|_| unknown())?;
+            Ok((dtype, modifiers))
+        }
+        (Type::FIXED_BINARY, 1) => {
+            let modifiers = TypeModifiers::checked_fixed(number(params[0])?)
+                .map_err(|_| unknown())?;
+            Ok((dtype, modifiers))
+        }
+        (Type::FIXED_BINARY, _) => Err(unknown()),
+        (_, 0) => Ok((dtype, TypeModifiers::default_for(dtype))),
+        _ => Err(unknown()),
+    }
+}
+
+/// Integral and fractional decimal digits needed to represent any value of an integer or DECIMAL
+/// attribute
+fn decimal_digits(attr: &Attribute) -> Option<(u8, u8)> {
+    match attr.dtype {
+        Type::UINT8 | Type::INT8 => Some((3, 0)),
+        Type::UINT16 | Type::INT16 => Some((5, 0)),
+        Type::UINT32 | Type::INT32 => Some((10, 0)),
+        Type::INT64 => Some((19, 0)),
+        Type::UINT64 => Some((20, 0)),
+        Type::DECIMAL => {
+            let mods = &attr.modifiers;
+            mods.precision.checked_sub(mods.scale).map(|int| (int, mods.scale))
+        }
+        _ => None,
+    }
+}
+
+/// DECIMAL modifiers able to hold any value of an integer or DECIMAL attribute
+pub fn decimal_modifiers(attr: &Attribute) -> Option<TypeModifiers> {
+    decimal_digits(attr).map(|(int, scale)| TypeModifiers::decimal(int + scale, scale))
+}
+
+/// Common supertype (see `types::common_supertype`) of two attributes, including the type
+/// modifiers.
+///
+/// DECIMAL keeps the larger number of integral and fractional digits, capped at
+/// `decimal::MAX_PRECISION`. FIXED_BINARY only has a supertype with the same width. Element and
+/// field types of nested types aren't coerced.
+pub fn common_supertype_attr(lhs: &Attribute, rhs: &Attribute) -> Option<(Type, TypeModifiers)> {
+    let dtype = types::common_supertype(lhs.dtype, rhs.dtype)?;
+
+    match dtype {
+        Type::DECIMAL => {
+            let (lint, lscale) = decimal_digits(lhs)?;
+            let (rint, rscale) = decimal_digits(rhs)?;
+            let scale = ::std::cmp::max(lscale, rscale);
+            let int = ::std::cmp::max(lint, rint);
+            let precision = ::std::cmp::min(int + scale, decimal::MAX_PRECISION);
+            Some((dtype, TypeModifiers::decimal(precision, scale)))
+        }
+        Type::FIXED_BINARY =>
+            if lhs.modifiers == rhs.modifiers {
+                Some((dtype, lhs.modifiers))
+            } else {
+                None
+            },
+        Type::LIST | Type::STRUCT =>
+            None,
+        _ => Some((dtype, Default::default())),
     }
 }
 
@@ -63,7 +327,7 @@ impl Schema {
 
     /// Create a single Attribute schema
     pub fn make_one_attr<S: Into<String>>(name: S, nullable: bool, dtype: Type) -> Schema {
-        Schema::from_attr(Attribute{name: name.into(), nullable: nullable, dtype: dtype})
+        Schema::from_attr(Attribute::new(name, nullable, dtype))
     }
 
     pub fn count(&self) -> usize {
@@ -103,6 +367,48 @@ impl Schema {
         Err(DBError::AttributeMissing(format!("(name: {})", name)))
     }
 
+    /// Resolve an attribute name or a dotted path to STRUCT fields (`address.city`) into the
+    /// positions of the attribute at each level of nesting.
+    ///
+    /// Exact matches of top-level attribute names take precedence over paths.
+    pub fn resolve_path(&self, path: &str) -> Result<Vec<usize>, DBError> {
+        if let Some(pos) = self.exists(path) {
+            return Ok(vec![pos])
+        }
+
+        let missing = || DBError::AttributeMissing(format!("(name: {})", path));
+
+        let mut parts = path.split('.');
+        let first = self.exists(parts.next().unwrap_or(path)).ok_or_else(&missing)?;
+
+        let mut out = vec![first];
+        let mut attr = &self.attrs[first];
+
+        for part in parts {
+            if attr.dtype != Type::STRUCT {
+                return Err(missing())
+            }
+
+            let pos = attr.child_pos(part).ok_or_else(&missing)?;
+            out.push(pos);
+            attr = &attr.children[pos];
+        }
+
+        Ok(out)
+    }
+
+    /// Find attribute by name or dotted path (see `resolve_path`)
+    pub fn find_path(&self, path: &str) -> Result<&Attribute, DBError> {
+        let positions = self.resolve_path(path)?;
+        let mut attr = &self.attrs[positions[0]];
+
+        for pos in &positions[1..] {
+            attr = &attr.children[*pos];
+        }
+
+        Ok(attr)
+    }
+
     pub fn iter(&self) -> AttributeIter {
         AttributeIter { schema: self, cur: 0 }
     }
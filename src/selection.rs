@@ -0,0 +1,174 @@
+// vim : set ts=4 sw=4 et :
+
+//! Selection vectors. A view with a selection only exposes a subset of its rows, the other rows
+//! are still there (so filtering doesn't copy any column data) but are skipped by consumers.
+//! `block::compact` materializes the selected rows into a dense `Block`.
+
+use std::ops::Range;
+use std::slice;
+
+use ::bitmaps::{Bitmap, OwnedBitmap, SetBits};
+use ::error::DBError;
+use ::row::RowOffset;
+
+/// Rows of a view that are selected
+#[derive(Clone)]
+pub enum Selection {
+    /// Positions of the selected rows, in strictly increasing order
+    Indices(Vec<RowOffset>),
+    /// One bit per view row, set bits are selected rows
+    Bitmap(OwnedBitmap),
+}
+
+/// Iterator over selected row positions, in increasing order
+pub enum SelectedRows<'a> {
+    All(Range<RowOffset>),
+    Indices(slice::Iter<'a, RowOffset>),
+    Bitmap(SetBits<'a>),
+}
+
+impl Selection {
+    /// Selection of row positions. Positions have to be strictly increasing.
+    pub fn from_indices(indices: Vec<RowOffset>) -> Result<Selection, DBError> {
+        for pair in indices.windows(2) {
+            if pair[0] >= pair[1] {
+                return Err(DBError::InvalidSelection(
+                    format!("row {} follows row {}", pair[1], pair[0])))
+            }
+        }
+
+        Ok(Selection::Indices(indices))
+    }
+
+    /// Selection of the rows with the bit set
+    pub fn from_bitmap(bitmap: OwnedBitmap) -> Selection {
+        Selection::Bitmap(bitmap)
+    }
+
+    /// Verify the selection fits a view of `rows` rows
+    pub fn check(&self, rows: RowOffset) -> Result<(), DBError> {
+        let fits = match *self {
+            Selection::Indices(ref indices) => indices.last().map_or(true, |last| *last < rows),
+            Selection::Bitmap(ref bitmap) => bitmap.len() == rows,
+        };
+
+        if fits { Ok(()) } else { Err(DBError::RowOutOfBounds) }
+    }
+
+    /// Number of selected rows
+    pub fn count(&self) -> usize {
+        match *self {
+            Selection::Indices(ref indices) => indices.len(),
+            Selection::Bitmap(ref bitmap) => bitmap.as_bitmap().count_ones(),
+        }
+    }
+
+    pub fn is_selected(&self, row: RowOffset) -> bool {
+        match *self {
+            Selection::Indices(ref indices) => indices.binary_search(&row).is_ok(),
+            Selection::Bitmap(ref bitmap) => bitmap.as_bitmap().is_set(row),
+        }
+    }
+
+    pub fn iter(&self) -> SelectedRows {
+        match *self {
+            Selection::Indices(ref indices) => SelectedRows::Indices(indices.iter()),
+            Selection::Bitmap(ref bitmap) => SelectedRows::Bitmap(bitmap.as_bitmap().iter_ones()),
+        }
+    }
+
+    /// Selection of a window (`offset`, `rows`) of the view, positions are relative to the window
+    pub fn slice(&self, offset: RowOffset, rows: RowOffset) -> Selection {
+        match *self {
+            Selection::Indices(ref indices) => {
+                let start = indices.binary_search(&offset).unwrap_or_else(|pos| pos);
+                let end = indices.binary_search(&(offset + rows)).unwrap_or_else(|pos| pos);
+
+                Selection::Indices(indices[start .. end].iter().map(|row| row - offset).collect())
+            }
+            Selection::Bitmap(ref bitmap) =>
+                Selection::Bitmap(bitmap.as_bitmap().slice(offset, rows).to_owned_bitmap()),
+        }
+    }
+
+    /// Selection bitmap of a view with `rows` rows
+    pub fn to_bitmap(&self, rows: RowOffset) -> OwnedBitmap {
+        match *self {
+            Selection::Bitmap(ref bitmap) => bitmap.clone(),
+            Selection::Indices(ref indices) => {
+                let mut out = OwnedBitmap::new(rows, false);
+                {
+                    let mut m = out.as_mut_bitmap();
+                    for row in indices {
+                        m.set(*row, true);
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Rows selected by an (optional) selection of a view with `rows` rows. Without a selection all
+/// rows are selected.
+pub fn selected_rows(selection: Option<&Selection>, rows: RowOffset) -> SelectedRows {
+    selection.map_or(SelectedRows::All(0 .. rows), |s| s.iter())
+}
+
+/// Number of rows selected by an (optional) selection
+pub fn selected_count(selection: Option<&Selection>, rows: RowOffset) -> usize {
+    selection.map_or(rows, |s| s.count())
+}
+
+impl<'a> Iterator for SelectedRows<'a> {
+    type Item = RowOffset;
+
+    fn next(&mut self) -> Option<RowOffset> {
+        match *self {
+            SelectedRows::All(ref mut range) => range.next(),
+            SelectedRows::Indices(ref mut iter) => iter.next().cloned(),
+            SelectedRows::Bitmap(ref mut iter) => iter.next(),
+        }
+    }
+}
+
+/// Selection of the rows with the bit set in `bitmap`, eg. the output of a boolean expression.
+impl<'a> From<Bitmap<'a>> for Selection {
+    fn from(bitmap: Bitmap<'a>) -> Selection {
+        Selection::Bitmap(bitmap.to_owned_bitmap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indices_selection() {
+        assert!(Selection::from_indices(vec![1, 3, 3]).is_err());
+
+        let sel = Selection::from_indices(vec![1, 4, 5, 9]).unwrap();
+        assert!(sel.check(10).is_ok());
+        assert!(sel.check(9).is_err());
+        assert_eq!(sel.count(), 4);
+        assert!(sel.is_selected(5) && !sel.is_selected(6));
+
+        let window = sel.slice(4, 5);
+        assert_eq!(window.iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        let bitmap = sel.to_bitmap(10);
+        assert_eq!(bitmap.as_bitmap().iter_ones().collect::<Vec<_>>(), vec![1, 4, 5, 9]);
+    }
+
+    #[test]
+    fn bitmap_selection() {
+        let sel = Selection::from_bitmap(
+            OwnedBitmap::from_bools(&[true, false, false, true, true, false]));
+
+        assert!(sel.check(6).is_ok());
+        assert_eq!(sel.count(), 3);
+        assert_eq!(sel.slice(2, 3).iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(selected_rows(None, 3).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(selected_count(Some(&sel), 6), 3);
+    }
+}
@@ -0,0 +1,635 @@
+// vim : set ts=4 sw=4 et :
+
+//! Binary serialization of blocks, for shipping them between processes and writing spill files.
+//!
+//! The encoding is self-describing and versioned. All integers are little endian:
+//!
+//! ```text
+//! "DBKB" | version: u16 | header length: u32 | header CRC-32: u32 | header | body
+//! ```
+//!
+//! The header holds the number of rows, the length and CRC-32 of the body and the schema. The body
+//! holds the columns in schema order, each as its null vector (nullable columns only, in 64 bit
+//! words) followed by the row data:
+//!
+//! - Fixed width types: `size_of` bytes per row, NULL rows are zeroed. TIMESTAMP and INTERVAL are
+//!   written field by field.
+//! - TEXT / BLOB: a `u32` length per row, followed by the bytes of all the values.
+//! - LIST: a `u32` length per row, followed by the element column with a row per element.
+//! - STRUCT: the field columns.
+//!
+//! Data is written densely: VARLEN values are copied out of the arenas (dictionary encoded columns
+//! are written decoded) and LIST elements are written in row order, whatever their position in the
+//! element column.
+
+use std::io::{Read, Write};
+use std::str;
+
+use ::allocator::Allocator;
+use ::bitmaps::{self, Bitmap, OwnedBitmap};
+use ::block::{Block, Column, RefColumn, View, column_nulls, column_row_data, list_row_data};
+use ::block::varlen_at;
+use ::error::DBError;
+use ::row::RowOffset;
+use ::schema::{Attribute, Schema};
+use ::types::{self, IntervalValue, ListEntry, TimestampValue, Type};
+use ::util::crc32::crc32;
+use ::util::endian::{ByteReader, put_u8, put_u16, put_u32, put_u64};
+
+const MAGIC: &'static [u8] = b"DBKB";
+
+/// Version of the encoding written by `Block::write_to`
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Magic, version, header length & checksum
+const PREFIX_SIZE: usize = 14;
+
+/// Guards against allocating huge buffers for a corrupt header length
+const MAX_HEADER_SIZE: usize = 16 * 1024 * 1024;
+
+/// Guards against unbounded recursion on corrupt nested attributes
+const MAX_NESTING: usize = 64;
+
+/// Types by their tag in the encoding. Only append to this list.
+const TYPE_TAGS: [Type; 21] = [
+    Type::UINT8, Type::UINT16, Type::UINT32, Type::UINT64,
+    Type::INT8, Type::INT16, Type::INT32, Type::INT64,
+    Type::FLOAT32, Type::FLOAT64, Type::BOOLEAN,
+    Type::DATE, Type::TIMESTAMP, Type::INTERVAL, Type::DECIMAL,
+    Type::FIXED_BINARY, Type::UUID, Type::TEXT, Type::BLOB,
+    Type::LIST, Type::STRUCT,
+];
+
+fn corrupt<S: Into<String>>(what: S) -> DBError {
+    DBError::CorruptData(what.into())
+}
+
+impl<'b> Block<'b> {
+    /// Serialize the rows of the block (see `serialize` for the encoding)
+    pub fn write_to<W: Write>(&self, out: &mut W) -> Result<(), DBError> {
+        let view: &View = self;
+        let schema = view.schema();
+        let rows: Vec<RowOffset> = (0 .. view.rows()).collect();
+
+        let mut body = Vec::new();
+        for pos in 0 .. schema.count() {
+            write_column(&mut body, &self[pos], &rows)?;
+        }
+
+        let mut header = Vec::new();
+        put_u64(&mut header, view.rows() as u64);
+        put_u64(&mut header, body.len() as u64);
+        put_u32(&mut header, crc32(&body));
+        put_u32(&mut header, schema.count() as u32);
+
+        for attr in schema.iter() {
+            write_attribute(&mut header, attr);
+        }
+
+        let mut prefix = Vec::with_capacity(PREFIX_SIZE);
+        prefix.extend_from_slice(MAGIC);
+        put_u16(&mut prefix, FORMAT_VERSION);
+        put_u32(&mut prefix, header.len() as u32);
+        put_u32(&mut prefix, crc32(&header));
+
+        out.write_all(&prefix)
+            .and_then(|_| out.write_all(&header))
+            .and_then(|_| out.write_all(&body))
+            .map_err(DBError::IO)
+    }
+
+    /// Deserialize a block written by `write_to`. Malformed input, input that fails its checksums
+    /// or was written by an unknown version is a `DBError::CorruptData` error.
+    pub fn read_from<R: Read>(alloc: &'b Allocator, input: &mut R) -> Result<Block<'b>, DBError> {
+        let prefix = read_checked(input, PREFIX_SIZE, None, "prefix")?;
+        let mut reader = ByteReader::new(&prefix);
+
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(corrupt("not a serialized block (bad magic)"))
+        }
+
+        let version = reader.read_u16()?;
+        if version != FORMAT_VERSION {
+            return Err(corrupt(format!("unsupported format version {}", version)))
+        }
+
+        let header_len = reader.read_u32()? as usize;
+        let header_crc = reader.read_u32()?;
+
+        if header_len > MAX_HEADER_SIZE {
+            return Err(corrupt(format!("header too large ({} bytes)", header_len)))
+        }
+
+        let header = read_checked(input, header_len, Some(header_crc), "header")?;
+        let mut reader = ByteReader::new(&header);
+
+        let rows = reader.read_u64()? as usize;
+        let body_len = reader.read_u64()? as usize;
+        let body_crc = reader.read_u32()?;
+        let count = reader.read_u32()?;
+
+        let mut attrs = Vec::new();
+        for _ in 0 .. count {
+            attrs.push(read_attribute(&mut reader, 0)?);
+        }
+
+        if !reader.is_empty() {
+            return Err(corrupt("trailing bytes after the schema"))
+        }
+
+        let schema = Schema::from_vec(attrs)
+            .map_err(|err| corrupt(format!("invalid schema ({})", err)))?;
+        let body = read_checked(input, body_len, Some(body_crc), "body")?;
+        check_rows(schema.iter(), rows, body.len())?;
+
+        let mut block = Block::new(alloc, &schema);
+        block.add_rows(rows)?;
+
+        let mut reader = ByteReader::new(&body);
+        for pos in 0 .. schema.count() {
+            read_column(&mut reader, &mut block[pos], 0, rows)?;
+        }
+
+        if !reader.is_empty() {
+            return Err(corrupt("trailing bytes after the columns"))
+        }
+
+        Ok(block)
+    }
+}
+
+/// Read exactly `len` bytes, verifying their checksum
+fn read_checked<R: Read>(input: &mut R, len: usize, crc: Option<u32>, what: &str)
+    -> Result<Vec<u8>, DBError>
+{
+    // Grows as data arrives, so a bogus length doesn't allocate up front
+    let mut out = Vec::new();
+    input.take(len as u64).read_to_end(&mut out).map_err(DBError::IO)?;
+
+    if out.len() != len {
+        return Err(corrupt(format!("truncated {} ({} of {} bytes)", what, out.len(), len)))
+    }
+
+    if crc.map_or(false, |crc| crc != crc32(&out)) {
+        return Err(corrupt(format!("{} checksum mismatch", what)))
+    }
+
+    Ok(out)
+}
+
+/// Append the encoding of an attribute (and its nested attributes) to `out`
+pub fn write_attribute(out: &mut Vec<u8>, attr: &Attribute) {
+    let tag = TYPE_TAGS.iter().position(|dtype| *dtype == attr.dtype).unwrap();
+
+    put_u32(out, attr.name.len() as u32);
+    out.extend_from_slice(attr.name.as_bytes());
+    put_u8(out, attr.nullable as u8);
+    put_u8(out, tag as u8);
+    put_u8(out, attr.modifiers.precision);
+    put_u8(out, attr.modifiers.scale);
+    put_u32(out, attr.modifiers.width);
+    put_u32(out, attr.children.len() as u32);
+
+    for child in &attr.children {
+        write_attribute(out, child);
+    }
+}
+
+/// Read an attribute written by `write_attribute`. `depth` is the nesting level of the attribute,
+/// 0 for top level attributes.
+pub fn read_attribute(reader: &mut ByteReader, depth: usize) -> Result<Attribute, DBError> {
+    if depth > MAX_NESTING {
+        return Err(corrupt("attributes nested too deep"))
+    }
+
+    let len = reader.read_u32()? as usize;
+    let name = String::from_utf8(reader.read_bytes(len)?.to_vec())
+        .map_err(|_| corrupt("attribute name is not UTF-8"))?;
+
+    let nullable = reader.read_u8()? != 0;
+    let tag = reader.read_u8()? as usize;
+    let dtype = *TYPE_TAGS.get(tag)
+        .ok_or(corrupt(format!("{} (unknown type tag {})", name, tag)))?;
+
+    let precision = reader.read_u8()?;
+    let scale = reader.read_u8()?;
+    let width = reader.read_u32()?;
+
+    let count = reader.read_u32()? as usize;
+    let expected = match dtype {
+        Type::LIST => count == 1,
+        Type::STRUCT => true,
+        _ => count == 0,
+    };
+
+    if !expected {
+        return Err(corrupt(format!("{} ({} with {} children)", name, dtype.name(), count)))
+    }
+
+    let mut children = Vec::with_capacity(count);
+    for _ in 0 .. count {
+        children.push(read_attribute(reader, depth + 1)?);
+    }
+
+    // Only DECIMAL and FIXED_BINARY have modifiers, their constructors check them
+    let unused = match dtype {
+        Type::DECIMAL => width != 0,
+        Type::FIXED_BINARY => precision != 0 || scale != 0,
+        _ => precision != 0 || scale != 0 || width != 0,
+    };
+
+    if unused {
+        return Err(corrupt(format!("{} ({} with unexpected modifiers)", name, dtype.name())))
+    }
+
+    let invalid = |err: DBError| corrupt(format!("{} ({})", name, err));
+
+    match dtype {
+        Type::DECIMAL =>
+            Attribute::decimal(name.clone(), nullable, precision, scale).map_err(invalid),
+        Type::FIXED_BINARY =>
+            Attribute::fixed_binary(name.clone(), nullable, width).map_err(invalid),
+        Type::LIST => Ok(Attribute::list(name.clone(), nullable, children.pop().unwrap())),
+        Type::STRUCT => Attribute::structure(name.clone(), nullable, children).map_err(invalid),
+        _ => Ok(Attribute::new(name.clone(), nullable, dtype)),
+    }
+}
+
+/// Lower bound of the body bits a row of the attribute takes. NULL rows are written too.
+fn min_row_bits(attr: &Attribute) -> usize {
+    let nulls = if attr.nullable { 1 } else { 0 };
+
+    nulls + match attr.dtype {
+        Type::TEXT | Type::BLOB | Type::LIST => 32,
+        Type::TIMESTAMP => 88,
+        Type::STRUCT => attr.children.iter().map(min_row_bits).sum(),
+        _ => attr.size_of() * 8,
+    }
+}
+
+/// Check a row count read from the input fits the `len` bytes left to read, so a corrupt count
+/// doesn't allocate huge (or overflowing) row vectors.
+fn check_rows<'a, I>(attrs: I, rows: RowOffset, len: usize) -> Result<(), DBError>
+    where I: Iterator<Item=&'a Attribute>
+{
+    let bits: usize = attrs.map(min_row_bits).sum();
+
+    if bits > 0 && rows > len.saturating_mul(8) / bits {
+        return Err(corrupt(format!("{} rows don't fit in {} bytes", rows, len)))
+    }
+
+    Ok(())
+}
+
+/// Write rows `indices` of a column, see the module documentation for the layout
+fn write_column<'c>(out: &mut Vec<u8>, col: &'c RefColumn<'c>, indices: &[RowOffset])
+    -> Result<(), DBError>
+{
+    let attr = col.attribute();
+    let nulls = column_nulls(col);
+
+    if attr.nullable {
+        let mut bits = OwnedBitmap::new(indices.len(), false);
+        {
+            let mut m = bits.as_mut_bitmap();
+            for (pos, idx) in indices.iter().enumerate() {
+                if nulls.is_set(*idx) {
+                    m.set(pos, true);
+                }
+            }
+        }
+
+        for word in bits.words() {
+            put_u64(out, *word);
+        }
+    }
+
+    match attr.dtype {
+        Type::TIMESTAMP => {
+            let values = column_row_data::<types::Timestamp>(col)?.values;
+
+            for idx in indices {
+                let ts = if nulls.is_set(*idx) { TimestampValue::utc(0) } else { values[*idx] };
+                put_u64(out, ts.micros as u64);
+                put_u8(out, ts.offset.is_some() as u8);
+                put_u16(out, ts.offset.unwrap_or(0) as u16);
+            }
+        }
+        Type::INTERVAL => {
+            let values = column_row_data::<types::Interval>(col)?.values;
+
+            for idx in indices {
+                let iv = if nulls.is_set(*idx) { IntervalValue::default() } else { values[*idx] };
+                put_u32(out, iv.months as u32);
+                put_u32(out, iv.days as u32);
+                put_u64(out, iv.micros as u64);
+            }
+        }
+        Type::TEXT | Type::BLOB => {
+            let value = |idx: RowOffset| {
+                if nulls.is_set(idx) { &[][..] } else { varlen_at(col, idx) }
+            };
+
+            for idx in indices {
+                put_u32(out, value(*idx).len() as u32);
+            }
+
+            for idx in indices {
+                out.extend_from_slice(value(*idx));
+            }
+        }
+        Type::LIST => {
+            let lists = list_row_data(col)?;
+            let mut elements = Vec::new();
+
+            for idx in indices {
+                if lists.nulls.is_set(*idx) {
+                    put_u32(out, 0);
+                } else {
+                    let range = lists.range(*idx);
+                    put_u32(out, range.len() as u32);
+                    elements.extend(range);
+                }
+            }
+
+            write_column(out, lists.elements, &elements)?;
+        }
+        Type::STRUCT => {
+            for pos in 0 .. attr.children.len() {
+                let field = col.child(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+                write_column(out, field, indices)?;
+            }
+        }
+        _ => {
+            let size = attr.size_of();
+            let raw = col.rows_raw_slice();
+            let zeros = vec![0; size];
+
+            for idx in indices {
+                if nulls.is_set(*idx) {
+                    out.extend_from_slice(&zeros);
+                } else {
+                    out.extend_from_slice(&raw[idx * size .. (idx + 1) * size]);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read `rows` rows of a column into `col` starting at row `at`
+fn read_column(reader: &mut ByteReader, col: &mut Column, at: RowOffset, rows: RowOffset)
+    -> Result<(), DBError>
+{
+    let attr = col.attribute().clone();
+
+    if attr.nullable {
+        let mut words = Vec::with_capacity(bitmaps::words_for(rows));
+        for _ in 0 .. bitmaps::words_for(rows) {
+            words.push(reader.read_u64()?);
+        }
+
+        col.nulls_mut()?.copy_from(at, &Bitmap::new(&words, rows));
+    }
+
+    match attr.dtype {
+        Type::TIMESTAMP => {
+            let values = col.rows_mut::<types::Timestamp>()?;
+
+            for row in at .. at + rows {
+                let micros = reader.read_u64()? as i64;
+                let has_offset = reader.read_u8()? != 0;
+                let offset = reader.read_u16()? as i16;
+
+                values[row] = TimestampValue {
+                    micros: micros,
+                    offset: if has_offset { Some(offset) } else { None },
+                };
+            }
+        }
+        Type::INTERVAL => {
+            let values = col.rows_mut::<types::Interval>()?;
+
+            for row in at .. at + rows {
+                let months = reader.read_u32()? as i32;
+                let days = reader.read_u32()? as i32;
+                let micros = reader.read_u64()? as i64;
+                values[row] = IntervalValue::new(months, days, micros);
+            }
+        }
+        Type::TEXT | Type::BLOB => {
+            let lens = read_lengths(reader, rows)?;
+
+            for (row, len) in (at .. at + rows).zip(lens) {
+                let value = reader.read_bytes(len)?;
+
+                // TEXT values are read as `str` without checking
+                if attr.dtype == Type::TEXT && str::from_utf8(value).is_err() {
+                    return Err(corrupt(format!("{} (TEXT value is not UTF-8)", attr.name)))
+                }
+
+                col.set_varlen(row, value)?;
+            }
+        }
+        Type::LIST => {
+            let lens = read_lengths(reader, rows)?;
+            let total = lens.iter().sum();
+            check_rows(attr.children.iter(), total, reader.remaining())?;
+            let start = col.list_reserve(total)?;
+
+            {
+                let entries = col.rows_mut::<types::List>()?;
+                let mut offset = start;
+
+                for (row, len) in (at .. at + rows).zip(&lens) {
+                    entries[row] = ListEntry { offset: offset as u32, len: *len as u32 };
+                    offset += *len;
+                }
+            }
+
+            let elements = col.child_mut(0)
+                .ok_or(DBError::AttributeMissing(format!("{}.element", attr.name)))?;
+            read_column(reader, elements, start, total)?;
+        }
+        Type::STRUCT => {
+            for pos in 0 .. attr.children.len() {
+                let field = col.child_mut(pos)
+                    .ok_or(DBError::make_column_unknown_pos(pos))?;
+                read_column(reader, field, at, rows)?;
+            }
+        }
+        _ => {
+            let size = attr.size_of();
+            let data = reader.read_bytes(rows * size)?;
+
+            if attr.dtype == Type::BOOLEAN && data.iter().any(|byte| *byte > 1) {
+                return Err(corrupt(format!("{} (BOOLEAN value not 0 or 1)", attr.name)))
+            }
+
+            col.rows_raw_mut()[at * size .. (at + rows) * size].copy_from_slice(data);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_lengths(reader: &mut ByteReader, rows: RowOffset) -> Result<Vec<usize>, DBError> {
+    let mut out = Vec::with_capacity(rows);
+    for _ in 0 .. rows {
+        out.push(reader.read_u32()? as usize);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use schema::TypeModifiers;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    /// Serialized block with a header made of the arguments, with valid checksums
+    fn assemble(rows: u64, attrs: &[Attribute], body: &[u8]) -> Vec<u8> {
+        let mut header = Vec::new();
+        put_u64(&mut header, rows);
+        put_u64(&mut header, body.len() as u64);
+        put_u32(&mut header, crc32(body));
+        put_u32(&mut header, attrs.len() as u32);
+
+        for attr in attrs {
+            write_attribute(&mut header, attr);
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put_u16(&mut out, FORMAT_VERSION);
+        put_u32(&mut out, header.len() as u32);
+        put_u32(&mut out, crc32(&header));
+        out.extend_from_slice(&header);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn is_corrupt(data: &[u8]) -> bool {
+        match Block::read_from(&allocator::GLOBAL, &mut &data[..]) {
+            Err(DBError::CorruptData(_)) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn write_read_block() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("name", false, Type::TEXT),
+            Attribute::new("at", true, Type::TIMESTAMP),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::TEXT)),
+            Attribute::decimal("price", false, 10, 2).unwrap(),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let at = TimestampValue { micros: 1500, offset: Some(-120) };
+            let price: DecimalValue = "12.50".parse().unwrap();
+
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i32).set("one").set(at).set(vec!["a", "b"]).set(price)
+                .add_row().set(NULL_VALUE).set("two").set(NULL_VALUE).set(NULL_VALUE).set(price)
+                .add_row().set(3 as i32).set("one").set(at).set(vec!["c"]).set(price)
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut block = table.take().unwrap();
+        block.encode_dictionary(1).unwrap();
+
+        let mut data = Vec::new();
+        block.write_to(&mut data).unwrap();
+
+        let read = Block::read_from(&allocator::GLOBAL, &mut &data[..]).unwrap();
+        assert_views_eq!(&block, &read);
+    }
+
+    #[test]
+    fn corrupt_input() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("name", false, Type::TEXT),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i32).set("one")
+                .add_row().set(NULL_VALUE).set("two")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut data = Vec::new();
+        table.block_ref().write_to(&mut data).unwrap();
+
+        let mut flipped = data.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 0xFF;
+        assert!(is_corrupt(&flipped));
+
+        assert!(is_corrupt(&data[.. data.len() - 3]));
+        assert!(is_corrupt(b"DBKX\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00"));
+    }
+
+    #[test]
+    fn corrupt_checksummed_input() {
+        let text = Attribute::new("t", false, Type::TEXT);
+
+        let mut body = Vec::new();
+        put_u32(&mut body, 2);
+        body.extend_from_slice(b"ok");
+        assert!(Block::read_from(&allocator::GLOBAL, &mut &assemble(1, &[text.clone()], &body)[..])
+            .is_ok());
+
+        // Row counts the body can't hold
+        assert!(is_corrupt(&assemble(1 << 40, &[text.clone()], &body)));
+        let wide = Attribute::new("i", false, Type::INT64);
+        assert!(is_corrupt(&assemble(u64::max_value() / 2, &[wide.clone()], &[])));
+
+        let mut lists = Vec::new();
+        put_u32(&mut lists, u32::max_value());
+        assert!(is_corrupt(&assemble(1, &[Attribute::list("l", false, wide)], &lists)));
+
+        let mut body = Vec::new();
+        put_u32(&mut body, 2);
+        body.extend_from_slice(&[0xC3, 0x28]);
+        assert!(is_corrupt(&assemble(1, &[text], &body)), "TEXT that isn't UTF-8");
+
+        // Attributes the constructors reject
+        let decimal = Attribute {
+            modifiers: TypeModifiers::decimal(0, 0),
+            .. Attribute::new("d", false, Type::DECIMAL)
+        };
+        let fixed = Attribute {
+            modifiers: TypeModifiers::fixed(0),
+            .. Attribute::new("f", false, Type::FIXED_BINARY)
+        };
+        let int = Attribute {
+            modifiers: TypeModifiers::fixed(4),
+            .. Attribute::new("i", false, Type::INT32)
+        };
+        let field = Attribute::new("x", false, Type::INT32);
+        let duplicate = Attribute {
+            children: vec![field.clone(), field],
+            .. Attribute::new("s", false, Type::STRUCT)
+        };
+
+        for attr in vec![decimal, fixed, int, duplicate] {
+            assert!(is_corrupt(&assemble(0, &[attr], &[])));
+        }
+    }
+}
@@ -0,0 +1,419 @@
+// vim : set ts=4 sw=4 et :
+
+//! Views over reference counted column buffers. `RefView` and `AliasColumn` borrow the source
+//! block, a `SharedView` owns a reference to the columns instead: aliasing a column (or a window
+//! of rows) bumps a count rather than borrowing, so shared views are `'static + Send` and can be
+//! returned from functions, handed to other threads or kept past a cursor step.
+//!
+//! Blocks using a `'static` allocator (eg. `allocator::GLOBAL`) move their columns into a shared
+//! view without copying (`Block::into_shared`), other views are copied first
+//! (`SharedView::copy_from`). The columns are immutable once shared.
+
+use std::sync::Arc;
+
+use ::allocator;
+use ::bitmaps::OwnedBitmap;
+use ::block::{Column, Encoding, RefColumn, View, compact, fold_parent_nulls};
+use ::error::DBError;
+use ::row::{RowOffset, RowRange};
+use ::schema::{Attribute, Schema};
+use ::selection::Selection;
+use ::types::{RawData, Type};
+
+/// Reference counted alias of a row range of a column
+#[derive(Clone)]
+pub struct SharedColumn {
+    /// Keeps the (top level) column the buffers belong to alive
+    owner: Arc<Column<'static>>,
+    /// The aliased column, `owner` or one of its nested columns
+    column: *const Column<'static>,
+    /// First aliased row of the column
+    offset: RowOffset,
+    rows: RowOffset,
+    children: Vec<SharedColumn>,
+    /// Null vector and attribute replacing the column's own, when the NULLs of parent STRUCTs are
+    /// folded in (see `SharedView::alias_nested_column`)
+    folded: Option<Arc<FoldedNulls>>,
+}
+
+/// Null vector of a STRUCT field including the NULLs of its parents
+struct FoldedNulls {
+    attr: Attribute,
+    /// A bit for every row of the underlying column, like its own null vector
+    nulls: OwnedBitmap,
+}
+
+// The columns are never mutated once shared, and the pointer is into memory owned by `owner`
+unsafe impl Send for SharedColumn {}
+unsafe impl Sync for SharedColumn {}
+
+impl SharedColumn {
+    /// Share the first `rows` rows of a column
+    pub fn new(column: Column<'static>, rows: RowOffset) -> SharedColumn {
+        let owner = Arc::new(column);
+        let column: *const Column<'static> = &*owner;
+        share_nested(&owner, column, rows)
+    }
+
+    fn column(&self) -> &Column<'static> {
+        unsafe { &*self.column }
+    }
+
+    /// Alias of a row range of this column, sharing the same buffers
+    pub fn slice(&self, range: RowRange) -> Result<SharedColumn, DBError> {
+        if range.offset + range.rows > self.rows {
+            return Err(DBError::RowOutOfBounds)
+        }
+
+        // LIST entries are relative to the whole element column, STRUCT fields have a row per row
+        let children = match self.attribute().dtype {
+            Type::STRUCT => self.children.iter()
+                .map(|child| child.slice(range))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => self.children.clone(),
+        };
+
+        Ok(SharedColumn {
+            owner: self.owner.clone(),
+            column: self.column,
+            offset: self.offset + range.offset,
+            rows: range.rows,
+            children: children,
+            folded: self.folded.clone(),
+        })
+    }
+
+    /// Nested column (the element column of a LIST, fields of a STRUCT)
+    pub fn shared_child(&self, pos: usize) -> Option<&SharedColumn> {
+        self.children.get(pos)
+    }
+}
+
+/// Share `rows` rows of `column`, which belongs to `owner`
+fn share_nested(owner: &Arc<Column<'static>>, column: *const Column<'static>, rows: RowOffset)
+    -> SharedColumn
+{
+    let col: &'static Column<'static> = unsafe { &*column };
+
+    let children = col.children().iter()
+        .map(|child| {
+            // LIST elements in use are shared whole
+            let rows = if col.attribute().dtype == Type::LIST { col.element_rows() } else { rows };
+            share_nested(owner, child, rows)
+        })
+        .collect();
+
+    SharedColumn {
+        owner: owner.clone(),
+        column: column,
+        offset: 0,
+        rows: rows,
+        children: children,
+        folded: None,
+    }
+}
+
+impl<'a> RefColumn<'a> for SharedColumn {
+    fn attribute(&self) -> &Attribute {
+        match self.folded {
+            Some(ref folded) => &folded.attr,
+            None => self.column().attribute(),
+        }
+    }
+
+    /// Row capacity
+    fn capacity(&self) -> usize {
+        self.rows
+    }
+
+    fn rows_raw_slice(&'a self) -> &'a [u8] {
+        let size = self.encoding().row_size(self.attribute());
+
+        unsafe {
+            let ptr = self.rows_ptr();
+            if ptr.is_null() { &[] } else { ::std::slice::from_raw_parts(ptr, self.rows * size) }
+        }
+    }
+
+    /// Pointer to the beginning of the raw row data
+    unsafe fn rows_ptr(&self) -> *const u8 {
+        let ptr = self.column().rows_ptr();
+        let size = self.encoding().row_size(self.attribute());
+
+        if ptr.is_null() { ptr } else { ptr.offset((self.offset * size) as isize) }
+    }
+
+    /// Pointer to the words of the null bitmap
+    unsafe fn nulls_ptr(&self) -> *const u8 {
+        match self.folded {
+            Some(ref folded) => folded.nulls.as_bitmap().words_ptr(),
+            None => self.column().nulls_ptr(),
+        }
+    }
+
+    fn nulls_offset(&self) -> usize {
+        self.offset
+    }
+
+    fn encoding(&self) -> Encoding {
+        self.column().encoding()
+    }
+
+    fn dictionary(&self) -> Option<&[RawData]> {
+        self.column().dictionary()
+    }
+
+    fn varlen_storage(&self) -> Option<Vec<&[u8]>> {
+        self.column().varlen_storage()
+    }
+
+    fn child(&'a self, pos: usize) -> Option<&'a RefColumn<'a>> {
+        self.children.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+}
+
+/// View over reference counted columns. Cloning the view, or taking a window of it, shares the
+/// column buffers.
+#[derive(Clone)]
+pub struct SharedView {
+    schema: Schema,
+    columns: Vec<SharedColumn>,
+    rows: RowOffset,
+    selection: Option<Selection>,
+}
+
+impl<'a> View<'a> for SharedView {
+    fn schema(&'a self) -> &'a Schema {
+        &self.schema
+    }
+
+    fn column(&'a self, pos: usize) -> Option<&'a RefColumn<'a>> {
+        self.columns.get(pos)
+            .map(|c| c as &RefColumn)
+    }
+
+    fn rows(&self) -> RowOffset {
+        self.rows
+    }
+
+    fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+}
+
+impl SharedView {
+    pub fn new(schema: Schema, columns: Vec<SharedColumn>, rows: RowOffset) -> SharedView {
+        SharedView { schema: schema, columns: columns, rows: rows, selection: None }
+    }
+
+    /// Copy the selected rows of a view into shared columns (see `block::compact`)
+    pub fn copy_from<'v>(src: &'v View<'v>) -> Result<SharedView, DBError> {
+        compact(&allocator::GLOBAL, src).map(|block| block.into_shared())
+    }
+
+    pub fn shared_column(&self, pos: usize) -> Option<&SharedColumn> {
+        self.columns.get(pos)
+    }
+
+    /// Resolve a column by its position path, descending into STRUCT fields (see
+    /// `block::nested_column`)
+    pub fn nested_column(&self, path: &[usize]) -> Result<&SharedColumn, DBError> {
+        self.nested_columns(path)
+            .map(|columns| columns[columns.len() - 1])
+    }
+
+    /// Columns along a position path, from the top level column to the resolved field
+    fn nested_columns(&self, path: &[usize]) -> Result<Vec<&SharedColumn>, DBError> {
+        let first = match path.first() {
+            Some(pos) => *pos,
+            None => return Err(DBError::AttributeMissing(String::from("(empty path)"))),
+        };
+
+        let mut col = self.shared_column(first)
+            .ok_or(DBError::make_column_unknown_pos(first))?;
+        let mut out = vec![col];
+
+        for pos in &path[1..] {
+            col = col.shared_child(*pos)
+                .ok_or(DBError::make_column_unknown_pos(*pos))?;
+            out.push(col);
+        }
+
+        Ok(out)
+    }
+
+    /// Share a column resolved by its position path, with the NULLs of its parent STRUCTs folded
+    /// into its null vector (see `block::alias_nested_column`)
+    pub fn alias_nested_column(&self, path: &[usize]) -> Result<SharedColumn, DBError> {
+        let columns = self.nested_columns(path)?;
+        let (col, parents) = columns.split_last().unwrap();
+
+        if !parents.iter().any(|p| p.attribute().nullable) {
+            return Ok((*col).clone())
+        }
+
+        let parents: Vec<&RefColumn> = parents.iter().map(|p| *p as &RefColumn).collect();
+        let rows = fold_parent_nulls(*col, &parents, RowRange { offset: 0, rows: col.rows })?;
+
+        // Slices of the column keep indexing the null vector from the underlying column's rows
+        let mut nulls = OwnedBitmap::new(col.offset + col.rows, false);
+        nulls.as_mut_bitmap().copy_from(col.offset, &rows.as_bitmap());
+
+        let mut attr = col.attribute().clone();
+        attr.nullable = true;
+
+        Ok(SharedColumn {
+            folded: Some(Arc::new(FoldedNulls { attr: attr, nulls: nulls })),
+            .. (*col).clone()
+        })
+    }
+
+    /// Window of the view sharing its columns. The window keeps the part of the selection that
+    /// falls into it (see `block::window_alias`).
+    pub fn window(&self, range: Option<RowRange>) -> Result<SharedView, DBError> {
+        let range = range.unwrap_or(RowRange { offset: 0, rows: self.rows });
+
+        if range.offset + range.rows > self.rows {
+            return Err(DBError::RowOutOfBounds)
+        }
+
+        let columns = self.columns.iter()
+            .map(|col| col.slice(range))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SharedView {
+            schema: self.schema.clone(),
+            columns: columns,
+            rows: range.rows,
+            selection: self.selection.as_ref().map(|s| s.slice(range.offset, range.rows)),
+        })
+    }
+
+    /// Only expose the selected rows to consumers (replacing the current selection)
+    pub fn with_selection(mut self, selection: Option<Selection>)
+        -> Result<SharedView, DBError>
+    {
+        if let Some(ref sel) = selection {
+            sel.check(self.rows)?;
+        }
+
+        self.selection = selection;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use block::{column_value, window_alias};
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn share_block() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT64),
+            Attribute::new("name", false, Type::TEXT),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::TEXT)),
+            Attribute::structure("point", false, vec![
+                Attribute::new("x", false, Type::INT32),
+                Attribute::new("y", true, Type::INT32),
+            ]).unwrap(),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i64).set("one").set(vec!["a", "b"])
+                .add_row().set(NULL_VALUE).set("two").set(NULL_VALUE)
+                .add_row().set(3 as i64).set("three").set(vec!["c"])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        // STRUCT fields aren't supported by the appender
+        let mut block = table.take().unwrap();
+        for (row, x) in block.column_mut(3).unwrap().child_mut(0).unwrap()
+            .rows_mut::<Int32>().unwrap().iter_mut().enumerate()
+        {
+            *x = row as i32 * 10;
+        }
+
+        // The table (and the borrow of it) is gone, the view owns its columns
+        let view = block.into_shared();
+        assert_eq!(view.rows(), 3);
+
+        let window = view.window(Some(RowRange { offset: 1, rows: 2 })).unwrap();
+        drop(view);
+
+        let handle = thread::spawn(move || {
+            let col = window.column(0).unwrap();
+            match (column_value(col, 0).unwrap(), column_value(col, 1).unwrap()) {
+                (Value::NULL, Value::INT64(3)) => {},
+                _ => panic!("unexpected ids"),
+            }
+
+            match column_value(window.column(2).unwrap(), 1).unwrap() {
+                Value::LIST(ref tags) if tags.len() == 1 => {},
+                _ => panic!("unexpected tags"),
+            }
+
+            let x = window.nested_column(&[3, 0]).unwrap();
+            match column_value(x, 1).unwrap() {
+                Value::INT32(20) => {},
+                _ => panic!("unexpected point.x"),
+            }
+
+            match column_value(window.column(1).unwrap(), 1).unwrap() {
+                Value::TEXT(name) => String::from(name),
+                _ => panic!("unexpected name"),
+            }
+        });
+
+        assert_eq!(handle.join().unwrap(), "three");
+    }
+
+    #[test]
+    fn copy_view() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT64),
+            Attribute::new("name", false, Type::TEXT),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i64).set("one")
+                .add_row().set(NULL_VALUE).set("two")
+                .add_row().set(3 as i64).set("three")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let view = table.take().unwrap().into_shared();
+        let copy = {
+            let alias = window_alias(&view, Some(RowRange { offset: 1, rows: 2 })).unwrap()
+                .with_selection(Some(Selection::from_indices(vec![1]).unwrap()))
+                .unwrap();
+
+            SharedView::copy_from(&alias).unwrap()
+        };
+
+        assert_eq!((copy.rows(), copy.selection().is_none()), (1, true));
+
+        match column_value(copy.column(1).unwrap(), 0).unwrap() {
+            Value::TEXT("three") => {},
+            _ => panic!("unexpected name"),
+        }
+
+        let selected = copy.clone().with_selection(Some(Selection::from_indices(vec![0]).unwrap()));
+        assert!(selected.is_ok());
+        assert!(copy.with_selection(Some(Selection::from_indices(vec![5]).unwrap())).is_err());
+    }
+}
@@ -0,0 +1,561 @@
+// vim : set ts=4 sw=4 et :
+
+//! Per-column statistics for planning and pruning: row and NULL counts, min / max values, the
+//! average size of VARLEN values and an approximate (HyperLogLog) distinct count.
+//!
+//! Statistics of blocks merge into statistics of the whole table, and serialize so they can be
+//! persisted next to the data. All integers are little endian:
+//!
+//! ```text
+//! "DBKS" | version: u16 | body length: u32 | body CRC-32: u32 | body
+//! ```
+//!
+//! The body holds the number of columns followed by the statistics of each: the attribute, the
+//! row, NULL and VARLEN byte counts, the min and max values (a presence byte, then the value) and
+//! the sketch registers.
+
+use std::cmp;
+use std::io::{Read, Write};
+
+use ::block::{View, column_value};
+use ::error::DBError;
+use ::schema::Attribute;
+use ::selection;
+use ::serialize::{read_attribute, write_attribute};
+use ::types::{DateValue, DecimalValue, IntervalValue, TimestampValue, Type};
+use ::types::{UuidValue, Value};
+use ::util::crc32::crc32;
+use ::util::endian::{ByteReader, put_u8, put_u16, put_u32, put_u64};
+use ::util::hll::{self, HyperLogLog};
+
+const MAGIC: &'static [u8] = b"DBKS";
+
+/// Version of the serialized statistics, bumped on every incompatible change
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Magic, version, body length and CRC
+const PREFIX_SIZE: usize = 14;
+
+fn corrupt<S: Into<String>>(what: S) -> DBError {
+    DBError::CorruptData(what.into())
+}
+
+/// Owned min / max value, outliving the view the statistics were computed on
+#[derive(Clone, PartialEq, Debug)]
+pub enum StatValue {
+    /// Fixed width values
+    Scalar(Value<'static>),
+    Text(String),
+    Blob(Vec<u8>),
+    FixedBinary(Vec<u8>),
+}
+
+impl StatValue {
+    /// `None` for NULLs and nested (LIST, STRUCT) values, which have no min / max
+    pub fn from_value(value: &Value) -> Option<StatValue> {
+        let scalar = match *value {
+            Value::NULL | Value::LIST(_) | Value::STRUCT(_) => return None,
+            Value::TEXT(v) => return Some(StatValue::Text(String::from(v))),
+            Value::BLOB(v) => return Some(StatValue::Blob(v.to_vec())),
+            Value::FIXED_BINARY(v) => return Some(StatValue::FixedBinary(v.to_vec())),
+            Value::UINT8(v) => Value::UINT8(v),
+            Value::UINT16(v) => Value::UINT16(v),
+            Value::UINT32(v) => Value::UINT32(v),
+            Value::UINT64(v) => Value::UINT64(v),
+            Value::INT8(v) => Value::INT8(v),
+            Value::INT16(v) => Value::INT16(v),
+            Value::INT32(v) => Value::INT32(v),
+            Value::INT64(v) => Value::INT64(v),
+            Value::FLOAT32(v) => Value::FLOAT32(v),
+            Value::FLOAT64(v) => Value::FLOAT64(v),
+            Value::BOOLEAN(v) => Value::BOOLEAN(v),
+            Value::DATE(v) => Value::DATE(v),
+            Value::TIMESTAMP(v) => Value::TIMESTAMP(v),
+            Value::INTERVAL(v) => Value::INTERVAL(v),
+            Value::DECIMAL(v) => Value::DECIMAL(v),
+            Value::UUID(v) => Value::UUID(v),
+        };
+
+        Some(StatValue::Scalar(scalar))
+    }
+
+    pub fn as_value(&self) -> Value {
+        match *self {
+            StatValue::Scalar(ref v) => v.clone(),
+            StatValue::Text(ref v) => Value::TEXT(v),
+            StatValue::Blob(ref v) => Value::BLOB(v),
+            StatValue::FixedBinary(ref v) => Value::FIXED_BINARY(v),
+        }
+    }
+}
+
+/// Statistics of a column (or the rows of it selected by a view)
+#[derive(Clone)]
+pub struct ColumnStats {
+    pub attribute: Attribute,
+    /// Number of rows, including NULL rows
+    pub rows: u64,
+    pub nulls: u64,
+    /// Smallest and largest non NULL value, in `Value::compare` order. Always `None` for LIST and
+    /// STRUCT columns.
+    pub min: Option<StatValue>,
+    pub max: Option<StatValue>,
+    /// Total size of the non NULL TEXT / BLOB values
+    pub varlen_bytes: u64,
+    /// Sketch of the non NULL values
+    pub distinct: HyperLogLog,
+}
+
+impl ColumnStats {
+    /// Empty statistics (no rows) of a column
+    pub fn new(attr: &Attribute) -> ColumnStats {
+        ColumnStats {
+            attribute: attr.clone(),
+            rows: 0,
+            nulls: 0,
+            min: None,
+            max: None,
+            varlen_bytes: 0,
+            distinct: HyperLogLog::default(),
+        }
+    }
+
+    /// Account for a row with `value`
+    pub fn add(&mut self, value: &Value) {
+        self.rows += 1;
+
+        match *value {
+            Value::NULL => {
+                self.nulls += 1;
+                return
+            }
+            Value::TEXT(v) => self.varlen_bytes += v.len() as u64,
+            Value::BLOB(v) => self.varlen_bytes += v.len() as u64,
+            _ => {},
+        }
+
+        self.distinct.insert(value);
+
+        if self.min.as_ref().map_or(true, |min| *value < min.as_value()) {
+            self.min = StatValue::from_value(value);
+        }
+
+        if self.max.as_ref().map_or(true, |max| *value > max.as_value()) {
+            self.max = StatValue::from_value(value);
+        }
+    }
+
+    /// Combine with the statistics of more rows of the same column (eg. of another block)
+    pub fn merge(&mut self, other: &ColumnStats) -> Result<(), DBError> {
+        if other.attribute.dtype != self.attribute.dtype {
+            return Err(DBError::AttributeType(other.attribute.name.clone()))
+        }
+
+        self.rows += other.rows;
+        self.nulls += other.nulls;
+        self.varlen_bytes += other.varlen_bytes;
+        self.distinct.merge(&other.distinct);
+
+        if let Some(ref min) = other.min {
+            if self.min.as_ref().map_or(true, |cur| min.as_value() < cur.as_value()) {
+                self.min = Some(min.clone());
+            }
+        }
+
+        if let Some(ref max) = other.max {
+            if self.max.as_ref().map_or(true, |cur| max.as_value() > cur.as_value()) {
+                self.max = Some(max.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn non_null(&self) -> u64 {
+        self.rows - self.nulls
+    }
+
+    /// Average size of the non NULL values of TEXT / BLOB columns, `None` for other types or
+    /// without non NULL values
+    pub fn avg_varlen_size(&self) -> Option<f64> {
+        match self.attribute.dtype {
+            Type::TEXT | Type::BLOB if self.non_null() > 0 =>
+                Some(self.varlen_bytes as f64 / self.non_null() as f64),
+            _ => None,
+        }
+    }
+
+    /// Estimated number of distinct non NULL values
+    pub fn distinct_count(&self) -> u64 {
+        cmp::min(self.distinct.estimate(), self.non_null())
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        write_attribute(out, &self.attribute);
+        put_u64(out, self.rows);
+        put_u64(out, self.nulls);
+        put_u64(out, self.varlen_bytes);
+        write_stat_value(out, self.min.as_ref());
+        write_stat_value(out, self.max.as_ref());
+        put_u8(out, self.distinct.precision());
+        out.extend_from_slice(self.distinct.registers());
+    }
+
+    pub fn read_from(reader: &mut ByteReader) -> Result<ColumnStats, DBError> {
+        let attribute = read_attribute(reader, 0)?;
+        let rows = reader.read_u64()?;
+        let nulls = reader.read_u64()?;
+        let varlen_bytes = reader.read_u64()?;
+        let min = read_stat_value(reader, &attribute)?;
+        let max = read_stat_value(reader, &attribute)?;
+
+        if nulls > rows {
+            return Err(corrupt(format!("{} ({} NULLs in {} rows)", attribute.name, nulls, rows)))
+        }
+
+        let precision = reader.read_u8()?;
+        if precision > hll::MAX_PRECISION {
+            return Err(corrupt(format!("{} (sketch precision {})", attribute.name, precision)))
+        }
+
+        let registers = reader.read_bytes(1 << precision)?.to_vec();
+
+        Ok(ColumnStats {
+            attribute: attribute,
+            rows: rows,
+            nulls: nulls,
+            min: min,
+            max: max,
+            varlen_bytes: varlen_bytes,
+            distinct: HyperLogLog::from_registers(registers)?,
+        })
+    }
+}
+
+/// Statistics of the rows of column `pos` of a view (respecting its selection)
+pub fn column_stats<'v>(view: &'v View<'v>, pos: usize) -> Result<ColumnStats, DBError> {
+    let col = view.column(pos).ok_or(DBError::make_column_unknown_pos(pos))?;
+    let mut stats = ColumnStats::new(col.attribute());
+
+    for row in selection::selected_rows(view.selection(), view.rows()) {
+        stats.add(&column_value(col, row)?);
+    }
+
+    Ok(stats)
+}
+
+/// Statistics of every column of a view, in schema order
+pub fn view_stats<'v>(view: &'v View<'v>) -> Result<Vec<ColumnStats>, DBError> {
+    (0 .. view.schema().count()).map(|pos| column_stats(view, pos)).collect()
+}
+
+/// Merge the statistics of another view (or block) of the same schema into `stats`
+pub fn merge_stats(stats: &mut [ColumnStats], other: &[ColumnStats]) -> Result<(), DBError> {
+    if stats.len() != other.len() {
+        return Err(DBError::AttributeMissing(
+            format!("merging statistics of {} and {} columns", stats.len(), other.len())))
+    }
+
+    for (lhs, rhs) in stats.iter_mut().zip(other) {
+        lhs.merge(rhs)?;
+    }
+
+    Ok(())
+}
+
+/// Serialize the statistics of a set of columns (see `stats` for the encoding)
+pub fn write_stats<W: Write>(stats: &[ColumnStats], out: &mut W) -> Result<(), DBError> {
+    let mut body = Vec::new();
+    put_u32(&mut body, stats.len() as u32);
+
+    for column in stats {
+        column.write_to(&mut body);
+    }
+
+    let mut prefix = Vec::with_capacity(PREFIX_SIZE);
+    prefix.extend_from_slice(MAGIC);
+    put_u16(&mut prefix, FORMAT_VERSION);
+    put_u32(&mut prefix, body.len() as u32);
+    put_u32(&mut prefix, crc32(&body));
+
+    out.write_all(&prefix)
+        .and_then(|_| out.write_all(&body))
+        .map_err(DBError::IO)
+}
+
+/// Deserialize statistics written by `write_stats`. Malformed input, input that fails its checksum
+/// or was written by an unknown version is a `DBError::CorruptData` error.
+pub fn read_stats<R: Read>(input: &mut R) -> Result<Vec<ColumnStats>, DBError> {
+    let mut prefix = [0; PREFIX_SIZE];
+    input.read_exact(&mut prefix).map_err(|_| corrupt("truncated prefix"))?;
+    let mut reader = ByteReader::new(&prefix);
+
+    if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(corrupt("not serialized statistics (bad magic)"))
+    }
+
+    let version = reader.read_u16()?;
+    if version != FORMAT_VERSION {
+        return Err(corrupt(format!("unsupported format version {}", version)))
+    }
+
+    let len = reader.read_u32()? as usize;
+    let crc = reader.read_u32()?;
+
+    // Grows as data arrives, so a bogus length doesn't allocate up front
+    let mut body = Vec::new();
+    input.take(len as u64).read_to_end(&mut body).map_err(DBError::IO)?;
+
+    if body.len() != len {
+        return Err(corrupt(format!("truncated body ({} of {} bytes)", body.len(), len)))
+    }
+
+    if crc != crc32(&body) {
+        return Err(corrupt("body checksum mismatch"))
+    }
+
+    let mut reader = ByteReader::new(&body);
+    let count = reader.read_u32()?;
+    let mut stats = Vec::new();
+
+    for _ in 0 .. count {
+        stats.push(ColumnStats::read_from(&mut reader)?);
+    }
+
+    if !reader.is_empty() {
+        return Err(corrupt("trailing bytes after the statistics"))
+    }
+
+    Ok(stats)
+}
+
+fn write_stat_value(out: &mut Vec<u8>, value: Option<&StatValue>) {
+    let value = match value {
+        Some(value) => value,
+        None => return put_u8(out, 0),
+    };
+
+    put_u8(out, 1);
+
+    match *value {
+        StatValue::Text(ref v) => write_bytes(out, v.as_bytes()),
+        StatValue::Blob(ref v) | StatValue::FixedBinary(ref v) => write_bytes(out, v),
+        StatValue::Scalar(ref v) => match *v {
+            Value::UINT8(v) => put_u64(out, v as u64),
+            Value::UINT16(v) => put_u64(out, v as u64),
+            Value::UINT32(v) => put_u64(out, v as u64),
+            Value::UINT64(v) => put_u64(out, v),
+            Value::INT8(v) => put_u64(out, v as i64 as u64),
+            Value::INT16(v) => put_u64(out, v as i64 as u64),
+            Value::INT32(v) => put_u64(out, v as i64 as u64),
+            Value::INT64(v) => put_u64(out, v as u64),
+            Value::FLOAT32(v) => put_u32(out, v.to_bits()),
+            Value::FLOAT64(v) => put_u64(out, v.to_bits()),
+            Value::BOOLEAN(v) => put_u8(out, v as u8),
+            Value::DATE(v) => put_u32(out, v.0 as u32),
+            Value::TIMESTAMP(v) => {
+                put_u64(out, v.micros as u64);
+                put_u8(out, v.offset.is_some() as u8);
+                put_u16(out, v.offset.unwrap_or(0) as u16);
+            }
+            Value::INTERVAL(v) => {
+                put_u32(out, v.months as u32);
+                put_u32(out, v.days as u32);
+                put_u64(out, v.micros as u64);
+            }
+            Value::DECIMAL(v) => {
+                put_u64(out, v.value as u64);
+                put_u64(out, (v.value >> 64) as u64);
+                put_u8(out, v.precision);
+                put_u8(out, v.scale);
+            }
+            Value::UUID(v) => out.extend_from_slice(&v.0),
+            _ => unreachable!("StatValue::Scalar holds fixed width values"),
+        },
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_u32(out, bytes.len() as u32);
+    out.extend_from_slice(bytes);
+}
+
+fn read_stat_value(reader: &mut ByteReader, attr: &Attribute)
+    -> Result<Option<StatValue>, DBError>
+{
+    match reader.read_u8()? {
+        0 => return Ok(None),
+        1 => {},
+        flag => return Err(corrupt(format!("{} (bad min / max flag {})", attr.name, flag))),
+    }
+
+    let scalar = match attr.dtype {
+        Type::TEXT => {
+            let len = reader.read_u32()? as usize;
+            return String::from_utf8(reader.read_bytes(len)?.to_vec())
+                .map(|v| Some(StatValue::Text(v)))
+                .map_err(|_| corrupt(format!("{} (min / max is not UTF-8)", attr.name)))
+        }
+        Type::BLOB | Type::FIXED_BINARY => {
+            let len = reader.read_u32()? as usize;
+            let bytes = reader.read_bytes(len)?.to_vec();
+            return Ok(Some(if attr.dtype == Type::BLOB {
+                StatValue::Blob(bytes)
+            } else {
+                StatValue::FixedBinary(bytes)
+            }))
+        }
+        Type::UINT8 => Value::UINT8(reader.read_u64()? as u8),
+        Type::UINT16 => Value::UINT16(reader.read_u64()? as u16),
+        Type::UINT32 => Value::UINT32(reader.read_u64()? as u32),
+        Type::UINT64 => Value::UINT64(reader.read_u64()?),
+        Type::INT8 => Value::INT8(reader.read_u64()? as i8),
+        Type::INT16 => Value::INT16(reader.read_u64()? as i16),
+        Type::INT32 => Value::INT32(reader.read_u64()? as i32),
+        Type::INT64 => Value::INT64(reader.read_u64()? as i64),
+        Type::FLOAT32 => Value::FLOAT32(f32::from_bits(reader.read_u32()?)),
+        Type::FLOAT64 => Value::FLOAT64(f64::from_bits(reader.read_u64()?)),
+        Type::BOOLEAN => Value::BOOLEAN(reader.read_u8()? != 0),
+        Type::DATE => Value::DATE(DateValue(reader.read_u32()? as i32)),
+        Type::TIMESTAMP => {
+            let micros = reader.read_u64()? as i64;
+            let has_offset = reader.read_u8()? != 0;
+            let offset = reader.read_u16()? as i16;
+            Value::TIMESTAMP(TimestampValue {
+                micros: micros,
+                offset: if has_offset { Some(offset) } else { None },
+            })
+        }
+        Type::INTERVAL => Value::INTERVAL(IntervalValue {
+            months: reader.read_u32()? as i32,
+            days: reader.read_u32()? as i32,
+            micros: reader.read_u64()? as i64,
+        }),
+        Type::DECIMAL => {
+            let low = reader.read_u64()? as u128;
+            let high = reader.read_u64()? as u128;
+            Value::DECIMAL(DecimalValue {
+                value: (high << 64 | low) as i128,
+                precision: reader.read_u8()?,
+                scale: reader.read_u8()?,
+            })
+        }
+        Type::UUID => {
+            let mut uuid = UuidValue::default();
+            uuid.0.copy_from_slice(reader.read_bytes(16)?);
+            Value::UUID(uuid)
+        }
+        Type::LIST | Type::STRUCT =>
+            return Err(corrupt(format!("{} (min / max of a nested column)", attr.name))),
+    };
+
+    Ok(Some(StatValue::Scalar(scalar)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use schema::Schema;
+    use selection::Selection;
+    use block::window_alias;
+    use table::{Table, TableAppender};
+    use types::*;
+
+    #[test]
+    fn compute_stats() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", false, Type::INT64),
+            Attribute::new("name", true, Type::TEXT),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::TEXT)),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(3 as i64).set("ccc").set(vec!["x"])
+                .add_row().set(1 as i64).set(NULL_VALUE).set(vec!["x"])
+                .add_row().set(2 as i64).set("a").set(vec!["x"])
+                .add_row().set(1 as i64).set("a").set(vec!["x"])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let stats = view_stats(table.block_ref()).unwrap();
+
+        assert_eq!((stats[0].rows, stats[0].nulls), (4, 0));
+        assert_eq!(stats[0].min, Some(StatValue::Scalar(Value::INT64(1))));
+        assert_eq!(stats[0].max, Some(StatValue::Scalar(Value::INT64(3))));
+        assert_eq!(stats[0].distinct_count(), 3);
+        assert_eq!(stats[0].avg_varlen_size(), None);
+
+        assert_eq!((stats[1].rows, stats[1].nulls), (4, 1));
+        assert_eq!(stats[1].min, Some(StatValue::Text(String::from("a"))));
+        assert_eq!(stats[1].max, Some(StatValue::Text(String::from("ccc"))));
+        assert_eq!(stats[1].distinct_count(), 2);
+        assert_eq!(stats[1].avg_varlen_size(), Some(5.0 / 3.0));
+
+        // Nested columns only have counts
+        assert_eq!((stats[2].min.is_none(), stats[2].distinct_count()), (true, 1));
+
+        let view = window_alias(table.block_ref(), None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![0]).unwrap()))
+            .unwrap();
+        let selected = column_stats(&view, 0).unwrap();
+        assert_eq!(selected.rows, 1);
+        assert_eq!(selected.min, Some(StatValue::Scalar(Value::INT64(3))));
+    }
+
+    #[test]
+    fn merge_serialize() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", false, Type::INT64),
+            Attribute::new("name", true, Type::TEXT),
+        ]).unwrap();
+
+        let mut lhs = Table::new(&allocator::GLOBAL, &schema, None);
+        let mut rhs = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut lhs)
+                .add_row().set(5 as i64).set("m")
+                .add_row().set(6 as i64).set(NULL_VALUE)
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+
+            let status = TableAppender::new(&mut rhs)
+                .add_row().set(-2 as i64).set("z")
+                .add_row().set(6 as i64).set("b")
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut stats = view_stats(lhs.block_ref()).unwrap();
+        merge_stats(&mut stats, &view_stats(rhs.block_ref()).unwrap()).unwrap();
+
+        assert_eq!((stats[0].rows, stats[0].distinct_count()), (4, 3));
+        assert_eq!(stats[0].min, Some(StatValue::Scalar(Value::INT64(-2))));
+        assert_eq!(stats[1].max, Some(StatValue::Text(String::from("z"))));
+        assert!(stats[0].clone().merge(&stats[1]).is_err(), "INT64 and TEXT don't merge");
+
+        let mut data = Vec::new();
+        write_stats(&stats, &mut data).unwrap();
+
+        let read = read_stats(&mut &data[..]).unwrap();
+        assert_eq!(read.len(), 2);
+
+        for (read, stats) in read.iter().zip(&stats) {
+            assert_eq!(read.attribute.name, stats.attribute.name);
+            assert_eq!((read.rows, read.nulls, read.varlen_bytes),
+                       (stats.rows, stats.nulls, stats.varlen_bytes));
+            assert_eq!((&read.min, &read.max), (&stats.min, &stats.max));
+            assert_eq!(read.distinct, stats.distinct);
+        }
+
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        assert!(read_stats(&mut &data[..]).is_err(), "checksum mismatch");
+    }
+}
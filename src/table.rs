@@ -86,6 +86,22 @@ impl<'alloc> Table<'alloc> {
             .column_mut(pos)
     }
 
+    /// VARLEN arena usage, see `Block::arena_stats`
+    pub fn arena_stats(&self) -> ArenaStats {
+        self.block
+            .as_ref()
+            .unwrap()
+            .arena_stats()
+    }
+
+    /// Free the VARLEN values that have been overwritten, see `Block::compact_arenas`
+    pub fn compact_arenas(&mut self) -> Result<(), DBError> {
+        self.block
+            .as_mut()
+            .unwrap()
+            .compact_arenas()
+    }
+
     /// Set nul value for (col, row) in the currently allocated table space.
     pub fn set_null(&mut self, col: usize, row: RowOffset, value: bool) -> Result<(), DBError> {
         if row >= self.rows() {
@@ -95,7 +111,7 @@ impl<'alloc> Table<'alloc> {
         self.column_mut(col)
             .ok_or(DBError::make_column_unknown_pos(col))
             .and_then(|c| c.nulls_mut())
-            .and_then(|nulls| { nulls[row] = value as u8; Ok(()) })
+            .and_then(|mut nulls| { nulls.set(row, value); Ok(()) })
     }
 
     /// Set value for (col, row) in the currently allocated table space.
@@ -195,6 +211,7 @@ mod tests {
     use super::*;
     use allocator;
     use error::DBError;
+    use row::RowRange;
     use schema::*;
     use types::*;
 
@@ -226,7 +243,7 @@ mod tests {
         let column = table.block_ref().column(0).unwrap();
         let rows = column_row_data::<UInt32>(column).unwrap();
 
-        assert!(rows.nulls[0] == 1 && rows.nulls[1] == 0, "Null vector incorrect");
+        assert!(rows.nulls.get(0) && !rows.nulls.get(1), "Null vector incorrect");
         assert_eq!(rows.values[1], 15);
     }
 
@@ -252,8 +269,8 @@ mod tests {
 
         let table = {
             let attrs = vec![
-                Attribute{name: "one".to_string(), nullable: false, dtype: Type::BLOB},
-                Attribute{name: "two".to_string(), nullable: false, dtype: Type::TEXT},
+                Attribute::new("one", false, Type::BLOB),
+                Attribute::new("two", false, Type::TEXT),
             ];
 
             let schema = Schema::from_vec(attrs).unwrap();
@@ -290,4 +307,374 @@ mod tests {
             assert_eq!(rows.values[1].to_string(), String::from("two"));
         }
     }
+
+    #[test]
+    fn narrow_columns() {
+        let attrs = vec![
+            Attribute::new("small", false, Type::UINT8),
+            Attribute::new("medium", false, Type::INT16),
+        ];
+
+        let schema = Schema::from_vec(attrs).unwrap();
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(200 as u8).set(-300 as i16)
+                .add_row().set(7 as u8).set(12 as i16)
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let block = table.block_ref();
+        assert_eq!(block[0].capacity(), block.capacity());
+        assert_eq!(block[1].capacity(), block.capacity());
+
+        // Aliasing must use the narrow element size
+        let window = window_alias(block, Some(RowRange { offset: 1, rows: 1 })).unwrap();
+
+        let small = column_row_data::<UInt8>(window.column(0).unwrap()).unwrap();
+        assert_eq!(small.values.len(), 1);
+        assert_eq!(small.values[0], 7);
+
+        let medium = column_row_data::<Int16>(window.column(1).unwrap()).unwrap();
+        assert_eq!(medium.values.len(), 1);
+        assert_eq!(medium.values[0], 12);
+    }
+
+    #[test]
+    fn list_columns() {
+        let element = Attribute::new("element", false, Type::INT32);
+        let schema = Schema::from_attr(Attribute::list("ids", false, element));
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(vec![1 as i32, 2])
+                .add_row().set(vec![3 as i32])
+                .add_row().set(vec![4 as i32, 5, 6])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        // Slicing the list column slices the element column
+        let block = table.block_ref();
+        let window = window_alias(block, Some(RowRange { offset: 1, rows: 2 })).unwrap();
+        let lists = list_row_data(window.column(0).unwrap()).unwrap();
+
+        assert_eq!(lists.elements.capacity(), 4);
+        assert_eq!(lists.range(0), 0..1);
+        assert_eq!(lists.range(1), 1..4);
+
+        let elements = column_row_data::<Int32>(lists.elements).unwrap();
+        assert_eq!(elements.values, &[3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn struct_columns() {
+        use projector::project_by_name;
+
+        let address = Attribute::structure("address", true, vec![
+            Attribute::new("city", false, Type::TEXT),
+            Attribute::new("zip", false, Type::UINT32),
+        ]).unwrap();
+
+        let schema = Schema::from_vec(vec![Attribute::new("id", false, Type::UINT32), address])
+            .unwrap();
+
+        assert_eq!(schema.resolve_path("address.zip").unwrap(), vec![1, 1]);
+        assert!(schema.resolve_path("id.zip").is_err());
+        assert!(schema.resolve_path("address.street").is_err());
+
+        let mut block = Block::new(&allocator::GLOBAL, &schema);
+        block.add_rows(2).unwrap();
+
+        {
+            let address = block.column_mut(1).unwrap();
+            address.child_mut(0).unwrap().set_varlen(0, b"Boston").unwrap();
+            address.child_mut(0).unwrap().set_varlen(1, b"Krakow").unwrap();
+            (2134 as u32).set_row(address.child_mut(1).unwrap(), 0).unwrap();
+            (30001 as u32).set_row(address.child_mut(1).unwrap(), 1).unwrap();
+        }
+
+        let proj = project_by_name("address.city").bind(&schema).unwrap();
+        assert_eq!(proj.schema.get(0).unwrap().name, "city");
+
+        let view = proj.project_view(&block).unwrap();
+        let cities = column_row_data::<Text>(view.column(0).unwrap()).unwrap();
+        assert_eq!(cities.values[1].as_ref() as &str, "Krakow");
+    }
+
+    #[test]
+    fn struct_null_rows() {
+        use projector::project_by_name;
+
+        let address = Attribute::structure("address", true, vec![
+            Attribute::new("city", false, Type::TEXT),
+        ]).unwrap();
+
+        let schema = Schema::from_vec(vec![Attribute::new("id", false, Type::UINT32), address])
+            .unwrap();
+
+        let mut block = Block::new(&allocator::GLOBAL, &schema);
+        block.add_rows(2).unwrap();
+
+        {
+            // The first address is NULL, its city is never set
+            let address = block.column_mut(1).unwrap();
+            address.nulls_mut().unwrap().set(0, true);
+            address.child_mut(0).unwrap().set_varlen(1, b"Krakow").unwrap();
+        }
+
+        let proj = project_by_name("address.city").bind(&schema).unwrap();
+        assert!(proj.schema.get(0).unwrap().nullable);
+
+        {
+            let view = proj.project_view(&block).unwrap();
+            assert!(view.column(0).unwrap().attribute().nullable);
+
+            match (view.value(0, 0).unwrap(), view.value(0, 1).unwrap()) {
+                (Value::NULL, Value::TEXT("Krakow")) => {},
+                _ => panic!("unexpected cities"),
+            }
+        }
+
+        let shared = proj.project_shared(&block.into_shared()).unwrap();
+
+        match (shared.value(0, 0).unwrap(), shared.value(0, 1).unwrap()) {
+            (Value::NULL, Value::TEXT("Krakow")) => {},
+            _ => panic!("unexpected shared cities"),
+        }
+
+        // Windows of the shared projection keep the folded NULLs
+        let window = shared.window(Some(RowRange { offset: 0, rows: 1 })).unwrap();
+        match window.value(0, 0).unwrap() {
+            Value::NULL => {},
+            _ => panic!("unexpected window city"),
+        }
+    }
+
+    #[test]
+    fn fixed_binary_columns() {
+        let attrs = vec![
+            Attribute::new("id", false, Type::UUID),
+            Attribute::fixed_binary("sha1", true, 20).unwrap(),
+        ];
+
+        let schema = Schema::from_vec(attrs).unwrap();
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+        let hash = [7 as u8; 20];
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("123e4567-e89b-12d3-a456-426614174000").set(&hash[..])
+                .add_row().set("00000000-0000-0000-0000-000000000001").set(NULL_VALUE)
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(UuidValue::default()).set(&hash[..4])
+                .done();
+
+            assert!(status.is_some(), "Value of the wrong width accepted");
+        }
+
+        let block = table.block_ref();
+
+        let ids = column_row_data::<Uuid>(block.column(0).unwrap()).unwrap();
+        assert_eq!(ids.values[1].to_string(), "00000000-0000-0000-0000-000000000001");
+
+        let hashes = fixed_row_data(block.column(1).unwrap()).unwrap();
+        assert_eq!(hashes.get(0), &hash[..]);
+        assert!(hashes.nulls.get(1));
+
+        // Plain row accessors don't know the width
+        assert!(column_row_data::<FixedBinary>(block.column(1).unwrap()).is_err());
+    }
+
+    #[test]
+    fn sliced_null_bitmap() {
+        let schema = Schema::make_one_attr("v", true, Type::INT32);
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let mut appender = TableAppender::new(&mut table);
+            for row in 0 .. 100 {
+                appender = if row % 3 == 0 {
+                    appender.add_row().set(NULL_VALUE)
+                } else {
+                    appender.add_row().set(row as i32)
+                };
+            }
+
+            let status = appender.done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        // Row offset isn't word aligned, the alias shares the parent bitmap
+        let block = table.block_ref();
+        let window = window_alias(block, Some(RowRange { offset: 61, rows: 10 })).unwrap();
+        let rows = column_row_data::<Int32>(window.column(0).unwrap()).unwrap();
+
+        assert_eq!(rows.nulls.len(), 10);
+        assert_eq!(rows.nulls.iter_ones().collect::<Vec<_>>(), vec![2, 5, 8]);
+        assert_eq!(rows.values[0], 61);
+    }
+
+    #[test]
+    fn selection_compact() {
+        use selection::Selection;
+
+        let element = Attribute::new("element", false, Type::INT32);
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("name", false, Type::TEXT),
+            Attribute::list("tags", false, element),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let mut appender = TableAppender::new(&mut table);
+            for row in 0 .. 10 {
+                appender = if row == 4 {
+                    appender.add_row().set(NULL_VALUE)
+                } else {
+                    appender.add_row().set(row as i32)
+                };
+                appender = appender.set(format!("n{}", row)).set(vec![row as i32; row % 3]);
+            }
+
+            let status = appender.done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let block = table.block_ref();
+        let view = window_alias(block, None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![1, 4, 5, 8]).unwrap()))
+            .unwrap();
+
+        // Window keeps the selected rows that fall into it
+        let window = window_alias(&view, Some(RowRange { offset: 3, rows: 5 })).unwrap();
+        assert_eq!(window.selection().unwrap().iter().collect::<Vec<_>>(), vec![1, 2]);
+
+        let dense = compact(&allocator::GLOBAL, &view).unwrap();
+        assert_eq!(dense.rows(), 4);
+        assert!(dense.selection().is_none());
+
+        let ids = column_row_data::<Int32>(dense.column(0).unwrap()).unwrap();
+        assert_eq!(ids.nulls.iter_ones().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(ids.values[2], 5);
+
+        let names = column_row_data::<Text>(dense.column(1).unwrap()).unwrap();
+        assert_eq!(names.values[3].as_ref() as &str, "n8");
+
+        let tags = list_row_data(dense.column(2).unwrap()).unwrap();
+        let elements = column_row_data::<Int32>(tags.elements).unwrap();
+        assert_eq!(&elements.values[tags.range(2)], &[5, 5]);
+        assert_eq!(&elements.values[tags.range(3)], &[8, 8]);
+
+        // LIST elements of a window of the alias
+        let dense = compact(&allocator::GLOBAL, &window).unwrap();
+        assert_eq!(dense.rows(), 2);
+
+        let tags = list_row_data(dense.column(2).unwrap()).unwrap();
+        let elements = column_row_data::<Int32>(tags.elements).unwrap();
+        assert_eq!(&elements.values[tags.range(0)], &[4]);
+        assert_eq!(&elements.values[tags.range(1)], &[5, 5]);
+    }
+
+    #[test]
+    fn append_concat() {
+        use selection::Selection;
+
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", true, Type::INT32),
+            Attribute::new("name", false, Type::TEXT),
+        ]).unwrap();
+
+        let mut first = Table::new(&allocator::GLOBAL, &schema, None);
+        let mut second = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut first)
+                .add_row().set(1 as i32).set("one")
+                .add_row().set(NULL_VALUE).set("two")
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+
+            let status = TableAppender::new(&mut second)
+                .add_row().set(3 as i32).set("three")
+                .done();
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut out = Block::new(&allocator::GLOBAL, &schema);
+        out.append_view(first.block_ref()).unwrap();
+
+        let selected = window_alias(first.block_ref(), None).unwrap()
+            .with_selection(Some(Selection::from_indices(vec![1]).unwrap()))
+            .unwrap();
+        let srcs: [&View; 2] = [second.block_ref(), &selected];
+        out.concat(&srcs).unwrap();
+        assert_eq!(out.rows(), 4);
+
+        {
+            let ids = column_row_data::<Int32>(out.column(0).unwrap()).unwrap();
+            assert_eq!(ids.nulls.iter_ones().collect::<Vec<_>>(), vec![1, 3]);
+            assert_eq!(ids.values[2], 3);
+
+            let names = column_row_data::<Text>(out.column(1).unwrap()).unwrap();
+            assert_eq!(names.values[2].as_ref() as &str, "three");
+            assert_eq!(names.values[3].as_ref() as &str, "two");
+        }
+
+        let other = Schema::make_one_attr("id", true, Type::INT64);
+        let mismatch = Block::new(&allocator::GLOBAL, &other);
+        assert!(out.append_view(&mismatch).is_err());
+        assert_eq!(out.rows(), 4);
+    }
+
+    #[test]
+    fn compact_arenas() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("name", true, Type::TEXT),
+            Attribute::list("tags", false, Attribute::new("tag", false, Type::TEXT)),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("first").set(vec!["a", "bc"])
+                .add_row().set_null(true).set(Vec::<&str>::new())
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        for _ in 0 .. 100 {
+            table.set(0, 0, "overwritten").unwrap();
+        }
+        table.set(0, 0, "last").unwrap();
+
+        let before = table.arena_stats();
+        assert_eq!(before.live, 7);
+        assert!(before.garbage() >= 1100);
+
+        table.compact_arenas().unwrap();
+        assert_eq!(table.arena_stats(), ArenaStats { live: 7, allocated: 7 });
+
+        let names = column_row_data::<Text>(table.column(0).unwrap()).unwrap();
+        assert_eq!(names.values[0].as_ref() as &str, "last");
+
+        let lists = list_row_data(table.column(1).unwrap()).unwrap();
+        let tags = column_row_data::<Text>(lists.elements).unwrap();
+        assert_eq!(tags.values[1].as_ref() as &str, "bc");
+    }
 }
@@ -1,10 +1,14 @@
 
+use std::cmp::Ordering;
 use std::convert::{AsRef, From};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::mem;
 use std::slice;
 use std::str;
 
 use super::error::DBError;
+use super::util::{datetime, decimal};
 
 /// "Native" type storing `Column` data for VARLEN columns
 #[derive(Clone, Copy)]
@@ -14,18 +18,95 @@ pub struct RawData {
     pub size: usize,
 }
 
+/// "Native" type storing `Column` data for LIST columns. Range of rows in the element (child)
+/// column.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[repr(C)]
+pub struct ListEntry {
+    pub offset: u32,
+    pub len: u32,
+}
+
+/// "Native" type storing `Column` data for INTERVAL columns.
+///
+/// Like SQL intervals the months, days and microseconds are kept separately since the length of
+/// a month (or a day, across DST changes) depends on the point in time it's applied to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct IntervalValue {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+/// Fixed-point decimal value. Unscaled value together with its precision & scale.
+///
+/// In a `Column` only the unscaled `i128` is stored, the precision & scale are part of the
+/// column's `Attribute` (`TypeModifiers`).
+#[derive(Clone, Copy, Debug)]
+pub struct DecimalValue {
+    pub value: i128,
+    pub precision: u8,
+    pub scale: u8,
+}
+
+/// "Native" type storing `Column` data for DATE columns. Days since the UNIX epoch.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct DateValue(pub i32);
+
+/// "Native" type storing `Column` data for TIMESTAMP columns. Microseconds since the UNIX epoch
+/// (UTC) and an optional time zone offset (minutes east of UTC) used for display.
+///
+/// Timestamps compare and hash by the point in time, the offset only affects display.
+#[derive(Clone, Copy, Debug)]
+pub struct TimestampValue {
+    pub micros: i64,
+    pub offset: Option<i16>,
+}
+
+impl PartialEq for TimestampValue {
+    fn eq(&self, other: &TimestampValue) -> bool {
+        self.micros == other.micros
+    }
+}
+
+impl Eq for TimestampValue {}
+
+impl Hash for TimestampValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.micros.hash(state)
+    }
+}
+
+/// "Native" type storing `Column` data for UUID columns. The 16 bytes in network order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[repr(C)]
+pub struct UuidValue(pub [u8; 16]);
+
 /// "Symbolic" Type of a `Column` `Attribute`
 #[derive(Clone, Copy, PartialEq)]
 pub enum Type {
+    UINT8,
+    UINT16,
     UINT32,
     UINT64,
+    INT8,
+    INT16,
     INT32,
     INT64,
     FLOAT32,
     FLOAT64,
     BOOLEAN,
+    DATE,
+    TIMESTAMP,
+    INTERVAL,
+    DECIMAL,
+    /// Fixed number of bytes per row stored inline, the width is an `Attribute` modifier
+    FIXED_BINARY,
+    UUID,
     TEXT,
     BLOB,
+    LIST,
+    STRUCT,
 }
 
 /// Trait providing higher level metadata about types
@@ -49,15 +130,37 @@ pub trait ValueInfo {
     }
 }
 
+pub struct UInt8;
+pub struct UInt16;
 pub struct UInt32;
 pub struct UInt64;
+pub struct Int8;
+pub struct Int16;
 pub struct Int32;
 pub struct Int64;
 pub struct Float32;
 pub struct Float64;
 pub struct Boolean;
+pub struct Date;
+pub struct Timestamp;
+pub struct Interval;
+pub struct Decimal;
+pub struct FixedBinary;
+pub struct Uuid;
 pub struct Text;
 pub struct Blob;
+pub struct List;
+pub struct Struct;
+
+impl ValueInfo for UInt8 {
+    type Store = u8;
+    const ENUM: Type = Type::UINT8;
+}
+
+impl ValueInfo for UInt16 {
+    type Store = u16;
+    const ENUM: Type = Type::UINT16;
+}
 
 impl ValueInfo for UInt32 {
     type Store = u32;
@@ -69,6 +172,16 @@ impl ValueInfo for UInt64 {
     const ENUM: Type = Type::UINT64;
 }
 
+impl ValueInfo for Int8 {
+    type Store = i8;
+    const ENUM: Type = Type::INT8;
+}
+
+impl ValueInfo for Int16 {
+    type Store = i16;
+    const ENUM: Type = Type::INT16;
+}
+
 impl ValueInfo for Int32 {
     type Store = i32;
     const ENUM: Type = Type::INT32;
@@ -94,6 +207,38 @@ impl ValueInfo for Boolean {
     const ENUM: Type = Type::BOOLEAN;
 }
 
+impl ValueInfo for Date {
+    type Store = DateValue;
+    const ENUM: Type = Type::DATE;
+}
+
+impl ValueInfo for Timestamp {
+    type Store = TimestampValue;
+    const ENUM: Type = Type::TIMESTAMP;
+}
+
+impl ValueInfo for Interval {
+    type Store = IntervalValue;
+    const ENUM: Type = Type::INTERVAL;
+}
+
+impl ValueInfo for Decimal {
+    type Store = i128;
+    const ENUM: Type = Type::DECIMAL;
+}
+
+/// Rows are `Attribute::size_of()` bytes wide, the row vector is addressed per byte.
+/// Use `block::fixed_row_data` to access the rows.
+impl ValueInfo for FixedBinary {
+    type Store = u8;
+    const ENUM: Type = Type::FIXED_BINARY;
+}
+
+impl ValueInfo for Uuid {
+    type Store = UuidValue;
+    const ENUM: Type = Type::UUID;
+}
+
 impl ValueInfo for Text {
     type Store = RawData;
     const ENUM: Type = Type::TEXT;
@@ -107,28 +252,64 @@ impl ValueInfo for Blob {
     const VARLEN: bool = true;
 }
 
+/// Elements are stored in the child column
+impl ValueInfo for List {
+    type Store = ListEntry;
+    const ENUM: Type = Type::LIST;
+}
+
+/// No row data, fields are stored in the child columns
+impl ValueInfo for Struct {
+    type Store = ();
+    const ENUM: Type = Type::STRUCT;
+}
+
+static UINT8: UInt8 = UInt8{};
+static UINT16: UInt16 = UInt16{};
 static UINT32: UInt32 = UInt32{};
 static UINT64: UInt64 = UInt64{};
+static INT8: Int8 = Int8{};
+static INT16: Int16 = Int16{};
 static INT32: Int32 = Int32{};
 static INT64: Int64 = Int64{};
 static FLOAT32: Float32 = Float32{};
 static FLOAT64: Float64 = Float64{};
 static BOOLEAN: Boolean = Boolean{};
+static DATE: Date = Date{};
+static TIMESTAMP: Timestamp = Timestamp{};
+static INTERVAL: Interval = Interval{};
+static DECIMAL: Decimal = Decimal{};
+static FIXED_BINARY: FixedBinary = FixedBinary{};
+static UUID: Uuid = Uuid{};
 static TEXT: Text = Text{};
 static BLOB: Blob = Blob{};
+static LIST: List = List{};
+static STRUCT: Struct = Struct{};
 
 impl Type {
     pub fn name(self) -> &'static str {
         match self {
+            Type::UINT8   => "UINT8",
+            Type::UINT16  => "UINT16",
             Type::UINT32  => "UINT32",
             Type::UINT64  => "UINT64",
+            Type::INT8    => "INT8",
+            Type::INT16   => "INT16",
             Type::INT32   => "INT32",
             Type::INT64   => "INT64",
             Type::FLOAT32 => "FLOAT32",
             Type::FLOAT64 => "FLOAT64",
             Type::BOOLEAN => "BOOLEAN",
+            Type::DATE    => "DATE",
+            Type::TIMESTAMP => "TIMESTAMP",
+            Type::INTERVAL => "INTERVAL",
+            Type::DECIMAL => "DECIMAL",
+            Type::FIXED_BINARY => "FIXED_BINARY",
+            Type::UUID    => "UUID",
             Type::TEXT    => "TEXT",
             Type::BLOB    => "BLOB",
+            Type::LIST    => "LIST",
+            Type::STRUCT  => "STRUCT",
         }
     }
 
@@ -136,34 +317,199 @@ impl Type {
     // There's no implementation specialization,
     // and can't use a associated trait type (defaulted or not) in an expression.
     // So we have to keep repeating ourselves
+    //
+    // FIXED_BINARY is parameterized, for the row size see `Attribute::size_of()`
     pub fn size_of(self) -> usize {
         match self {
+            Type::UINT8     => UINT8.size_of(),
+            Type::UINT16    => UINT16.size_of(),
             Type::UINT32    => UINT32.size_of(),
             Type::UINT64    => UINT64.size_of(),
+            Type::INT8      => INT8.size_of(),
+            Type::INT16     => INT16.size_of(),
             Type::INT32     => INT32.size_of(),
             Type::INT64     => INT64.size_of(),
             Type::FLOAT32   => FLOAT32.size_of(),
             Type::FLOAT64   => FLOAT64.size_of(),
             Type::BOOLEAN   => BOOLEAN.size_of(),
+            Type::DATE      => DATE.size_of(),
+            Type::TIMESTAMP => TIMESTAMP.size_of(),
+            Type::INTERVAL  => INTERVAL.size_of(),
+            Type::DECIMAL   => DECIMAL.size_of(),
+            Type::FIXED_BINARY => FIXED_BINARY.size_of(),
+            Type::UUID      => UUID.size_of(),
             Type::TEXT      => TEXT.size_of(),
             Type::BLOB      => BLOB.size_of(),
+            Type::LIST      => LIST.size_of(),
+            Type::STRUCT    => STRUCT.size_of(),
+        }
+    }
+}
+
+impl Type {
+    pub fn is_integer(self) -> bool {
+        match self {
+            Type::UINT8 | Type::UINT16 | Type::UINT32 | Type::UINT64 |
+            Type::INT8 | Type::INT16 | Type::INT32 | Type::INT64 => true,
+            _ => false,
         }
     }
+
+    pub fn is_float(self) -> bool {
+        self == Type::FLOAT32 || self == Type::FLOAT64
+    }
+
+    /// Integer, floating point and DECIMAL types
+    pub fn is_numeric(self) -> bool {
+        self.is_integer() || self.is_float() || self == Type::DECIMAL
+    }
+
+    /// Types that have nested attributes
+    pub fn is_nested(self) -> bool {
+        self == Type::LIST || self == Type::STRUCT
+    }
+}
+
+/// Strictness of a conversion between two types
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CastMode {
+    /// Widening conversions expression binders insert on their own (`INT32` => `INT64`)
+    Implicit,
+    /// Conversions the user asks for (`CAST(x AS INT8)`), may fail (overflow, parse errors) or
+    /// lose precision at evaluation time
+    Explicit,
+}
+
+/// Implicit widening rules, the edges of the coercion lattice.
+///
+/// - integers widen to larger integers of the same signedness, unsigned integers also to signed
+///   integers twice their size
+/// - integers widen to DECIMAL, to FLOAT64 and if they have at most 16 bits to FLOAT32
+/// - FLOAT32 and DECIMAL widen to FLOAT64
+/// - DATE widens to TIMESTAMP (midnight UTC)
+///
+/// INT64 / UINT64 / DECIMAL to FLOAT64 can lose precision, same as in most SQL databases.
+fn widens_to(from: Type, to: Type) -> bool {
+    if from == to {
+        return true
+    }
+
+    match from {
+        Type::UINT8 => match to {
+            Type::UINT16 | Type::UINT32 | Type::UINT64 |
+            Type::INT16 | Type::INT32 | Type::INT64 |
+            Type::FLOAT32 | Type::FLOAT64 | Type::DECIMAL => true,
+            _ => false,
+        },
+        Type::UINT16 => match to {
+            Type::UINT32 | Type::UINT64 | Type::INT32 | Type::INT64 |
+            Type::FLOAT32 | Type::FLOAT64 | Type::DECIMAL => true,
+            _ => false,
+        },
+        Type::UINT32 => match to {
+            Type::UINT64 | Type::INT64 | Type::FLOAT64 | Type::DECIMAL => true,
+            _ => false,
+        },
+        Type::INT8 => match to {
+            Type::INT16 | Type::INT32 | Type::INT64 |
+            Type::FLOAT32 | Type::FLOAT64 | Type::DECIMAL => true,
+            _ => false,
+        },
+        Type::INT16 => match to {
+            Type::INT32 | Type::INT64 | Type::FLOAT32 | Type::FLOAT64 | Type::DECIMAL => true,
+            _ => false,
+        },
+        Type::INT32 => match to {
+            Type::INT64 | Type::FLOAT64 | Type::DECIMAL => true,
+            _ => false,
+        },
+        Type::UINT64 | Type::INT64 =>
+            to == Type::FLOAT64 || to == Type::DECIMAL,
+        Type::FLOAT32 | Type::DECIMAL =>
+            to == Type::FLOAT64,
+        Type::DATE =>
+            to == Type::TIMESTAMP,
+        _ => false,
+    }
 }
 
+/// Smallest type both `lhs` and `rhs` implicitly convert to, if there's one.
+///
+/// `INT32, INT64` => `INT64`; `UINT8, INT8` => `INT16`; `UINT64, INT64` => `DECIMAL`;
+/// `INT32, FLOAT32` => `FLOAT64`. DECIMAL precision & scale are attribute modifiers,
+/// see `schema::common_supertype_attr`.
+pub fn common_supertype(lhs: Type, rhs: Type) -> Option<Type> {
+    if widens_to(lhs, rhs) {
+        return Some(rhs)
+    }
+
+    if widens_to(rhs, lhs) {
+        return Some(lhs)
+    }
+
+    // Mixed signedness / integer & float
+    let candidates = [Type::INT16, Type::INT32, Type::INT64, Type::DECIMAL, Type::FLOAT64];
+    candidates.iter()
+        .find(|t| widens_to(lhs, **t) && widens_to(rhs, **t))
+        .cloned()
+}
+
+/// Can values of type `from` be converted to type `to`
+///
+/// Explicit casts allow any conversion between numeric types, TIMESTAMP to DATE, anything
+/// (but nested types) to and from TEXT, and between the binary types.
+pub fn can_cast(from: Type, to: Type, mode: CastMode) -> bool {
+    if widens_to(from, to) {
+        return true
+    }
+
+    if mode == CastMode::Implicit || from.is_nested() || to.is_nested() {
+        return false
+    }
+
+    let binary = |t: Type| match t {
+        Type::BLOB | Type::FIXED_BINARY | Type::UUID => true,
+        _ => false,
+    };
+
+    (from.is_numeric() && to.is_numeric())
+        || (from == Type::TIMESTAMP && to == Type::DATE)
+        || from == Type::TEXT
+        || to == Type::TEXT
+        || (binary(from) && binary(to))
+}
+
+/// Parameterized names (`DECIMAL(10,2)`, `FIXED_BINARY(16)`) are accepted and checked, use
+/// `schema::parse_type` to keep the type modifiers.
 impl str::FromStr for Type {
     type Err = DBError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('(') {
+            return ::schema::parse_type(s).map(|(dtype, _)| dtype)
+        }
+
         match s {
+            "UINT8"   => Ok(Type::UINT8),
+            "UINT16"  => Ok(Type::UINT16),
             "UINT32"  => Ok(Type::UINT32),
             "UINT64"  => Ok(Type::UINT64),
+            "INT8"    => Ok(Type::INT8),
+            "INT16"   => Ok(Type::INT16),
             "INT32"   => Ok(Type::INT32),
             "INT64"   => Ok(Type::INT64),
             "FLOAT32" => Ok(Type::FLOAT32),
             "FLOAT64" => Ok(Type::FLOAT64),
             "BOOLEAN" => Ok(Type::BOOLEAN),
+            "DATE"    => Ok(Type::DATE),
+            "TIMESTAMP" => Ok(Type::TIMESTAMP),
+            "INTERVAL" => Ok(Type::INTERVAL),
+            "DECIMAL" => Ok(Type::DECIMAL),
+            "FIXED_BINARY" => Ok(Type::FIXED_BINARY),
+            "UUID"    => Ok(Type::UUID),
             "TEXT"    => Ok(Type::TEXT),
             "BLOB"    => Ok(Type::BLOB),
+            "LIST"    => Ok(Type::LIST),
+            "STRUCT"  => Ok(Type::STRUCT),
             _         => Err(DBError::UnknownType(String::from(s)))
         }
     }
@@ -191,22 +537,320 @@ impl ToString for RawData {
     }
 }
 
+impl DecimalValue {
+    pub fn new(value: i128, precision: u8, scale: u8) -> Result<DecimalValue, DBError> {
+        let valid = precision <= decimal::MAX_PRECISION
+            && scale <= precision
+            && decimal::fits(value, precision);
+
+        if !valid {
+            return Err(DBError::NumericOverflow(format!("DECIMAL({}, {})", precision, scale)))
+        }
+
+        Ok(DecimalValue { value: value, precision: precision, scale: scale })
+    }
+
+    /// Convert to a different precision & scale (rounding half away from zero)
+    pub fn rescale(&self, precision: u8, scale: u8) -> Result<DecimalValue, DBError> {
+        decimal::rescale_to(self.value, self.scale, precision, scale)
+            .map(|v| DecimalValue { value: v, precision: precision, scale: scale })
+    }
+
+    pub fn add(&self, rhs: &DecimalValue) -> Result<DecimalValue, DBError> {
+        let (p, s) = decimal::add_result(self.precision, self.scale, rhs.precision, rhs.scale);
+        let lhs = decimal::rescale(self.value, self.scale, s)?;
+        let rhs = decimal::rescale(rhs.value, rhs.scale, s)?;
+
+        lhs.checked_add(rhs)
+            .ok_or(DBError::NumericOverflow(String::from("DECIMAL add")))
+            .and_then(|v| DecimalValue::new(v, p, s))
+    }
+
+    pub fn sub(&self, rhs: &DecimalValue) -> Result<DecimalValue, DBError> {
+        let neg = DecimalValue { value: -rhs.value, .. *rhs };
+        self.add(&neg)
+    }
+
+    pub fn mul(&self, rhs: &DecimalValue) -> Result<DecimalValue, DBError> {
+        let (p, s) = decimal::mul_result(self.precision, self.scale, rhs.precision, rhs.scale);
+
+        let v = self.value.checked_mul(rhs.value)
+            .ok_or(DBError::NumericOverflow(String::from("DECIMAL multiply")))?;
+
+        // Product scale is s1 + s2, capping the precision may have reduced it
+        let v = decimal::rescale(v, self.scale + rhs.scale, s)?;
+        DecimalValue::new(v, p, s)
+    }
+
+    pub fn div(&self, rhs: &DecimalValue) -> Result<DecimalValue, DBError> {
+        if rhs.value == 0 {
+            return Err(DBError::NumericOverflow(String::from("DECIMAL division by zero")))
+        }
+
+        let (p, s) = decimal::div_result(self.precision, self.scale, rhs.precision, rhs.scale);
+
+        // Scale the dividend so the quotient ends up with scale s (plus one digit for rounding)
+        let shift = s + rhs.scale + 1;
+        let lhs = decimal::rescale(self.value, self.scale, shift)?;
+        let quot = decimal::rescale(lhs / rhs.value, 1 + s, s)?;
+
+        DecimalValue::new(quot, p, s)
+    }
+}
+
+/// Decimal literal, with exactly `scale` fractional digits
+impl fmt::Display for DecimalValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&decimal::format(self.value, self.scale))
+    }
+}
+
+/// Parse decimal literal, precision & scale are inferred from the number of digits
+impl str::FromStr for DecimalValue {
+    type Err = DBError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decimal::parse(s)
+            .map(|(v, p, s)| DecimalValue { value: v, precision: p, scale: s })
+    }
+}
+
+impl DateValue {
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> DateValue {
+        DateValue(datetime::days_from_civil(year, month, day) as i32)
+    }
+
+    /// Midnight UTC of the date
+    pub fn to_timestamp(&self) -> TimestampValue {
+        TimestampValue::utc(self.0 as i64 * datetime::MICROS_PER_DAY)
+    }
+}
+
+/// ISO-8601 (`YYYY-MM-DD`)
+impl fmt::Display for DateValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&datetime::format_date(self.0 as i64))
+    }
+}
+
+impl str::FromStr for DateValue {
+    type Err = DBError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        datetime::parse_date(s).map(|days| DateValue(days as i32))
+    }
+}
+
+impl TimestampValue {
+    /// UTC timestamp (no offset)
+    pub fn utc(micros: i64) -> TimestampValue {
+        TimestampValue { micros: micros, offset: None }
+    }
+
+    /// Add interval. Calendar (month & day) arithmetic is done in the local time of the offset.
+    pub fn add_interval(&self, iv: &IntervalValue) -> Result<TimestampValue, DBError> {
+        let shift = self.offset.unwrap_or(0) as i64 * datetime::MICROS_PER_MINUTE;
+        let overflow = || DBError::NumericOverflow(String::from("TIMESTAMP + INTERVAL"));
+
+        let local = self.micros.checked_add(shift).ok_or_else(&overflow)?;
+        let local = datetime::add_interval(local, iv.months, iv.days, iv.micros)?;
+        let micros = local.checked_sub(shift).ok_or_else(&overflow)?;
+        Ok(TimestampValue { micros: micros, offset: self.offset })
+    }
+
+    /// Date in the local time of the offset
+    pub fn to_date(&self) -> Result<DateValue, DBError> {
+        let overflow = || DBError::NumericOverflow(String::from("TIMESTAMP to DATE"));
+        let shift = self.offset.unwrap_or(0) as i64 * datetime::MICROS_PER_MINUTE;
+
+        let local = self.micros.checked_add(shift).ok_or_else(&overflow)?;
+        let days = local / datetime::MICROS_PER_DAY;
+        let rem = local % datetime::MICROS_PER_DAY;
+        let days = if rem < 0 { days - 1 } else { days };
+
+        if days < i32::min_value() as i64 || days > i32::max_value() as i64 {
+            return Err(overflow())
+        }
+
+        Ok(DateValue(days as i32))
+    }
+
+    pub fn sub_interval(&self, iv: &IntervalValue) -> Result<TimestampValue, DBError> {
+        self.add_interval(&iv.negate())
+    }
+
+    /// Difference between two points in time, expressed in days and microseconds
+    pub fn sub(&self, rhs: &TimestampValue) -> Result<IntervalValue, DBError> {
+        let diff = self.micros.checked_sub(rhs.micros)
+            .ok_or(DBError::NumericOverflow(String::from("TIMESTAMP - TIMESTAMP")))?;
+
+        Ok(IntervalValue {
+            months: 0,
+            days: (diff / datetime::MICROS_PER_DAY) as i32,
+            micros: diff % datetime::MICROS_PER_DAY,
+        })
+    }
+}
+
+impl IntervalValue {
+    pub fn new(months: i32, days: i32, micros: i64) -> IntervalValue {
+        IntervalValue { months: months, days: days, micros: micros }
+    }
+
+    pub fn negate(&self) -> IntervalValue {
+        IntervalValue { months: -self.months, days: -self.days, micros: -self.micros }
+    }
+
+    pub fn add(&self, rhs: &IntervalValue) -> Result<IntervalValue, DBError> {
+        let months = self.months.checked_add(rhs.months);
+        let days = self.days.checked_add(rhs.days);
+        let micros = self.micros.checked_add(rhs.micros);
+
+        match (months, days, micros) {
+            (Some(m), Some(d), Some(us)) => Ok(IntervalValue::new(m, d, us)),
+            _ => Err(DBError::NumericOverflow(String::from("INTERVAL + INTERVAL"))),
+        }
+    }
+}
+
+/// ISO-8601 duration (`P1Y2M3DT4H5M6.5S`)
+impl fmt::Display for IntervalValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&datetime::format_interval(self.months, self.days, self.micros))
+    }
+}
+
+/// Parses ISO-8601 durations and SQL style intervals (`1 day 3 hours`)
+impl str::FromStr for IntervalValue {
+    type Err = DBError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        datetime::parse_interval(s)
+            .map(|(months, days, micros)| IntervalValue::new(months, days, micros))
+    }
+}
+
+/// ISO-8601 (`YYYY-MM-DDTHH:MM:SS[.ffffff](Z|+HH:MM)`)
+impl fmt::Display for TimestampValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&datetime::format_timestamp(self.micros, self.offset))
+    }
+}
+
+impl str::FromStr for TimestampValue {
+    type Err = DBError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        datetime::parse_timestamp(s)
+            .map(|(micros, offset)| TimestampValue { micros: micros, offset: offset })
+    }
+}
+
+impl UuidValue {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<UuidValue, DBError> {
+        if bytes.len() != 16 {
+            return Err(DBError::ValueParse(format!("UUID from {} bytes", bytes.len())))
+        }
+
+        let mut out = UuidValue::default();
+        out.0.copy_from_slice(bytes);
+        Ok(out)
+    }
+}
+
+/// Canonical lower case form (`xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`)
+impl fmt::Display for UuidValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, b) in self.0.iter().enumerate() {
+            if idx == 4 || idx == 6 || idx == 8 || idx == 10 {
+                f.write_str("-")?;
+            }
+
+            write!(f, "{:02x}", b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the canonical form, hex digits in either case
+impl str::FromStr for UuidValue {
+    type Err = DBError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DBError::ValueParse(format!("UUID: {}", s));
+        let bytes = s.as_bytes();
+
+        if bytes.len() != 36 {
+            return Err(invalid())
+        }
+
+        let mut out = UuidValue::default();
+        let mut pos = 0;
+
+        for (idx, byte) in out.0.iter_mut().enumerate() {
+            if idx == 4 || idx == 6 || idx == 8 || idx == 10 {
+                if bytes[pos] != b'-' {
+                    return Err(invalid())
+                }
+                pos += 1;
+            }
+
+            let hi = (bytes[pos] as char).to_digit(16).ok_or_else(&invalid)?;
+            let lo = (bytes[pos + 1] as char).to_digit(16).ok_or_else(&invalid)?;
+            *byte = (hi << 4 | lo) as u8;
+            pos += 2;
+        }
+
+        Ok(out)
+    }
+}
+
 /// Value representing the null database column value
 pub struct NullType { }
 pub const NULL_VALUE: NullType = NullType {};
 
+/// Where NULLs sort relative to non-NULL values
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NullOrder {
+    First,
+    Last,
+}
+
 /// Container storing any kind of value
+///
+/// Values are totally ordered (`Ord`) so they can be sorted and used as map keys:
+/// - NULLs sort first (see `Value::compare` for NULLs last) and are equal to each other,
+///   use `sql_eq` for SQL semantics
+/// - floats compare numerically (`-0.0 == 0.0`), NaN is equal to itself and greater than any
+///   other number
+/// - TEXT, BLOB and FIXED_BINARY compare byte-wise; LIST and STRUCT element-wise
+/// - TIMESTAMPs compare by instant (offset is ignored), DECIMALs numerically regardless of scale
+/// - values of different types are never equal, they're ordered by type
+#[derive(Clone, Debug)]
 pub enum Value<'a> {
     NULL,
+    UINT8(u8),
+    UINT16(u16),
     UINT32(u32),
     UINT64(u64),
+    INT8(i8),
+    INT16(i16),
     INT32(i32),
     INT64(i64),
     FLOAT32(f32),
     FLOAT64(f64),
     BOOLEAN(bool),
+    DATE(DateValue),
+    TIMESTAMP(TimestampValue),
+    INTERVAL(IntervalValue),
+    DECIMAL(DecimalValue),
+    FIXED_BINARY(&'a [u8]),
+    UUID(UuidValue),
     TEXT(&'a str),
     BLOB(&'a [u8]),
+    LIST(Vec<Value<'a>>),
+    /// Field values in attribute order
+    STRUCT(Vec<Value<'a>>),
 }
 
 impl<'a> From<NullType> for Value<'a> {
@@ -215,6 +859,18 @@ impl<'a> From<NullType> for Value<'a> {
     }
 }
 
+impl<'a> From<u8> for Value<'a> {
+    fn from(v: u8) -> Self {
+        Value::UINT8(v)
+    }
+}
+
+impl<'a> From<u16> for Value<'a> {
+    fn from(v: u16) -> Self {
+        Value::UINT16(v)
+    }
+}
+
 impl<'a> From<u32> for Value<'a> {
     fn from(v: u32) -> Self {
         Value::UINT32(v)
@@ -227,6 +883,18 @@ impl<'a> From<u64> for Value<'a> {
     }
 }
 
+impl<'a> From<i8> for Value<'a> {
+    fn from(v: i8) -> Self {
+        Value::INT8(v)
+    }
+}
+
+impl<'a> From<i16> for Value<'a> {
+    fn from(v: i16) -> Self {
+        Value::INT16(v)
+    }
+}
+
 impl<'a> From<i32> for Value<'a> {
     fn from(v: i32) -> Self {
         Value::INT32(v)
@@ -251,6 +919,36 @@ impl<'a> From<f64> for Value<'a> {
     }
 }
 
+impl<'a> From<DateValue> for Value<'a> {
+    fn from(v: DateValue) -> Self {
+        Value::DATE(v)
+    }
+}
+
+impl<'a> From<TimestampValue> for Value<'a> {
+    fn from(v: TimestampValue) -> Self {
+        Value::TIMESTAMP(v)
+    }
+}
+
+impl<'a> From<IntervalValue> for Value<'a> {
+    fn from(v: IntervalValue) -> Self {
+        Value::INTERVAL(v)
+    }
+}
+
+impl<'a> From<DecimalValue> for Value<'a> {
+    fn from(v: DecimalValue) -> Self {
+        Value::DECIMAL(v)
+    }
+}
+
+impl<'a> From<UuidValue> for Value<'a> {
+    fn from(v: UuidValue) -> Self {
+        Value::UUID(v)
+    }
+}
+
 impl<'a> From<&'a str> for Value<'a> {
     fn from(v: &'a str) -> Self {
         Value::TEXT(v)
@@ -262,3 +960,399 @@ impl<'a> From<&'a [u8]> for Value<'a> {
         Value::BLOB(v)
     }
 }
+
+impl<'a> From<Vec<Value<'a>>> for Value<'a> {
+    fn from(v: Vec<Value<'a>>) -> Self {
+        Value::LIST(v)
+    }
+}
+
+/// Total order of floats: NaN greater than anything else, `-0.0 == 0.0`
+fn cmp_float(lhs: f64, rhs: f64) -> Ordering {
+    match (lhs.is_nan(), rhs.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => lhs.partial_cmp(&rhs).unwrap(),
+    }
+}
+
+/// Hash floats consistently with `cmp_float`
+fn hash_float<H: Hasher>(value: f64, state: &mut H) {
+    let canonical = if value.is_nan() {
+        ::std::f64::NAN
+    } else if value == 0.0 {
+        0.0
+    } else {
+        value
+    };
+
+    canonical.to_bits().hash(state)
+}
+
+fn cmp_values(lhs: &[Value], rhs: &[Value], nulls: NullOrder) -> Ordering {
+    for (l, r) in lhs.iter().zip(rhs.iter()) {
+        let ord = l.compare(r, nulls);
+        if ord != Ordering::Equal {
+            return ord
+        }
+    }
+
+    lhs.len().cmp(&rhs.len())
+}
+
+impl<'a> Value<'a> {
+    pub fn is_null(&self) -> bool {
+        match *self {
+            Value::NULL => true,
+            _ => false,
+        }
+    }
+
+    /// Position in the ordering of values of different types
+    fn rank(&self) -> u8 {
+        match *self {
+            Value::NULL => 0,
+            Value::UINT8(_) => 1,
+            Value::UINT16(_) => 2,
+            Value::UINT32(_) => 3,
+            Value::UINT64(_) => 4,
+            Value::INT8(_) => 5,
+            Value::INT16(_) => 6,
+            Value::INT32(_) => 7,
+            Value::INT64(_) => 8,
+            Value::FLOAT32(_) => 9,
+            Value::FLOAT64(_) => 10,
+            Value::BOOLEAN(_) => 11,
+            Value::DATE(_) => 12,
+            Value::TIMESTAMP(_) => 13,
+            Value::INTERVAL(_) => 14,
+            Value::DECIMAL(_) => 15,
+            Value::FIXED_BINARY(_) => 16,
+            Value::UUID(_) => 17,
+            Value::TEXT(_) => 18,
+            Value::BLOB(_) => 19,
+            Value::LIST(_) => 20,
+            Value::STRUCT(_) => 21,
+        }
+    }
+
+    /// Compare two values, with NULLs sorting according to `nulls`.
+    ///
+    /// Nested values (LIST, STRUCT) use the same NULL ordering.
+    pub fn compare(&self, other: &Value, nulls: NullOrder) -> Ordering {
+        match (self, other) {
+            (&Value::NULL, &Value::NULL) => Ordering::Equal,
+            (&Value::NULL, _) =>
+                if nulls == NullOrder::First { Ordering::Less } else { Ordering::Greater },
+            (_, &Value::NULL) =>
+                if nulls == NullOrder::First { Ordering::Greater } else { Ordering::Less },
+            (&Value::UINT8(l), &Value::UINT8(r)) => l.cmp(&r),
+            (&Value::UINT16(l), &Value::UINT16(r)) => l.cmp(&r),
+            (&Value::UINT32(l), &Value::UINT32(r)) => l.cmp(&r),
+            (&Value::UINT64(l), &Value::UINT64(r)) => l.cmp(&r),
+            (&Value::INT8(l), &Value::INT8(r)) => l.cmp(&r),
+            (&Value::INT16(l), &Value::INT16(r)) => l.cmp(&r),
+            (&Value::INT32(l), &Value::INT32(r)) => l.cmp(&r),
+            (&Value::INT64(l), &Value::INT64(r)) => l.cmp(&r),
+            (&Value::FLOAT32(l), &Value::FLOAT32(r)) => cmp_float(l as f64, r as f64),
+            (&Value::FLOAT64(l), &Value::FLOAT64(r)) => cmp_float(l, r),
+            (&Value::BOOLEAN(l), &Value::BOOLEAN(r)) => l.cmp(&r),
+            (&Value::DATE(l), &Value::DATE(r)) => l.cmp(&r),
+            (&Value::TIMESTAMP(l), &Value::TIMESTAMP(r)) => l.micros.cmp(&r.micros),
+            (&Value::INTERVAL(l), &Value::INTERVAL(r)) =>
+                (l.months, l.days, l.micros).cmp(&(r.months, r.days, r.micros)),
+            (&Value::DECIMAL(l), &Value::DECIMAL(r)) =>
+                decimal::compare(l.value, l.scale, r.value, r.scale),
+            (&Value::FIXED_BINARY(l), &Value::FIXED_BINARY(r)) => l.cmp(r),
+            (&Value::UUID(l), &Value::UUID(r)) => l.cmp(&r),
+            (&Value::TEXT(l), &Value::TEXT(r)) => l.as_bytes().cmp(r.as_bytes()),
+            (&Value::BLOB(l), &Value::BLOB(r)) => l.cmp(r),
+            (&Value::LIST(ref l), &Value::LIST(ref r)) => cmp_values(l, r, nulls),
+            (&Value::STRUCT(ref l), &Value::STRUCT(ref r)) => cmp_values(l, r, nulls),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    /// SQL equality: NULL if either side is NULL, otherwise a BOOLEAN
+    pub fn sql_eq(&self, other: &Value) -> Value<'static> {
+        if self.is_null() || other.is_null() {
+            Value::NULL
+        } else {
+            Value::BOOLEAN(self == other)
+        }
+    }
+}
+
+impl<'a, 'b> PartialEq<Value<'b>> for Value<'a> {
+    fn eq(&self, other: &Value<'b>) -> bool {
+        self.compare(other, NullOrder::First) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for Value<'a> {}
+
+impl<'a, 'b> PartialOrd<Value<'b>> for Value<'a> {
+    fn partial_cmp(&self, other: &Value<'b>) -> Option<Ordering> {
+        Some(self.compare(other, NullOrder::First))
+    }
+}
+
+/// NULLs first
+impl<'a> Ord for Value<'a> {
+    fn cmp(&self, other: &Value<'a>) -> Ordering {
+        self.compare(other, NullOrder::First)
+    }
+}
+
+/// Agrees with `Eq`: equal values (`-0.0` / `0.0`, `1.0` / `1.00` DECIMALs, the same instant in
+/// different time zones) hash the same.
+impl<'a> Hash for Value<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+
+        match *self {
+            Value::NULL => {},
+            Value::UINT8(v) => v.hash(state),
+            Value::UINT16(v) => v.hash(state),
+            Value::UINT32(v) => v.hash(state),
+            Value::UINT64(v) => v.hash(state),
+            Value::INT8(v) => v.hash(state),
+            Value::INT16(v) => v.hash(state),
+            Value::INT32(v) => v.hash(state),
+            Value::INT64(v) => v.hash(state),
+            Value::FLOAT32(v) => hash_float(v as f64, state),
+            Value::FLOAT64(v) => hash_float(v, state),
+            Value::BOOLEAN(v) => v.hash(state),
+            Value::DATE(v) => v.hash(state),
+            Value::TIMESTAMP(v) => v.micros.hash(state),
+            Value::INTERVAL(v) => v.hash(state),
+            Value::DECIMAL(v) => decimal::normalize(v.value, v.scale).hash(state),
+            Value::FIXED_BINARY(v) => v.hash(state),
+            Value::UUID(v) => v.hash(state),
+            Value::TEXT(v) => v.as_bytes().hash(state),
+            Value::BLOB(v) => v.hash(state),
+            Value::LIST(ref v) => v.hash(state),
+            Value::STRUCT(ref v) => v.hash(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_iso8601() {
+        assert_eq!(DateValue(0).to_string(), "1970-01-01");
+        assert_eq!(DateValue(-1).to_string(), "1969-12-31");
+        assert_eq!(DateValue::from_ymd(2000, 2, 29).to_string(), "2000-02-29");
+
+        let parsed: DateValue = "2017-08-14".parse().unwrap();
+        assert_eq!(parsed, DateValue::from_ymd(2017, 8, 14));
+
+        assert!("2017-02-29".parse::<DateValue>().is_err(), "Not a leap year");
+        assert!("2017-8-14".parse::<DateValue>().is_err(), "Not zero padded");
+    }
+
+    #[test]
+    fn timestamp_iso8601() {
+        assert_eq!(TimestampValue::utc(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(TimestampValue::utc(-1).to_string(), "1969-12-31T23:59:59.999999Z");
+
+        let ts: TimestampValue = "2017-08-14T10:30:00.5+02:00".parse().unwrap();
+        assert_eq!(ts.offset, Some(120));
+        assert_eq!(ts.to_string(), "2017-08-14T10:30:00.500000+02:00");
+
+        let utc: TimestampValue = "2017-08-14 08:30:00.5".parse().unwrap();
+        assert_eq!(utc.micros, ts.micros);
+
+        let zulu: TimestampValue = "2017-08-14T08:30:00.5Z".parse().unwrap();
+        assert_eq!(zulu, utc);
+        assert_eq!(zulu.to_string(), "2017-08-14T08:30:00.500000Z");
+
+        // The same point in time, whatever the offset
+        assert_eq!(ts, zulu);
+        assert_eq!(ts.to_date().unwrap(), DateValue(17392));
+
+        let late = TimestampValue { micros: i64::max_value(), offset: Some(60) };
+        assert!(late.to_date().is_err());
+
+        assert!("2017-08-14T08:30:00+99:99".parse::<TimestampValue>().is_err());
+        assert!("2017-08\u{e9}-1".parse::<DateValue>().is_err(), "Not ASCII");
+    }
+
+    #[test]
+    fn interval_parse_print() {
+        let iv: IntervalValue = "P1Y2M3DT4H5M6.5S".parse().unwrap();
+        assert_eq!(iv, IntervalValue::new(14, 3, 4 * 3600_000_000 + 5 * 60_000_000 + 6_500_000));
+        assert_eq!(iv.to_string(), "P1Y2M3DT4H5M6.5S");
+
+        let sql: IntervalValue = "1 day 3 hours".parse().unwrap();
+        assert_eq!(sql, IntervalValue::new(0, 1, 3 * 3600_000_000));
+        assert_eq!(sql.negate().to_string(), "P-1DT-3H");
+
+        assert_eq!(IntervalValue::default().to_string(), "PT0S");
+        assert!("P1H".parse::<IntervalValue>().is_err(), "Hours belong in the time part");
+        assert!("3 fortnights".parse::<IntervalValue>().is_err());
+    }
+
+    #[test]
+    fn timestamp_interval_arithmetic() {
+        let ts: TimestampValue = "2017-01-31T12:00:00Z".parse().unwrap();
+        let month: IntervalValue = "1 month".parse().unwrap();
+
+        // Day of month is clamped to the end of February
+        let next = ts.add_interval(&month).unwrap();
+        assert_eq!(next.to_string(), "2017-02-28T12:00:00Z");
+        assert_eq!(next.sub_interval(&month).unwrap().to_string(), "2017-01-28T12:00:00Z");
+
+        let diff = next.sub(&ts).unwrap();
+        assert_eq!(diff, IntervalValue::new(0, 28, 0));
+
+        let last = TimestampValue { micros: i64::max_value(), offset: Some(60) };
+        assert!(last.add_interval(&IntervalValue::new(0, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn parse_type_names() {
+        use schema::{Attribute, TypeModifiers, parse_type};
+
+        assert!("DECIMAL(10,2)".parse::<Type>().unwrap() == Type::DECIMAL);
+        assert!(parse_type("DECIMAL(10, 2)").unwrap().1 == TypeModifiers::decimal(10, 2));
+        assert!(parse_type("FIXED_BINARY(16)").unwrap().1 == TypeModifiers::fixed(16));
+        assert!(parse_type("DECIMAL(2,3)").is_err(), "Scale larger than the precision");
+        assert!(parse_type("INT32(4)").is_err());
+        assert!(parse_type("DECIMAL(10,2").is_err());
+
+        let attr = Attribute::new("d", false, Type::DECIMAL);
+        assert_eq!(attr.type_name(), "DECIMAL(38,0)");
+        assert!(parse_type(&attr.type_name()).unwrap().1 == attr.modifiers);
+
+        assert!(Attribute::decimal("d", false, 10, 2).is_ok());
+        assert!(Attribute::decimal("d", false, 0, 0).is_err());
+        assert!(Attribute::decimal("d", false, 39, 0).is_err());
+        assert!(Attribute::decimal("d", false, 5, 6).is_err());
+
+        assert!(parse_type("FIXED_BINARY(0)").is_err(), "Zero width");
+        assert!(Attribute::fixed_binary("f", false, 16).is_ok());
+        assert!(Attribute::fixed_binary("f", false, 0).is_err());
+    }
+
+    #[test]
+    fn decimal_parse_print() {
+        let d: DecimalValue = "-123.4500".parse().unwrap();
+        assert_eq!((d.value, d.precision, d.scale), (-1234500, 7, 4));
+        assert_eq!(d.to_string(), "-123.4500");
+
+        let small: DecimalValue = "0.05".parse().unwrap();
+        assert_eq!((small.precision, small.scale), (2, 2));
+
+        assert!("1.2.3".parse::<DecimalValue>().is_err());
+        assert!("12a".parse::<DecimalValue>().is_err());
+    }
+
+    #[test]
+    fn decimal_arithmetic() {
+        let a: DecimalValue = "10.25".parse().unwrap();
+        let b: DecimalValue = "0.125".parse().unwrap();
+
+        let sum = a.add(&b).unwrap();
+        assert_eq!(sum.to_string(), "10.375");
+        assert_eq!((sum.precision, sum.scale), (6, 3));
+
+        assert_eq!(a.sub(&b).unwrap().to_string(), "10.125");
+        assert_eq!(a.mul(&b).unwrap().to_string(), "1.28125");
+
+        let third = DecimalValue::new(1, 1, 0).unwrap()
+            .div(&DecimalValue::new(3, 1, 0).unwrap())
+            .unwrap();
+        assert_eq!(third.to_string(), "0.333333");
+
+        // Rounding when reducing the scale
+        assert_eq!(a.rescale(3, 1).unwrap().to_string(), "10.3");
+        assert!(a.rescale(3, 2).is_err(), "10.25 doesn't fit DECIMAL(3, 2)");
+    }
+
+    #[test]
+    fn uuid_parse_print() {
+        let text = "123e4567-e89b-12d3-a456-426614174000";
+        let uuid: UuidValue = text.parse().unwrap();
+
+        assert_eq!(uuid.0[0], 0x12);
+        assert_eq!(uuid.0[15], 0x00);
+        assert_eq!(uuid.to_string(), text);
+        assert!("123E4567-E89B-12D3-A456-426614174000".parse::<UuidValue>().unwrap() == uuid);
+
+        assert!("123e4567e89b12d3a456426614174000".parse::<UuidValue>().is_err());
+        assert!("123e4567-e89b-12d3-a456-42661417400g".parse::<UuidValue>().is_err());
+    }
+
+    #[test]
+    fn value_ordering() {
+        let mut values = vec![
+            Value::FLOAT64(::std::f64::NAN),
+            Value::NULL,
+            Value::FLOAT64(1.5),
+            Value::FLOAT64(-0.0),
+        ];
+
+        values.sort();
+        assert!(values[0].is_null());
+        assert!(values[1] == Value::FLOAT64(0.0));
+        assert!(values[3] == Value::FLOAT64(::std::f64::NAN));
+
+        let null = Value::NULL;
+        assert_eq!(null.compare(&Value::INT32(1), NullOrder::Last), Ordering::Greater);
+        assert_eq!(null.compare(&Value::INT32(1), NullOrder::First), Ordering::Less);
+
+        // Byte-wise ordering, shorter prefix first
+        assert!(Value::TEXT("ab") < Value::TEXT("b"));
+        assert!(Value::BLOB(b"ab") < Value::BLOB(b"abc"));
+        assert!(Value::LIST(vec![Value::INT32(1)]) < Value::LIST(vec![Value::INT32(2)]));
+
+        // Numerically equal decimals
+        let a = DecimalValue::new(10, 2, 1).unwrap();
+        let b = DecimalValue::new(100, 3, 2).unwrap();
+        assert!(Value::DECIMAL(a) == Value::DECIMAL(b));
+    }
+
+    #[test]
+    fn value_sql_eq_hash() {
+        use std::collections::HashSet;
+
+        assert!(Value::NULL.sql_eq(&Value::NULL).is_null());
+        assert!(Value::INT32(1).sql_eq(&Value::NULL).is_null());
+        assert!(Value::INT32(1).sql_eq(&Value::INT32(1)) == Value::BOOLEAN(true));
+        assert!(Value::INT32(1).sql_eq(&Value::INT64(1)) == Value::BOOLEAN(false));
+
+        let mut set = HashSet::new();
+        set.insert(Value::FLOAT64(0.0));
+        set.insert(Value::FLOAT64(-0.0));
+        set.insert(Value::FLOAT64(::std::f64::NAN));
+        set.insert(Value::FLOAT64(-::std::f64::NAN));
+        set.insert(Value::DECIMAL(DecimalValue::new(10, 2, 1).unwrap()));
+        set.insert(Value::DECIMAL(DecimalValue::new(1, 1, 0).unwrap()));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn coercion_rules() {
+        assert!(common_supertype(Type::INT32, Type::INT64) == Some(Type::INT64));
+        assert!(common_supertype(Type::INT64, Type::INT32) == Some(Type::INT64));
+        assert!(common_supertype(Type::UINT8, Type::INT8) == Some(Type::INT16));
+        assert!(common_supertype(Type::UINT32, Type::INT32) == Some(Type::INT64));
+        assert!(common_supertype(Type::UINT64, Type::INT64) == Some(Type::DECIMAL));
+        assert!(common_supertype(Type::INT32, Type::FLOAT32) == Some(Type::FLOAT64));
+        assert!(common_supertype(Type::INT32, Type::FLOAT64) == Some(Type::FLOAT64));
+        assert!(common_supertype(Type::DATE, Type::TIMESTAMP) == Some(Type::TIMESTAMP));
+        assert!(common_supertype(Type::INT32, Type::TEXT).is_none());
+
+        assert!(can_cast(Type::INT16, Type::INT64, CastMode::Implicit));
+        assert!(!can_cast(Type::INT64, Type::INT16, CastMode::Implicit));
+        assert!(can_cast(Type::INT64, Type::INT16, CastMode::Explicit));
+        assert!(can_cast(Type::TEXT, Type::UUID, CastMode::Explicit));
+        assert!(!can_cast(Type::TEXT, Type::UUID, CastMode::Implicit));
+        assert!(!can_cast(Type::LIST, Type::TEXT, CastMode::Explicit));
+    }
+}
@@ -1,4 +1,4 @@
-use ::block::Column;
+use ::block::{Column, RefColumn};
 use ::error::DBError;
 use ::row::RowOffset;
 use ::types;
@@ -11,8 +11,23 @@ pub trait ValueSetter {
 
 impl ValueSetter for types::NullType {
     fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
-        let rows = col.nulls_mut()?;
-        rows[row] = true as u8;
+        col.nulls_mut()?.set(row, true);
+        Ok(())
+    }
+}
+
+impl ValueSetter for u8 {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::UInt8>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
+
+impl ValueSetter for u16 {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::UInt16>()?;
+        rows[row] = *self;
         Ok(())
     }
 }
@@ -33,6 +48,22 @@ impl ValueSetter for u64 {
     }
 }
 
+impl ValueSetter for i8 {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::Int8>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
+
+impl ValueSetter for i16 {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::Int16>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
+
 impl ValueSetter for i32 {
     fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
         let rows = col.rows_mut::<types::Int32>()?;
@@ -49,6 +80,22 @@ impl ValueSetter for i64 {
     }
 }
 
+impl ValueSetter for f32 {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::Float32>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
+
+impl ValueSetter for f64 {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::Float64>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
+
 impl ValueSetter for bool {
     fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
         let rows = col.rows_mut::<types::Boolean>()?;
@@ -57,44 +104,127 @@ impl ValueSetter for bool {
     }
 }
 
-impl<'b> ValueSetter for &'b str {
+impl ValueSetter for types::DateValue {
     fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
-        let data = self.as_bytes();
-        let ptr = {
-            let arena = col.arena();
-            arena.append(data)?.1
-        };
+        let rows = col.rows_mut::<types::Date>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
 
-        let rows = col.rows_mut::<types::Text>()?;
-        rows[row] = types::RawData{data: ptr, size: data.len()};
+impl ValueSetter for types::TimestampValue {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::Timestamp>()?;
+        rows[row] = *self;
         Ok(())
     }
 }
 
-impl ValueSetter for String {
+impl ValueSetter for types::IntervalValue {
     fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
-        let data = self.as_bytes();
-        let ptr = {
-            let arena = col.arena();
-            arena.append(data)?.1
+        let rows = col.rows_mut::<types::Interval>()?;
+        rows[row] = *self;
+        Ok(())
+    }
+}
+
+/// The value is rescaled to the precision & scale of the column attribute.
+impl ValueSetter for types::DecimalValue {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let value = {
+            let mods = &col.attribute().modifiers;
+            self.rescale(mods.precision, mods.scale)?.value
         };
 
-        let rows = col.rows_mut::<types::Text>()?;
-        rows[row] = types::RawData{data: ptr, size: data.len()};
+        let rows = col.rows_mut::<types::Decimal>()?;
+        rows[row] = value;
+        Ok(())
+    }
+}
+
+/// VARLEN setter shared by TEXT / BLOB values. Works with plain and dictionary encoded columns.
+fn set_varlen<'a>(col: &mut Column<'a>, row: RowOffset, dtype: types::Type, data: &[u8])
+    -> Result<(), DBError>
+{
+    if col.attribute().dtype != dtype {
+        return Err(DBError::AttributeType(col.attribute().name.clone()))
+    }
+
+    col.set_varlen(row, data)
+}
+
+impl ValueSetter for types::UuidValue {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        let rows = col.rows_mut::<types::Uuid>()?;
+        rows[row] = *self;
         Ok(())
     }
 }
 
+/// Strings are stored in TEXT columns, or parsed when set into UUID columns
+fn set_str<'a>(col: &mut Column<'a>, row: RowOffset, value: &str) -> Result<(), DBError> {
+    if col.attribute().dtype == types::Type::UUID {
+        value.parse::<types::UuidValue>()?.set_row(col, row)
+    } else {
+        set_varlen(col, row, types::Type::TEXT, value.as_bytes())
+    }
+}
+
+impl<'b> ValueSetter for &'b str {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        set_str(col, row, self)
+    }
+}
+
+impl ValueSetter for String {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        set_str(col, row, self.as_str())
+    }
+}
+
+/// Slices of bytes are BLOBs (or fixed width binary values), not LIST<UINT8>
 impl<'b> ValueSetter for &'b[u8] {
     fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
-        let ptr = {
-            let arena = col.arena();
-            arena.append(self)?.1
-        };
+        match col.attribute().dtype {
+            types::Type::FIXED_BINARY | types::Type::UUID => col.set_fixed(row, self),
+            _ => set_varlen(col, row, types::Type::BLOB, self),
+        }
+    }
+}
 
-        let rows = col.rows_mut::<types::Blob>()?;
-        rows[row] = types::RawData{data: ptr, size: self.len()};
-        Ok(())
+/// Append the items to the end of the LIST element column and point the row at them
+fn set_list<'a, T: ValueSetter>(col: &mut Column<'a>, row: RowOffset, items: &[T])
+    -> Result<(), DBError>
+{
+    let start = col.list_reserve(items.len())?;
+
+    {
+        let elements = col.child_mut(0).unwrap();
+        let nullable = elements.attribute().nullable;
+
+        for (idx, item) in items.iter().enumerate() {
+            if nullable {
+                elements.nulls_mut()?.set(start + idx, false);
+            }
+
+            item.set_row(elements, start + idx)?;
+        }
+    }
+
+    let rows = col.rows_mut::<types::List>()?;
+    rows[row] = types::ListEntry { offset: start as u32, len: items.len() as u32 };
+    Ok(())
+}
+
+impl<'b, T: ValueSetter> ValueSetter for &'b [T] {
+    default fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        set_list(col, row, self)
+    }
+}
+
+impl<T: ValueSetter> ValueSetter for Vec<T> {
+    fn set_row<'a>(&self, col: &mut Column<'a>, row: RowOffset) -> Result<(), DBError> {
+        set_list(col, row, self.as_slice())
     }
 }
 
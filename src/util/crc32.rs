@@ -0,0 +1,62 @@
+// vim : set ts=4 sw=4 et :
+
+//! CRC-32 checksums (IEEE 802.3 polynomial, the same as zlib and PNG), used to detect corrupt
+//! serialized data.
+
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Incremental CRC-32 of a byte stream
+pub struct Crc32 {
+    table: [u32; 256],
+    state: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        let mut table = [0; 256];
+
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0 .. 8 {
+                c = if c & 1 != 0 { POLYNOMIAL ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+
+        Crc32 { table: table, state: !0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.state = self.table[((self.state ^ *byte as u32) & 0xFF) as usize]
+                ^ (self.state >> 8);
+        }
+    }
+
+    /// Checksum of the data so far
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// CRC-32 of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
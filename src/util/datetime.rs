@@ -0,0 +1,421 @@
+// vim : set ts=4 sw=4 et :
+
+//! Calendar helpers for the temporal types. Dates are represented as days since the UNIX epoch,
+//! timestamps as microseconds since the UNIX epoch.
+//!
+//! The day <-> civil conversion is based on Howard Hinnant's `days_from_civil` algorithms, which
+//! are valid for the whole proleptic Gregorian calendar.
+
+use num::Integer;
+use std::cmp::min;
+
+use ::error::DBError;
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// Convert days since epoch into a (year, month, day) triple
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_floor(&146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400;
+
+    (if m <= 2 { y + 1 } else { y }, m as u32, d as u32)
+}
+
+/// Convert a (year, month, day) triple into days since epoch
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let m = month as i64;
+    let era = y.div_floor(&400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ => if is_leap_year(year) { 29 } else { 28 },
+    }
+}
+
+fn parse_err(s: &str) -> DBError {
+    DBError::ValueParse(String::from(s))
+}
+
+/// Parse a fixed width run of ASCII digits
+fn parse_digits(s: &str, orig: &str) -> Result<i64, DBError> {
+    if s.is_empty() || !s.chars().all(|c| c.is_digit(10)) {
+        return Err(parse_err(orig))
+    }
+
+    s.parse::<i64>().map_err(|_| parse_err(orig))
+}
+
+/// The fixed width formats are sliced by byte offsets, which requires ASCII input
+fn is_ascii(s: &str) -> bool {
+    s.bytes().all(|b| b < 0x80)
+}
+
+/// Parse ISO-8601 date (`YYYY-MM-DD`) into days since epoch.
+pub fn parse_date(s: &str) -> Result<i64, DBError> {
+    if s.len() != 10 || !is_ascii(s) || &s[4..5] != "-" || &s[7..8] != "-" {
+        return Err(parse_err(s))
+    }
+
+    let year = parse_digits(&s[0..4], s)?;
+    let month = parse_digits(&s[5..7], s)? as u32;
+    let day = parse_digits(&s[8..10], s)? as u32;
+
+    if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) {
+        return Err(parse_err(s))
+    }
+
+    Ok(days_from_civil(year, month, day))
+}
+
+/// Parse ISO-8601 timestamp into microseconds since epoch (UTC) and optional offset in minutes.
+///
+/// Accepted form is `YYYY-MM-DD[T ]HH:MM:SS[.ffffff][Z|(+|-)HH:MM]`. Timestamps without an offset
+/// or with the `Z` suffix are UTC, and have no offset (`None`).
+pub fn parse_timestamp(s: &str) -> Result<(i64, Option<i16>), DBError> {
+    if s.len() < 19 || !is_ascii(s) {
+        return Err(parse_err(s))
+    }
+
+    let days = parse_date(&s[0..10]).map_err(|_| parse_err(s))?;
+
+    let sep = &s[10..11];
+    if (sep != "T" && sep != " ") || &s[13..14] != ":" || &s[16..17] != ":" {
+        return Err(parse_err(s))
+    }
+
+    let hour = parse_digits(&s[11..13], s)?;
+    let minute = parse_digits(&s[14..16], s)?;
+    let second = parse_digits(&s[17..19], s)?;
+
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(parse_err(s))
+    }
+
+    let mut rest = &s[19..];
+    let mut fraction = 0;
+
+    if rest.starts_with(".") {
+        let digits = rest[1..].chars().take_while(|c| c.is_digit(10)).count();
+        if digits == 0 || digits > 6 {
+            return Err(parse_err(s))
+        }
+
+        fraction = parse_digits(&rest[1 .. 1 + digits], s)? * 10i64.pow(6 - digits as u32);
+        rest = &rest[1 + digits ..];
+    }
+
+    let offset = match rest {
+        "" | "Z" => None,
+        _ if rest.len() == 6 && &rest[3..4] == ":" => {
+            let hours = parse_digits(&rest[1..3], s)?;
+            let minutes = parse_digits(&rest[4..6], s)?;
+
+            if hours > 23 || minutes > 59 {
+                return Err(parse_err(s))
+            }
+
+            let total = (hours * 60 + minutes) as i16;
+
+            match &rest[0..1] {
+                "+" => Some(total),
+                "-" => Some(-total),
+                _ => return Err(parse_err(s)),
+            }
+        }
+        _ => return Err(parse_err(s)),
+    };
+
+    let local = days * MICROS_PER_DAY
+        + hour * MICROS_PER_HOUR
+        + minute * MICROS_PER_MINUTE
+        + second * MICROS_PER_SECOND
+        + fraction;
+
+    let utc = local - offset.unwrap_or(0) as i64 * MICROS_PER_MINUTE;
+    Ok((utc, offset))
+}
+
+/// Render days since epoch as ISO-8601 date
+pub fn format_date(days: i64) -> String {
+    let (y, m, d) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// Render a timestamp as ISO-8601. When an offset is present the local time is displayed with
+/// the offset suffix, otherwise the time is displayed as UTC (with the `Z` suffix).
+pub fn format_timestamp(micros: i64, offset: Option<i16>) -> String {
+    let local = micros + offset.unwrap_or(0) as i64 * MICROS_PER_MINUTE;
+    let (days, time) = local.div_mod_floor(&MICROS_PER_DAY);
+
+    let hour = time / MICROS_PER_HOUR;
+    let minute = (time % MICROS_PER_HOUR) / MICROS_PER_MINUTE;
+    let second = (time % MICROS_PER_MINUTE) / MICROS_PER_SECOND;
+    let fraction = time % MICROS_PER_SECOND;
+
+    let mut out = format!("{}T{:02}:{:02}:{:02}", format_date(days), hour, minute, second);
+
+    if fraction != 0 {
+        out.push_str(&format!(".{:06}", fraction));
+    }
+
+    match offset {
+        None => out.push('Z'),
+        Some(off) => {
+            let sign = if off < 0 { '-' } else { '+' };
+            let abs = (off as i32).abs();
+            out.push_str(&format!("{}{:02}:{:02}", sign, abs / 60, abs % 60));
+        }
+    }
+
+    out
+}
+
+/// Add months to a date (days since epoch). The day of month is clamped to the length of the
+/// resulting month (Jan 31 + 1 month = Feb 28/29).
+pub fn add_months(days: i64, months: i64) -> i64 {
+    let (y, m, d) = civil_from_days(days);
+    let (year, month) = (y * 12 + m as i64 - 1 + months).div_mod_floor(&12);
+    let month = month as u32 + 1;
+
+    days_from_civil(year, month, min(d, days_in_month(year, month)))
+}
+
+/// Add interval components to a timestamp (microseconds since epoch). Months are added first,
+/// followed by days and microseconds.
+pub fn add_interval(micros: i64, months: i32, days: i32, iv_micros: i64) -> Result<i64, DBError> {
+    let (day, time) = micros.div_mod_floor(&MICROS_PER_DAY);
+    let day = add_months(day, months as i64) + days as i64;
+
+    day.checked_mul(MICROS_PER_DAY)
+        .and_then(|v| v.checked_add(time))
+        .and_then(|v| v.checked_add(iv_micros))
+        .ok_or(DBError::NumericOverflow(String::from("TIMESTAMP + INTERVAL")))
+}
+
+/// Render interval as ISO-8601 duration (`PnYnMnDTnHnMn.nS`). Components may be negative.
+pub fn format_interval(months: i32, days: i32, micros: i64) -> String {
+    let mut out = String::from("P");
+
+    if months / 12 != 0 {
+        out.push_str(&format!("{}Y", months / 12));
+    }
+    if months % 12 != 0 {
+        out.push_str(&format!("{}M", months % 12));
+    }
+    if days != 0 {
+        out.push_str(&format!("{}D", days));
+    }
+
+    if micros != 0 {
+        out.push('T');
+
+        let hours = micros / MICROS_PER_HOUR;
+        let minutes = (micros % MICROS_PER_HOUR) / MICROS_PER_MINUTE;
+        let rem = micros % MICROS_PER_MINUTE;
+
+        if hours != 0 {
+            out.push_str(&format!("{}H", hours));
+        }
+        if minutes != 0 {
+            out.push_str(&format!("{}M", minutes));
+        }
+        if rem != 0 {
+            let sign = if rem < 0 { "-" } else { "" };
+            let (secs, frac) = ((rem / MICROS_PER_SECOND).abs(), (rem % MICROS_PER_SECOND).abs());
+
+            if frac == 0 {
+                out.push_str(&format!("{}{}S", sign, secs));
+            } else {
+                let frac = format!("{:06}", frac);
+                out.push_str(&format!("{}{}.{}S", sign, secs, frac.trim_right_matches('0')));
+            }
+        }
+    }
+
+    if out.len() == 1 {
+        out.push_str("T0S");
+    }
+
+    out
+}
+
+/// Parse `[+-]seconds[.ffffff]` into microseconds
+fn parse_seconds(s: &str, orig: &str) -> Result<i64, DBError> {
+    let (neg, body) = match s.chars().next() {
+        Some('-') => (true, &s[1..]),
+        Some('+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let (int_part, frac_part) = match body.find('.') {
+        Some(pos) => (&body[..pos], &body[pos + 1..]),
+        None => (body, ""),
+    };
+
+    if frac_part.len() > 6 || (int_part.is_empty() && frac_part.is_empty()) {
+        return Err(parse_err(orig))
+    }
+
+    let secs = if int_part.is_empty() { 0 } else { parse_digits(int_part, orig)? };
+    let frac = if frac_part.is_empty() {
+        0
+    } else {
+        parse_digits(frac_part, orig)? * 10i64.pow(6 - frac_part.len() as u32)
+    };
+
+    let out = secs.checked_mul(MICROS_PER_SECOND)
+        .and_then(|v| v.checked_add(frac))
+        .ok_or_else(|| parse_err(orig))?;
+
+    Ok(if neg { -out } else { out })
+}
+
+fn parse_integer(s: &str, orig: &str) -> Result<i64, DBError> {
+    match s.chars().next() {
+        Some('-') => parse_digits(&s[1..], orig).map(|v| -v),
+        Some('+') => parse_digits(&s[1..], orig),
+        _ => parse_digits(s, orig),
+    }
+}
+
+/// Interval components accumulated while parsing
+#[derive(Default)]
+struct IntervalParts {
+    months: i64,
+    days: i64,
+    micros: i64,
+}
+
+impl IntervalParts {
+    fn add(&mut self, unit: &str, value: &str, orig: &str) -> Result<(), DBError> {
+        let checked = |acc: i64, v: i64, mul: i64| {
+            v.checked_mul(mul)
+                .and_then(|v| acc.checked_add(v))
+                .ok_or_else(|| parse_err(orig))
+        };
+
+        match unit {
+            "Y" | "year" | "years" =>
+                self.months = checked(self.months, parse_integer(value, orig)?, 12)?,
+            "M" | "month" | "months" | "mon" | "mons" =>
+                self.months = checked(self.months, parse_integer(value, orig)?, 1)?,
+            "W" | "week" | "weeks" =>
+                self.days = checked(self.days, parse_integer(value, orig)?, 7)?,
+            "D" | "day" | "days" =>
+                self.days = checked(self.days, parse_integer(value, orig)?, 1)?,
+            "TH" | "hour" | "hours" =>
+                self.micros = checked(self.micros, parse_integer(value, orig)?, MICROS_PER_HOUR)?,
+            "TM" | "minute" | "minutes" | "min" | "mins" =>
+                self.micros = checked(self.micros, parse_integer(value, orig)?, MICROS_PER_MINUTE)?,
+            "TS" | "second" | "seconds" | "sec" | "secs" =>
+                self.micros = checked(self.micros, parse_seconds(value, orig)?, 1)?,
+            "millisecond" | "milliseconds" =>
+                self.micros = checked(self.micros, parse_integer(value, orig)?, 1000)?,
+            "microsecond" | "microseconds" =>
+                self.micros = checked(self.micros, parse_integer(value, orig)?, 1)?,
+            _ => return Err(parse_err(orig)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an interval into (months, days, microseconds).
+///
+/// Accepts ISO-8601 durations (`P1Y2M3DT4H5M6.5S`, optionally prefixed by `-`) and SQL style
+/// quantity/unit pairs (`1 day 3 hours`).
+pub fn parse_interval(s: &str) -> Result<(i32, i32, i64), DBError> {
+    let mut parts = IntervalParts::default();
+    let trimmed = s.trim();
+
+    let (neg, iso) = if trimmed.starts_with("-P") {
+        (true, Some(&trimmed[2..]))
+    } else if trimmed.starts_with("P") {
+        (false, Some(&trimmed[1..]))
+    } else {
+        (false, None)
+    };
+
+    if let Some(body) = iso {
+        if body.is_empty() {
+            return Err(parse_err(s))
+        }
+
+        let mut time = false;
+        let mut start = 0;
+
+        for (pos, c) in body.char_indices() {
+            match c {
+                'T' if !time && start == pos => {
+                    time = true;
+                    start = pos + 1;
+                }
+                'Y' | 'M' | 'W' | 'D' | 'H' | 'S' => {
+                    let unit = match (time, c) {
+                        (false, 'H') | (false, 'S') | (true, 'Y') | (true, 'W') | (true, 'D') =>
+                            return Err(parse_err(s)),
+                        (true, 'H') => "TH",
+                        (true, 'M') => "TM",
+                        (true, 'S') => "TS",
+                        (false, 'Y') => "Y",
+                        (false, 'M') => "M",
+                        (false, 'W') => "W",
+                        _ => "D",
+                    };
+
+                    parts.add(unit, &body[start..pos], s)?;
+                    start = pos + 1;
+                }
+                _ => (),
+            }
+        }
+
+        if start != body.len() {
+            return Err(parse_err(s))
+        }
+    } else {
+        let tokens: Vec<&str> = trimmed.split_whitespace().collect();
+        if tokens.is_empty() || tokens.len() % 2 != 0 {
+            return Err(parse_err(s))
+        }
+
+        for pair in tokens.chunks(2) {
+            parts.add(&pair[1].to_lowercase(), pair[0], s)?;
+        }
+    }
+
+    let sign = if neg { -1 } else { 1 };
+    let months = parts.months * sign;
+    let days = parts.days * sign;
+
+    if months < i32::min_value() as i64 || months > i32::max_value() as i64
+        || days < i32::min_value() as i64 || days > i32::max_value() as i64
+    {
+        return Err(parse_err(s))
+    }
+
+    Ok((months as i32, days as i32, parts.micros * sign))
+}
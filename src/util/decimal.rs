@@ -0,0 +1,177 @@
+// vim : set ts=4 sw=4 et :
+
+//! Fixed-point decimal helpers. Decimal values are stored as an unscaled `i128` together with
+//! a precision (total number of digits) and scale (digits after the decimal point) that live in
+//! the column `Attribute`.
+//!
+//! Result precision/scale rules follow the common SQL conventions (SQL Server / Hive), capped at
+//! `MAX_PRECISION`.
+
+use std::cmp::{max, min, Ordering};
+
+use ::error::DBError;
+
+/// Largest number of digits representable in the i128 storage
+pub const MAX_PRECISION: u8 = 38;
+
+/// Default scale used for division results
+pub const MIN_DIV_SCALE: u8 = 6;
+
+/// 10^exp
+pub fn pow10(exp: u8) -> i128 {
+    let mut out: i128 = 1;
+    for _ in 0..exp {
+        out *= 10;
+    }
+    out
+}
+
+/// Check that the unscaled value fits into `precision` digits
+pub fn fits(value: i128, precision: u8) -> bool {
+    precision >= MAX_PRECISION || (value < pow10(precision) && value > -pow10(precision))
+}
+
+fn overflow(value: i128, scale: u8) -> DBError {
+    DBError::NumericOverflow(format!("DECIMAL {}", format(value, scale)))
+}
+
+/// Change the scale of an unscaled value. Reducing the scale rounds half away from zero.
+pub fn rescale(value: i128, from: u8, to: u8) -> Result<i128, DBError> {
+    if to >= from {
+        value.checked_mul(pow10(to - from))
+            .ok_or_else(|| overflow(value, from))
+    } else {
+        let div = pow10(from - to);
+        let (quot, rem) = (value / div, value % div);
+
+        if rem.abs() * 2 >= div {
+            Ok(if value < 0 { quot - 1 } else { quot + 1 })
+        } else {
+            Ok(quot)
+        }
+    }
+}
+
+/// Rescale the value and verify it fits into the target precision
+pub fn rescale_to(value: i128, from: u8, precision: u8, scale: u8) -> Result<i128, DBError> {
+    let out = rescale(value, from, scale)?;
+
+    if fits(out, precision) {
+        Ok(out)
+    } else {
+        Err(overflow(value, from))
+    }
+}
+
+/// Drop trailing fractional zeros, so numerically equal values have the same representation
+pub fn normalize(mut value: i128, mut scale: u8) -> (i128, u8) {
+    while scale > 0 && value % 10 == 0 {
+        value /= 10;
+        scale -= 1;
+    }
+
+    (value, scale)
+}
+
+/// Numeric comparison of two unscaled values with different scales
+pub fn compare(lhs: i128, lscale: u8, rhs: i128, rscale: u8) -> Ordering {
+    if lscale == rscale {
+        return lhs.cmp(&rhs)
+    }
+
+    let scale = max(lscale, rscale);
+
+    // Overflowing when scaling up means the magnitude is larger than the other side's
+    match (rescale(lhs, lscale, scale), rescale(rhs, rscale, scale)) {
+        (Ok(l), Ok(r)) => l.cmp(&r),
+        (Err(_), _) => if lhs < 0 { Ordering::Less } else { Ordering::Greater },
+        (_, Err(_)) => if rhs < 0 { Ordering::Greater } else { Ordering::Less },
+    }
+}
+
+/// Precision & scale of `a (+|-) b`
+pub fn add_result(p1: u8, s1: u8, p2: u8, s2: u8) -> (u8, u8) {
+    let scale = max(s1, s2);
+    let int_digits = max(p1 - s1, p2 - s2);
+    cap(int_digits as u32 + scale as u32 + 1, scale)
+}
+
+/// Precision & scale of `a * b`
+pub fn mul_result(p1: u8, s1: u8, p2: u8, s2: u8) -> (u8, u8) {
+    cap(p1 as u32 + p2 as u32 + 1, s1 + s2)
+}
+
+/// Precision & scale of `a / b`
+pub fn div_result(p1: u8, s1: u8, p2: u8, s2: u8) -> (u8, u8) {
+    let scale = max(MIN_DIV_SCALE as u32, s1 as u32 + p2 as u32 + 1);
+    cap(p1 as u32 - s1 as u32 + s2 as u32 + scale, min(scale, MAX_PRECISION as u32) as u8)
+}
+
+/// Cap precision at `MAX_PRECISION`; scale is reduced by the same amount so the integral digits
+/// are preserved where possible.
+fn cap(precision: u32, scale: u8) -> (u8, u8) {
+    if precision <= MAX_PRECISION as u32 {
+        (precision as u8, scale)
+    } else {
+        let excess = precision - MAX_PRECISION as u32;
+        let scale = max(scale as i64 - excess as i64, min(scale, MIN_DIV_SCALE) as i64);
+        (MAX_PRECISION, scale as u8)
+    }
+}
+
+/// Parse a decimal literal (`[+-]digits[.digits]`) into unscaled value, precision and scale.
+pub fn parse(s: &str) -> Result<(i128, u8, u8), DBError> {
+    let err = || DBError::ValueParse(String::from(s));
+
+    let (neg, body) = match s.chars().next() {
+        Some('-') => (true, &s[1..]),
+        Some('+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let (int_part, frac_part) = match body.find('.') {
+        Some(pos) => (&body[..pos], &body[pos + 1..]),
+        None => (body, ""),
+    };
+
+    if int_part.is_empty() && frac_part.is_empty() {
+        return Err(err())
+    }
+
+    let mut value: i128 = 0;
+    let mut digits = 0;
+
+    for c in int_part.chars().chain(frac_part.chars()) {
+        let d = c.to_digit(10).ok_or_else(&err)?;
+        value = value * 10 + d as i128;
+
+        if value != 0 {
+            digits += 1;
+        }
+
+        if digits > MAX_PRECISION as usize {
+            return Err(err())
+        }
+    }
+
+    let scale = frac_part.len();
+    if scale > MAX_PRECISION as usize {
+        return Err(err())
+    }
+
+    let precision = max(max(digits, scale), 1);
+    Ok((if neg { -value } else { value }, precision as u8, scale as u8))
+}
+
+/// Render unscaled value with scale as a decimal literal
+pub fn format(value: i128, scale: u8) -> String {
+    let div = pow10(scale);
+    let sign = if value < 0 { "-" } else { "" };
+    let abs = value.abs();
+
+    if scale == 0 {
+        format!("{}{}", sign, abs)
+    } else {
+        format!("{}{}.{:0width$}", sign, abs / div, abs % div, width = scale as usize)
+    }
+}
@@ -0,0 +1,76 @@
+// vim : set ts=4 sw=4 et :
+
+//! Little endian encoding of integers into byte buffers, and a bounds checked reader for decoding
+//! them. Reading past the end of the data is a `DBError::CorruptData` error.
+
+use ::error::DBError;
+
+pub fn put_u8(out: &mut Vec<u8>, value: u8) {
+    out.push(value);
+}
+
+pub fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+}
+
+pub fn put_u32(out: &mut Vec<u8>, value: u32) {
+    for shift in 0 .. 4 {
+        out.push((value >> (shift * 8)) as u8);
+    }
+}
+
+pub fn put_u64(out: &mut Vec<u8>, value: u64) {
+    for shift in 0 .. 8 {
+        out.push((value >> (shift * 8)) as u8);
+    }
+}
+
+/// Reads little endian values from a byte slice, front to back
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { data: data, pos: 0 }
+    }
+
+    /// Number of bytes left
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DBError> {
+        if len > self.remaining() {
+            return Err(DBError::CorruptData(
+                format!("unexpected end of data ({} bytes at offset {})", len, self.pos)))
+        }
+
+        let out = &self.data[self.pos .. self.pos + len];
+        self.pos += len;
+        Ok(out)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DBError> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, DBError> {
+        self.read_bytes(2).map(|bytes| bytes[0] as u16 | (bytes[1] as u16) << 8)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DBError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(bytes.iter().rev().fold(0, |acc, byte| acc << 8 | *byte as u32))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, DBError> {
+        let bytes = self.read_bytes(8)?;
+        Ok(bytes.iter().rev().fold(0, |acc, byte| acc << 8 | *byte as u64))
+    }
+}
@@ -0,0 +1,110 @@
+use ::block::{RefColumn, column_value};
+use ::error::DBError;
+use ::row::RowOffset;
+use ::types::{self, Value};
+
+/// Trait for reading column row values into rust native types, the read side of `ValueSetter`.
+///
+/// Only `Option<T>` getters accept NULL values. Borrowed values (`&str`, `&[u8]`) point into the
+/// column.
+pub trait ValueGetter<'a>: Sized {
+    /// Convert a value, `None` if the value is NULL or of another type
+    fn from_value(value: Value<'a>) -> Option<Self>;
+}
+
+/// Typed value of a single row of a column (see `column_value`). Reading a NULL row into a
+/// getter that doesn't accept NULLs is an `AttributeNullability` error, reading it into the wrong
+/// type an `AttributeType` error.
+pub fn get_value<'c, T: ValueGetter<'c>>(col: &'c RefColumn<'c>, row: RowOffset)
+    -> Result<T, DBError>
+{
+    let value = column_value(col, row)?;
+    let null = match value { Value::NULL => true, _ => false };
+
+    T::from_value(value).ok_or_else(|| {
+        let name = col.attribute().name.clone();
+        if null { DBError::AttributeNullability(name) } else { DBError::AttributeType(name) }
+    })
+}
+
+macro_rules! value_getter {
+    ($native:ty, $variant:ident) => {
+        impl<'a> ValueGetter<'a> for $native {
+            fn from_value(value: Value<'a>) -> Option<Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+value_getter!(u8, UINT8);
+value_getter!(u16, UINT16);
+value_getter!(u32, UINT32);
+value_getter!(u64, UINT64);
+value_getter!(i8, INT8);
+value_getter!(i16, INT16);
+value_getter!(i32, INT32);
+value_getter!(i64, INT64);
+value_getter!(f32, FLOAT32);
+value_getter!(f64, FLOAT64);
+value_getter!(bool, BOOLEAN);
+value_getter!(types::DateValue, DATE);
+value_getter!(types::TimestampValue, TIMESTAMP);
+value_getter!(types::IntervalValue, INTERVAL);
+value_getter!(types::DecimalValue, DECIMAL);
+value_getter!(types::UuidValue, UUID);
+
+impl<'a> ValueGetter<'a> for &'a str {
+    fn from_value(value: Value<'a>) -> Option<Self> {
+        match value {
+            Value::TEXT(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Slices of bytes are BLOBs or fixed width binary values
+impl<'a> ValueGetter<'a> for &'a [u8] {
+    fn from_value(value: Value<'a>) -> Option<Self> {
+        match value {
+            Value::BLOB(v) | Value::FIXED_BINARY(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> ValueGetter<'a> for String {
+    fn from_value(value: Value<'a>) -> Option<Self> {
+        <&'a str as ValueGetter<'a>>::from_value(value).map(String::from)
+    }
+}
+
+/// NULL values are `Some(None)`
+impl<'a, T: ValueGetter<'a>> ValueGetter<'a> for Option<T> {
+    fn from_value(value: Value<'a>) -> Option<Self> {
+        match value {
+            Value::NULL => Some(None),
+            v => T::from_value(v).map(Some),
+        }
+    }
+}
+
+/// LIST values, every item has to convert
+impl<'a, T: ValueGetter<'a>> ValueGetter<'a> for Vec<T> {
+    fn from_value(value: Value<'a>) -> Option<Self> {
+        match value {
+            Value::LIST(items) => items.into_iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Untyped value, including NULL
+impl<'a> ValueGetter<'a> for Value<'a> {
+    fn from_value(value: Value<'a>) -> Option<Self> {
+        Some(value)
+    }
+}
@@ -0,0 +1,245 @@
+// vim : set ts=4 sw=4 et :
+
+//! HyperLogLog sketches for approximate distinct counts. Sketches merge losslessly, the merged
+//! sketch estimates the distinct count of the union.
+//!
+//! Items are hashed with 64 bit FNV-1a rather than the std `DefaultHasher`, whose output may
+//! change between Rust releases, so persisted sketches stay valid.
+
+use std::borrow::Cow;
+use std::cmp;
+use std::hash::{Hash, Hasher};
+
+use ::error::DBError;
+
+/// Default precision, 4096 registers with a standard error of about 1.6%
+pub const DEFAULT_PRECISION: u8 = 12;
+
+pub const MIN_PRECISION: u8 = 4;
+pub const MAX_PRECISION: u8 = 16;
+
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// 64 bit FNV-1a. Integers are hashed little endian and `usize` as 64 bits, the same input
+/// hashes the same on every platform.
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher {
+        FnvHasher(FNV_OFFSET)
+    }
+}
+
+impl FnvHasher {
+    fn write_le(&mut self, value: u64, bytes: usize) {
+        for shift in 0 .. bytes {
+            self.write(&[(value >> (shift * 8)) as u8]);
+        }
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.write_le(value as u64, 2)
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write_le(value as u64, 4)
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_le(value, 8)
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_le(value as u64, 8)
+    }
+}
+
+/// FNV's high bits are poorly mixed for short inputs, the sketch uses them for the register index
+fn finalize(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    hash ^ (hash >> 33)
+}
+
+/// Distinct count sketch
+#[derive(Clone, PartialEq, Debug)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> HyperLogLog {
+        HyperLogLog::new(DEFAULT_PRECISION)
+    }
+}
+
+impl HyperLogLog {
+    /// Empty sketch with `2 ^ precision` registers. Panics if the precision isn't within
+    /// `MIN_PRECISION` and `MAX_PRECISION`.
+    pub fn new(precision: u8) -> HyperLogLog {
+        assert!(precision >= MIN_PRECISION && precision <= MAX_PRECISION,
+                "HyperLogLog precision {}", precision);
+
+        HyperLogLog { precision: precision, registers: vec![0; 1 << precision] }
+    }
+
+    /// Sketch from registers, eg. deserialized ones. The number of registers has to be a power of
+    /// two within the supported precisions.
+    pub fn from_registers(registers: Vec<u8>) -> Result<HyperLogLog, DBError> {
+        let len = registers.len();
+        let precision = len.trailing_zeros() as u8;
+
+        if !len.is_power_of_two() || precision < MIN_PRECISION || precision > MAX_PRECISION {
+            return Err(DBError::CorruptData(format!("{} HyperLogLog registers", len)))
+        }
+
+        if registers.iter().any(|r| *r > 64 - precision + 1) {
+            return Err(DBError::CorruptData(String::from("HyperLogLog register out of range")))
+        }
+
+        Ok(HyperLogLog { precision: precision, registers: registers })
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        let mut hasher = FnvHasher::default();
+        item.hash(&mut hasher);
+        self.insert_hash(hasher.finish())
+    }
+
+    /// Add an item by its (`FnvHasher`) hash
+    pub fn insert_hash(&mut self, hash: u64) {
+        let hash = finalize(hash);
+        let index = (hash >> (64 - self.precision)) as usize;
+        // Leading zeros of the remaining bits, with a sentinel bit for an all zero remainder
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Add the items of another sketch. Merging sketches of different precisions reduces the
+    /// result to the lower one.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.precision < self.precision {
+            *self = self.reduce(other.precision);
+        }
+
+        let other = if other.precision > self.precision {
+            Cow::Owned(other.reduce(self.precision))
+        } else {
+            Cow::Borrowed(other)
+        };
+
+        for (reg, other) in self.registers.iter_mut().zip(&other.registers) {
+            *reg = cmp::max(*reg, *other);
+        }
+    }
+
+    /// The same sketch with fewer registers. The index bits dropped become the leading bits of
+    /// the remainder the rank is counted in.
+    fn reduce(&self, precision: u8) -> HyperLogLog {
+        let dropped = self.precision - precision;
+        let mut out = HyperLogLog::new(precision);
+
+        for (index, reg) in self.registers.iter().enumerate().filter(|&(_, reg)| *reg > 0) {
+            let bits = index & ((1 << dropped) - 1);
+            let rank = if bits == 0 {
+                *reg + dropped
+            } else {
+                (bits as u64).leading_zeros() as u8 - (64 - dropped) + 1
+            };
+
+            let reduced = &mut out.registers[index >> dropped];
+            *reduced = cmp::max(*reduced, rank);
+        }
+
+        out
+    }
+
+    /// Estimated number of distinct items
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+
+        let sum: f64 = self.registers.iter().map(|r| (-(*r as f64)).exp2()).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+
+        // Linear counting is more accurate for small cardinalities
+        let estimate = if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        };
+
+        estimate.round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv_check_value() {
+        let mut hasher = FnvHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xAF63_DC4C_8601_EC8C);
+    }
+
+    #[test]
+    fn estimate_merge() {
+        let mut lhs = HyperLogLog::default();
+        let mut rhs = HyperLogLog::default();
+        assert_eq!(lhs.estimate(), 0);
+
+        for n in 0 .. 10_000u64 {
+            lhs.insert(&n);
+            rhs.insert(&(n + 5_000));
+        }
+
+        let error = |estimate: u64, actual: f64| (estimate as f64 - actual).abs() / actual;
+        assert!(error(lhs.estimate(), 10_000.0) < 0.05, "estimate {}", lhs.estimate());
+
+        lhs.merge(&rhs);
+        assert!(error(lhs.estimate(), 15_000.0) < 0.05, "estimate {}", lhs.estimate());
+        assert_eq!(HyperLogLog::from_registers(lhs.registers().to_vec()).unwrap(), lhs);
+
+        // Reduced to the coarser precision
+        let mut coarse = HyperLogLog::new(8);
+        coarse.merge(&lhs);
+        assert_eq!(coarse.precision(), 8);
+        assert!(error(coarse.estimate(), 15_000.0) < 0.2, "estimate {}", coarse.estimate());
+    }
+}
@@ -22,4 +22,98 @@ pub fn round_up<T>(n: T, m: T) -> T
     } else {
         (n / m) * m
     }
-}
\ No newline at end of file
+}
+
+/// Native numeric types (`ValueInfo::Store` of integer and float columns) used by numeric casts
+/// and arithmetic. Conversions return `None` when the value doesn't fit the destination type.
+pub trait Numeric: Copy {
+    const FLOAT: bool;
+
+    fn to_i128(self) -> i128;
+    fn to_f64(self) -> f64;
+    fn from_i128(v: i128) -> Option<Self>;
+    /// Integers truncate the fractional part
+    fn from_f64(v: f64) -> Option<Self>;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! numeric_int {
+    ($($t:ty),*) => { $(
+        impl Numeric for $t {
+            const FLOAT: bool = false;
+
+            fn to_i128(self) -> i128 {
+                self as i128
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_i128(v: i128) -> Option<$t> {
+                if v < <$t>::min_value() as i128 || v > <$t>::max_value() as i128 {
+                    None
+                } else {
+                    Some(v as $t)
+                }
+            }
+
+            fn from_f64(v: f64) -> Option<$t> {
+                // max as f64 + 1.0 is exact (a power of 2) even when max itself isn't
+                let v = v.trunc();
+                let (lo, hi) = (<$t>::min_value() as f64, <$t>::max_value() as f64 + 1.0);
+
+                if v.is_nan() || v < lo || v >= hi {
+                    None
+                } else {
+                    Some(v as $t)
+                }
+            }
+
+            fn checked_add(self, rhs: $t) -> Option<$t> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: $t) -> Option<$t> {
+                <$t>::checked_sub(self, rhs)
+            }
+        }
+    )* }
+}
+
+macro_rules! numeric_float {
+    ($($t:ty),*) => { $(
+        impl Numeric for $t {
+            const FLOAT: bool = true;
+
+            fn to_i128(self) -> i128 {
+                self as i128
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_i128(v: i128) -> Option<$t> {
+                Some(v as $t)
+            }
+
+            fn from_f64(v: f64) -> Option<$t> {
+                let out = v as $t;
+                if out.is_infinite() && v.is_finite() { None } else { Some(out) }
+            }
+
+            fn checked_add(self, rhs: $t) -> Option<$t> {
+                Some(self + rhs)
+            }
+
+            fn checked_sub(self, rhs: $t) -> Option<$t> {
+                Some(self - rhs)
+            }
+        }
+    )* }
+}
+
+numeric_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+numeric_float!(f32, f64);
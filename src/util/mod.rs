@@ -1,5 +1,12 @@
 pub mod copy_value;
+pub mod crc32;
+pub mod datetime;
+pub mod decimal;
+pub mod endian;
+pub mod get_value;
+pub mod hll;
 pub mod math;
 
 pub use self::copy_value::ValueSetter;
+pub use self::get_value::ValueGetter;
 
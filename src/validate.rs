@@ -0,0 +1,326 @@
+// vim : set ts=4 sw=4 et :
+
+//! Integrity checks for blocks that come from outside: deserialized data, data imported over FFI
+//! or written through the raw row accessors (`Column::rows_mut`). Kernels and operations assume
+//! their input is valid and don't check it themselves.
+//!
+//! Problems are reported per column and kind (eg. "5 rows not UTF-8, first at row 3"), so a
+//! column full of garbage doesn't produce an error per row.
+
+use std::mem;
+use std::slice;
+use std::str;
+
+use ::bitmaps::Bitmap;
+use ::block::{Encoding, RefColumn, View, stored_row, varlen_at};
+use ::dictionary::DictionaryCode;
+use ::error::DBError;
+use ::row::RowOffset;
+use ::types::{ListEntry, RawData, Type};
+
+/// How thorough `validate` is
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ValidationLevel {
+    /// Row counts, null vectors and encoding metadata (dictionary codes, run ends, VARLEN offsets,
+    /// LIST entries). Doesn't look at the values.
+    Structure,
+    /// Also checks that VARLEN values point into the column's arena and TEXT values are UTF-8.
+    Full,
+}
+
+/// Check the columns of a view, returns every problem found.
+///
+/// `RawData` pointers are only checked for columns that know their storage
+/// (`RefColumn::varlen_storage`), validate the `Block` rather than an alias of it.
+pub fn validate<'v>(view: &'v View<'v>, level: ValidationLevel) -> Result<(), Vec<DBError>> {
+    let mut problems = Vec::new();
+
+    for (pos, attr) in view.schema().iter().enumerate() {
+        let col = match view.column(pos) {
+            Some(col) => col,
+            None => {
+                problems.push(DBError::make_column_unknown_pos(pos));
+                continue
+            }
+        };
+
+        if col.attribute().dtype != attr.dtype {
+            problems.push(DBError::AttributeType(attr.name.clone()));
+            continue
+        }
+
+        validate_column(col, &attr.name, view.rows(), level, &mut problems);
+    }
+
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+fn invalid(path: &str, what: String) -> DBError {
+    DBError::InvalidColumn(format!("{} ({})", path, what))
+}
+
+/// Number of rows and the first one, `None` if there are none
+fn first_and_count<I: Iterator<Item=RowOffset>>(mut rows: I) -> Option<(RowOffset, usize)> {
+    rows.next().map(|first| (first, rows.count() + 1))
+}
+
+/// Check the first `rows` rows of a column. `path` names the column in problem reports.
+fn validate_column<'c>(col: &'c RefColumn<'c>, path: &str, rows: RowOffset,
+                       level: ValidationLevel, out: &mut Vec<DBError>)
+{
+    let attr = col.attribute();
+    let capacity = col.capacity();
+
+    // The remaining checks would read past the column
+    if rows > capacity {
+        out.push(invalid(path, format!("{} rows, capacity {}", rows, capacity)));
+        return
+    }
+
+    if col.encoding() == Encoding::RunLength {
+        let ends = col.run_ends().unwrap_or(&[]);
+        let sorted = ends.windows(2).all(|w| w[0] < w[1]);
+
+        if !sorted || ends.last().map_or(rows > 0, |end| *end < rows) {
+            out.push(invalid(path, String::from("run ends out of order or short")));
+            return
+        }
+    }
+
+    let stored = if rows == 0 { 0 } else { stored_row(col, rows - 1) + 1 };
+    let row_size = col.encoding().row_size(attr);
+    let extra = if col.encoding() == Encoding::Offsets { 1 } else { 0 };
+
+    if col.rows_raw_slice().len() < (stored + extra) * row_size {
+        out.push(invalid(path, format!("row data too short for {} rows", stored)));
+        return
+    }
+
+    let nulls = unsafe { Bitmap::from_raw(col.nulls_ptr(), col.nulls_offset(), stored) };
+
+    if !attr.nullable {
+        if let Some((first, count)) = first_and_count(nulls.iter_ones()) {
+            out.push(DBError::AttributeNullability(
+                format!("{} ({} NULL rows, first at row {})", path, count, first)));
+        }
+    }
+
+    let nulls = if attr.nullable { nulls } else { Bitmap::empty() };
+    let valid = |row: &RowOffset| !nulls.is_set(*row);
+
+    match col.encoding() {
+        Encoding::Dictionary => {
+            let codes = unsafe { typed_rows::<DictionaryCode>(col.rows_raw_slice(), stored) };
+            let size = col.dictionary().map_or(0, |dict| dict.len());
+            let bad = (0 .. stored).filter(&valid).filter(|row| codes[*row] as usize >= size);
+
+            if let Some((first, count)) = first_and_count(bad) {
+                out.push(invalid(path, format!("{} dictionary codes out of range, first at row {}",
+                                               count, first)));
+                return
+            }
+        }
+        Encoding::Offsets => {
+            let offsets = unsafe { typed_rows::<u64>(col.rows_raw_slice(), stored + 1) };
+            let len = col.varlen_data().map_or(0, |data| data.len()) as u64;
+
+            if offsets.windows(2).any(|w| w[0] > w[1]) || offsets[stored] > len {
+                out.push(invalid(path, String::from("value offsets out of order or bounds")));
+                return
+            }
+        }
+        _ => {},
+    }
+
+    match attr.dtype {
+        Type::TEXT | Type::BLOB if level == ValidationLevel::Full => {
+            validate_varlen(col, path, stored, &valid, out)
+        }
+        Type::LIST => {
+            let entries = unsafe { typed_rows::<ListEntry>(col.rows_raw_slice(), stored) };
+            let element = &attr.children[0];
+            let elements = match col.child(0) {
+                Some(elements) => elements,
+                None => {
+                    out.push(DBError::AttributeMissing(format!("{}.{}", path, element.name)));
+                    return
+                }
+            };
+
+            // Entries are relative to the original element column, elements past the ones in use
+            // aren't initialized
+            let base = col.list_base();
+            let element_rows = col.element_rows();
+            let end = |row: &RowOffset| entries[*row].offset as usize + entries[*row].len as usize;
+            let bad = (0 .. stored).filter(&valid)
+                .filter(|row| (entries[*row].offset as usize) < base
+                    || end(row) > base + element_rows);
+
+            if let Some((first, count)) = first_and_count(bad) {
+                out.push(invalid(path, format!("{} list entries out of bounds, first at row {}",
+                                               count, first)));
+                return
+            }
+
+            let used = (0 .. stored).filter(&valid).map(|row| end(&row) - base).max().unwrap_or(0);
+            validate_column(elements, &format!("{}.{}", path, element.name), used, level, out);
+        }
+        Type::STRUCT => {
+            for (pos, field) in attr.children.iter().enumerate() {
+                match col.child(pos) {
+                    Some(child) =>
+                        validate_column(child, &format!("{}.{}", path, field.name), rows, level,
+                                        out),
+                    None => out.push(DBError::AttributeMissing(format!("{}.{}", path, field.name))),
+                }
+            }
+        }
+        _ => {},
+    }
+}
+
+/// `RawData` pointers of plain columns have to point into the column's storage, TEXT values
+/// have to be UTF-8. Values outside of the storage aren't read.
+fn validate_varlen<'c, F>(col: &'c RefColumn<'c>, path: &str, stored: RowOffset, valid: &F,
+                          out: &mut Vec<DBError>)
+    where F: Fn(&RowOffset) -> bool
+{
+    let mut outside = Vec::new();
+
+    if col.encoding() == Encoding::Plain {
+        if let Some(storage) = col.varlen_storage() {
+            let values = unsafe { typed_rows::<RawData>(col.rows_raw_slice(), stored) };
+
+            outside = (0 .. stored).filter(valid)
+                .filter(|row| !storage.iter().any(|chunk| contains(chunk, &values[*row])))
+                .collect();
+        }
+    }
+
+    if let Some((first, count)) = first_and_count(outside.iter().cloned()) {
+        out.push(invalid(path, format!("{} values outside of the column arena, first at row {}",
+                                       count, first)));
+    }
+
+    if col.attribute().dtype != Type::TEXT {
+        return
+    }
+
+    let bad = (0 .. stored).filter(valid)
+        .filter(|row| outside.binary_search(row).is_err())
+        .filter(|row| str::from_utf8(varlen_at(col, *row)).is_err());
+
+    if let Some((first, count)) = first_and_count(bad) {
+        out.push(DBError::ValueParse(
+            format!("{} ({} values not UTF-8, first at row {})", path, count, first)));
+    }
+}
+
+/// Value bytes are within the chunk. Compares addresses only, the value isn't read.
+fn contains(chunk: &[u8], value: &RawData) -> bool {
+    let start = chunk.as_ptr() as usize;
+    let data = value.data as usize;
+
+    data >= start && data <= start + chunk.len() && value.size <= start + chunk.len() - data
+}
+
+/// Row data is allocated with (at least) `MIN_ALIGN` alignment. The length was checked by the
+/// caller.
+unsafe fn typed_rows<T>(raw: &[u8], rows: RowOffset) -> &[T] {
+    debug_assert!(raw.len() >= rows * mem::size_of::<T>());
+    slice::from_raw_parts(raw.as_ptr() as *const T, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator;
+    use schema::{Attribute, Schema};
+    use table::{Table, TableAppender};
+    use types::*;
+
+    static OUTSIDE: &'static [u8] = b"outside";
+
+    #[test]
+    fn validate_values() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("id", false, Type::INT32),
+            Attribute::new("name", true, Type::TEXT),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set(1 as i32).set("one")
+                .add_row().set(2 as i32).set(NULL_VALUE)
+                .add_row().set(3 as i32).set("three")
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        assert!(validate(table.block_ref(), ValidationLevel::Full).is_ok());
+
+        let mut block = table.take().unwrap();
+
+        {
+            let names = block.column_mut(1).unwrap();
+            names.set_varlen(0, b"\xff\xfe").unwrap();
+            names.rows_mut::<Text>().unwrap()[2] =
+                RawData { data: OUTSIDE.as_ptr() as *mut u8, size: OUTSIDE.len() };
+        }
+
+        assert!(validate(&block, ValidationLevel::Structure).is_ok());
+
+        let problems = validate(&block, ValidationLevel::Full).unwrap_err();
+        assert_eq!(problems.len(), 2);
+
+        match (&problems[0], &problems[1]) {
+            (&DBError::InvalidColumn(ref arena), &DBError::ValueParse(ref utf8)) => {
+                assert!(arena.starts_with("name (1 values outside"), "{}", arena);
+                assert!(utf8.starts_with("name (1 values not UTF-8"), "{}", utf8);
+            }
+            _ => panic!("unexpected problems {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn validate_structure() {
+        let schema = Schema::from_vec(vec![
+            Attribute::new("name", true, Type::TEXT),
+            Attribute::list("tags", true, Attribute::new("tag", false, Type::TEXT)),
+        ]).unwrap();
+
+        let mut table = Table::new(&allocator::GLOBAL, &schema, None);
+
+        {
+            let status = TableAppender::new(&mut table)
+                .add_row().set("one").set(vec!["a", "b"])
+                .add_row().set(NULL_VALUE).set(NULL_VALUE)
+                .add_row().set("three").set(vec!["c"])
+                .done();
+
+            assert!(status.is_none(), "Error appending rows {}", status.unwrap());
+        }
+
+        let mut block = table.take().unwrap();
+        assert!(validate(&block, ValidationLevel::Structure).is_ok());
+
+        // Past the 3 elements in use, within the element column's capacity
+        block.column_mut(1).unwrap().rows_mut::<List>().unwrap()[2] =
+            ListEntry { offset: 1, len: 1000 };
+        block.column_mut(0).unwrap().encode_dictionary(3).unwrap();
+        block.column_mut(0).unwrap().rows_raw_mut()[0] = 0xFF;
+
+        let problems = validate(&block, ValidationLevel::Structure).unwrap_err();
+        assert_eq!(problems.len(), 2);
+
+        for problem in &problems {
+            match *problem {
+                DBError::InvalidColumn(_) => {},
+                _ => panic!("unexpected problem {}", problem),
+            }
+        }
+    }
+}